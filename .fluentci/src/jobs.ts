@@ -47,6 +47,7 @@ export const build = async (src = ".") => {
       "-y",
       "build-essential",
       "libasound2-dev",
+      "libopus-dev",
       "protobuf-compiler",
     ])
     .withExec([
@@ -87,6 +88,8 @@ export const build = async (src = ".") => {
         "libasound2-dev:armhf",
         "libdbus-1-dev:armhf",
         "libdbus-1-3:armhf",
+        "libopus0:armhf",
+        "libopus-dev:armhf",
         "libsystemd-dev:armhf",
         "libsystemd0:armhf",
         "libcap2:armhf",
@@ -108,6 +111,8 @@ export const build = async (src = ".") => {
         "libasound2-dev:arm64",
         "libdbus-1-dev:arm64",
         "libdbus-1-3:arm64",
+        "libopus0:arm64",
+        "libopus-dev:arm64",
         "libsystemd-dev:arm64",
         "libsystemd0:arm64",
         "libcap2:arm64",
@@ -131,6 +136,8 @@ export const build = async (src = ".") => {
     .withExec(dpkgExtract("libasound2", "armhf", "/build/sysroot/"))
     .withExec(dpkgExtract("libdbus-1-dev", "armhf", "/build/sysroot/"))
     .withExec(dpkgExtract("libdbus-1-3", "armhf", "/build/sysroot/"))
+    .withExec(dpkgExtract("libopus0", "armhf", "/build/sysroot/"))
+    .withExec(dpkgExtract("libopus-dev", "armhf", "/build/sysroot/"))
     .withExec(dpkgExtract("libsystemd-dev", "armhf", "/build/sysroot/"))
     .withExec(dpkgExtract("libsystemd0", "armhf", "/build/sysroot/"))
     .withExec(dpkgExtract("libcap-dev", "armhf", "/build/sysroot/"))
@@ -152,6 +159,8 @@ export const build = async (src = ".") => {
     .withExec(dpkgExtract("libasound2", "arm64", "/build/sysroot/"))
     .withExec(dpkgExtract("libdbus-1-dev", "arm64", "/build/sysroot/"))
     .withExec(dpkgExtract("libdbus-1-3", "arm64", "/build/sysroot/"))
+    .withExec(dpkgExtract("libopus0", "arm64", "/build/sysroot/"))
+    .withExec(dpkgExtract("libopus-dev", "arm64", "/build/sysroot/"))
     .withExec(dpkgExtract("libsystemd-dev", "arm64", "/build/sysroot/"))
     .withExec(dpkgExtract("libsystemd0", "arm64", "/build/sysroot/"))
     .withExec(dpkgExtract("libcap-dev", "arm64", "/build/sysroot/"))
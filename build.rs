@@ -11,8 +11,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             &[
                 "proto/objects/v1alpha1/category.proto",
                 "proto/objects/v1alpha1/station.proto",
+                "proto/objects/v1alpha1/alarm.proto",
                 "proto/tunein/v1alpha1/browse.proto",
                 "proto/tunein/v1alpha1/playback.proto",
+                "proto/tunein/v1alpha1/alarm.proto",
             ],
             &["proto"],
         )?;
@@ -7,7 +7,7 @@ License:        MIT
 
 BuildArch:      x86_64
 
-Requires: alsa-utils, alsa-lib-devel, dbus-libs
+Requires: alsa-utils, alsa-lib-devel, dbus-libs, opus
 
 %description
 Browse and listen to thousands of radio stations across the globe right from your terminal 🌎 📻 🎵✨
@@ -138,6 +138,7 @@
             pkgs.perl
             pkgs.protobuf
             pkgs.dbus
+            pkgs.libopus
           ] ++ lib.optionals pkgs.stdenv.isLinux [
             pkgs.alsa-lib.dev
           ] ++ lib.optionals pkgs.stdenv.isDarwin [
@@ -0,0 +1,440 @@
+//! Alarm clock: wake-up alarms persisted next to `favorites.json` and fired
+//! by the `server` daemon.
+//!
+//! The store is the single source of truth shared by the CLI, the gRPC
+//! service and the GraphQL API. The daemon's scheduler marks the alarm it
+//! is ringing, and only that one can be snoozed. Snoozing only writes
+//! `snoozed_until`; the scheduler re-reads the file whenever it changes,
+//! silences the alarm as soon as it sees the snooze and rings again once it
+//! expires.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Error};
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDateTime, NaiveTime, Utc, Weekday};
+use directories::ProjectDirs;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+/// Volume ramp used when `--ramp` is not given, in seconds.
+pub const DEFAULT_RAMP_SECS: u64 = 180;
+
+/// Snooze length used when the client does not ask for one, in minutes.
+pub const DEFAULT_SNOOZE_MINUTES: u32 = 9;
+
+const DAY_NAMES: [(&str, Weekday); 7] = [
+    ("mon", Weekday::Mon),
+    ("tue", Weekday::Tue),
+    ("wed", Weekday::Wed),
+    ("thu", Weekday::Thu),
+    ("fri", Weekday::Fri),
+    ("sat", Weekday::Sat),
+    ("sun", Weekday::Sun),
+];
+
+/// One persisted alarm.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Alarm {
+    pub id: u32,
+    /// Wall-clock time in `HH:MM` (24 h, local time).
+    pub time: String,
+    /// Lowercase three-letter day names (`mon` … `sun`); empty means every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Station name or id, resolved through `provider` when the alarm fires.
+    pub station: String,
+    pub provider: String,
+    /// Target volume in percent, reached at the end of the ramp.
+    pub volume: f32,
+    /// How long the volume takes to ramp up from 0 to `volume`.
+    pub ramp_secs: u64,
+    /// Local sound file played (looped) when the stream fails to start.
+    #[serde(default)]
+    pub fallback: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Unix timestamp the alarm is snoozed until.
+    #[serde(default)]
+    pub snoozed_until: Option<i64>,
+    /// Set by the daemon while the alarm is on air.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ringing: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Alarm {
+    pub fn ramp(&self) -> Duration {
+        Duration::from_secs(self.ramp_secs)
+    }
+
+    /// Whether a snooze is currently holding the alarm quiet.
+    pub fn is_snoozed(&self, now_ts: i64) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now_ts)
+    }
+
+    /// The first scheduled ring strictly after `after` (local wall clock).
+    pub fn next_occurrence(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = parse_time(&self.time).ok()?;
+        let days = parse_days(&self.days).ok()?;
+        (0..=7)
+            .map(|offset| after.date() + ChronoDuration::days(offset))
+            .filter(|date| days.is_empty() || days.contains(&date.weekday()))
+            .map(|date| date.and_time(time))
+            .find(|candidate| *candidate > after)
+    }
+
+    /// Whether the alarm should ring for a scheduler tick covering
+    /// `(last, now]`: either its scheduled time or its snooze expiry falls
+    /// in that window. `last_ts`/`now_ts` are the same instants as unix
+    /// timestamps, used for the snooze.
+    pub fn is_due(
+        &self,
+        last: NaiveDateTime,
+        now: NaiveDateTime,
+        last_ts: i64,
+        now_ts: i64,
+    ) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let scheduled = self.next_occurrence(last).is_some_and(|next| next <= now);
+        let snooze_expired = self
+            .snoozed_until
+            .is_some_and(|until| until > last_ts && until <= now_ts);
+        (scheduled && !self.is_snoozed(now_ts)) || snooze_expired
+    }
+}
+
+/// File-backed alarm store.
+pub struct AlarmStore {
+    path: PathBuf,
+    alarms: Vec<Alarm>,
+}
+
+impl AlarmStore {
+    /// Load alarms from disk, falling back to an empty list when the file
+    /// does not exist or is corrupted.
+    pub fn load() -> Result<Self, Error> {
+        let path = alarms_path()?;
+
+        let alarms = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<Vec<Alarm>>(&content) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    eprintln!("warning: alarms file corrupted ({}), starting fresh", err);
+                    Vec::new()
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(Error::from(err).context("failed to read alarms file")),
+        };
+
+        Ok(Self { path, alarms })
+    }
+
+    /// When the alarms file was last written, `None` when it doesn't exist
+    /// (yet) or can't be read.
+    pub fn modified() -> Option<SystemTime> {
+        let path = alarms_path().ok()?;
+        fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    pub fn all(&self) -> &[Alarm] {
+        &self.alarms
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Alarm> {
+        self.alarms.iter().find(|alarm| alarm.id == id)
+    }
+
+    /// Validate and persist a new alarm, assigning it the next free id.
+    pub fn add(&mut self, mut alarm: Alarm) -> Result<Alarm, Error> {
+        parse_time(&alarm.time)?;
+        parse_days(&alarm.days)?;
+        alarm.id = self.alarms.iter().map(|a| a.id).max().unwrap_or(0) + 1;
+        self.alarms.push(alarm.clone());
+        self.save()?;
+        Ok(alarm)
+    }
+
+    /// Remove an alarm. Returns whether it existed.
+    pub fn remove(&mut self, id: u32) -> Result<bool, Error> {
+        let initial_len = self.alarms.len();
+        self.alarms.retain(|alarm| alarm.id != id);
+        let removed = self.alarms.len() != initial_len;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Snooze a ringing alarm for `minutes` from `now_ts`, returning the
+    /// updated entry.
+    pub fn snooze(&mut self, id: u32, minutes: u32, now_ts: i64) -> Result<Alarm, Error> {
+        let alarm = self
+            .alarms
+            .iter_mut()
+            .find(|alarm| alarm.id == id)
+            .ok_or_else(|| anyhow!("no alarm with id {}", id))?;
+        if !alarm.ringing {
+            return Err(anyhow!("alarm {} is not ringing", id));
+        }
+        alarm.ringing = false;
+        alarm.snoozed_until = Some(now_ts + i64::from(minutes) * 60);
+        let alarm = alarm.clone();
+        self.save()?;
+        Ok(alarm)
+    }
+
+    /// Mark `id` as the alarm on air, or none. Only writes the file when
+    /// that changes.
+    pub fn mark_ringing(&mut self, id: Option<u32>) -> Result<(), Error> {
+        let mut changed = false;
+        for alarm in &mut self.alarms {
+            let ringing = Some(alarm.id) == id;
+            changed |= alarm.ringing != ringing;
+            alarm.ringing = ringing;
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let serialized = serde_json::to_string_pretty(&self.alarms)
+            .context("failed to serialize alarms list")?;
+        ensure_parent(&self.path)?;
+        fs::write(&self.path, serialized).context("failed to write alarms file")
+    }
+}
+
+/// `tunein alarm <HH:MM> --station ...`: schedule a new alarm.
+pub fn add(alarm: Alarm) -> Result<(), Error> {
+    let mut store = AlarmStore::load()?;
+    let alarm = store.add(alarm)?;
+    println!("Alarm {} set for {}", alarm.id, alarm.time.bright_green());
+    if let Some(next) = alarm.next_occurrence(Local::now().naive_local()) {
+        println!("Next ring: {}", next.format("%a %Y-%m-%d %H:%M"));
+    }
+    println!("Alarms ring through the `tunein server` daemon, make sure it is running.");
+    Ok(())
+}
+
+/// `tunein alarm list`
+pub fn list() -> Result<(), Error> {
+    let store = AlarmStore::load()?;
+    if store.all().is_empty() {
+        println!("No alarms set");
+        return Ok(());
+    }
+
+    let now_ts = Utc::now().timestamp();
+    for alarm in store.all() {
+        let days = match alarm.days.is_empty() {
+            true => "every day".to_string(),
+            false => alarm.days.join(","),
+        };
+        let mut line = format!(
+            "{} | {} | {} | {} | volume: {}% | ramp: {}s",
+            alarm.id,
+            alarm.time.bright_green(),
+            days,
+            alarm.station.magenta(),
+            alarm.volume,
+            alarm.ramp_secs
+        );
+        if alarm.is_snoozed(now_ts) {
+            line.push_str(" | snoozed");
+        }
+        if !alarm.enabled {
+            line.push_str(" | disabled");
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// `tunein alarm remove <id>`
+pub fn remove(id: u32) -> Result<(), Error> {
+    let mut store = AlarmStore::load()?;
+    match store.remove(id)? {
+        true => println!("Alarm {} removed", id),
+        false => println!("No alarm with id {}", id),
+    }
+    Ok(())
+}
+
+/// `tunein alarm snooze <id>`
+pub fn snooze(id: u32, minutes: u32) -> Result<(), Error> {
+    let mut store = AlarmStore::load()?;
+    store.snooze(id, minutes, Utc::now().timestamp())?;
+    println!("Alarm {} snoozed for {} minutes", id, minutes);
+    Ok(())
+}
+
+/// Parse `HH:MM` (or `H:MM`) in 24-hour time.
+pub fn parse_time(time: &str) -> Result<NaiveTime, Error> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+        .map_err(|_| anyhow!("invalid alarm time '{}', expected HH:MM", time))
+}
+
+/// Parse day names (`mon`, `Tuesday`, …) into weekdays.
+pub fn parse_days(days: &[String]) -> Result<Vec<Weekday>, Error> {
+    days.iter()
+        .map(|day| {
+            let day = day.trim().to_ascii_lowercase();
+            DAY_NAMES
+                .iter()
+                .find(|(name, _)| day.starts_with(name))
+                .map(|(_, weekday)| *weekday)
+                .ok_or_else(|| anyhow!("invalid day '{}', expected mon…sun", day))
+        })
+        .collect()
+}
+
+/// Normalize a comma-separated `--days` value into the stored form.
+pub fn normalize_days(days: &str) -> Result<Vec<String>, Error> {
+    let raw: Vec<String> = days
+        .split(',')
+        .map(str::trim)
+        .filter(|day| !day.is_empty())
+        .map(String::from)
+        .collect();
+    Ok(parse_days(&raw)?
+        .into_iter()
+        .map(|weekday| {
+            DAY_NAMES
+                .iter()
+                .find(|(_, w)| *w == weekday)
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+fn alarms_path() -> Result<PathBuf, Error> {
+    let dirs = ProjectDirs::from("io", "tunein-cli", "tunein-cli")
+        .ok_or_else(|| Error::msg("unable to determine configuration directory"))?;
+    Ok(dirs.config_dir().join("alarms.json"))
+}
+
+fn ensure_parent(path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create alarms directory")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn alarm(time: &str, days: &[&str]) -> Alarm {
+        Alarm {
+            id: 1,
+            time: time.to_string(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+            station: "s12345".to_string(),
+            provider: "tunein".to_string(),
+            volume: 80.0,
+            ramp_secs: DEFAULT_RAMP_SECS,
+            fallback: None,
+            enabled: true,
+            snoozed_until: None,
+            ringing: false,
+        }
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn next_occurrence_honours_days() {
+        // 2024-01-05 is a Friday.
+        let weekdays = alarm("07:00", &["mon", "tue", "wed", "thu", "fri"]);
+        assert_eq!(
+            weekdays.next_occurrence(at(2024, 1, 5, 6, 0)),
+            Some(at(2024, 1, 5, 7, 0))
+        );
+        // Past Friday's ring, the next one is Monday.
+        assert_eq!(
+            weekdays.next_occurrence(at(2024, 1, 5, 7, 0)),
+            Some(at(2024, 1, 8, 7, 0))
+        );
+
+        let daily = alarm("23:30", &[]);
+        assert_eq!(
+            daily.next_occurrence(at(2024, 1, 6, 23, 45)),
+            Some(at(2024, 1, 7, 23, 30))
+        );
+    }
+
+    #[test]
+    fn due_once_per_window_and_held_by_snooze() {
+        let mut daily = alarm("07:00", &[]);
+        let before = at(2024, 1, 5, 6, 59);
+        let ring = at(2024, 1, 5, 7, 0);
+        let after = at(2024, 1, 5, 7, 1);
+        let ts = |t: NaiveDateTime| t.and_utc().timestamp();
+
+        assert!(daily.is_due(before, ring, ts(before), ts(ring)));
+        assert!(!daily.is_due(ring, after, ts(ring), ts(after)));
+
+        // Snoozed for 9 minutes: quiet at 07:00, rings again at 07:09.
+        daily.snoozed_until = Some(ts(before) + 10 * 60);
+        assert!(!daily.is_due(before, ring, ts(before), ts(ring)));
+        let snooze_end = at(2024, 1, 5, 7, 8);
+        let snooze_tick = at(2024, 1, 5, 7, 9);
+        assert!(daily.is_due(snooze_end, snooze_tick, ts(snooze_end), ts(snooze_tick)));
+
+        daily.snoozed_until = None;
+        daily.enabled = false;
+        assert!(!daily.is_due(before, ring, ts(before), ts(ring)));
+    }
+
+    #[test]
+    fn only_a_ringing_alarm_can_be_snoozed() {
+        let path = std::env::temp_dir().join(format!("tunein-alarms-{}.json", std::process::id()));
+        let mut store = AlarmStore {
+            path: path.clone(),
+            alarms: vec![alarm("07:00", &[])],
+        };
+        let ring = at(2024, 1, 5, 7, 0);
+        let ts = |t: NaiveDateTime| t.and_utc().timestamp();
+
+        // Idle at 06:00: refused, so nothing rings when the "snooze" would
+        // have run out.
+        let idle = at(2024, 1, 5, 6, 0);
+        assert!(store.snooze(1, 9, ts(idle)).is_err());
+        let (from, to) = (at(2024, 1, 5, 6, 9), at(2024, 1, 5, 6, 10));
+        assert!(!store.all()[0].is_due(from, to, ts(from), ts(to)));
+
+        store.mark_ringing(Some(1)).unwrap();
+        let snoozed = store.snooze(1, 9, ts(ring)).unwrap();
+        assert_eq!(snoozed.snoozed_until, Some(ts(ring) + 9 * 60));
+        assert!(!snoozed.ringing);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn parses_times_and_days() {
+        assert!(parse_time("7:05").is_ok());
+        assert!(parse_time("24:00").is_err());
+        assert_eq!(
+            normalize_days("Mon, friday,sun").unwrap(),
+            vec!["mon", "fri", "sun"]
+        );
+        assert!(normalize_days("funday").is_err());
+    }
+}
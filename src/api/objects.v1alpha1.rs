@@ -46,3 +46,26 @@ pub struct Category {
     #[prost(message, repeated, tag = "3")]
     pub stations: ::prost::alloc::vec::Vec<Station>,
 }
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Alarm {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(string, tag = "2")]
+    pub time: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "3")]
+    pub days: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, tag = "4")]
+    pub station: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub provider: ::prost::alloc::string::String,
+    #[prost(float, tag = "6")]
+    pub volume: f32,
+    #[prost(uint64, tag = "7")]
+    pub ramp_secs: u64,
+    #[prost(string, optional, tag = "8")]
+    pub fallback: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(bool, tag = "9")]
+    pub enabled: bool,
+    #[prost(int64, optional, tag = "10")]
+    pub snoozed_until: ::core::option::Option<i64>,
+}
@@ -534,6 +534,91 @@ pub struct PlayRequest {
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct PlayResponse {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetPlaybackStatsRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PlaybackStats {
+    /// idle, connecting, buffering, playing or paused.
+    #[prost(string, tag = "1")]
+    pub phase: ::prost::alloc::string::String,
+    /// Progress towards the prebuffer threshold in percent, set while buffering.
+    #[prost(uint32, optional, tag = "2")]
+    pub buffering_percent: ::core::option::Option<u32>,
+    #[prost(string, optional, tag = "3")]
+    pub station: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "4")]
+    pub codec: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "5")]
+    pub sample_rate: u32,
+    #[prost(uint32, tag = "6")]
+    pub channels: u32,
+    #[prost(uint64, tag = "7")]
+    pub bytes_received: u64,
+    #[prost(uint32, tag = "8")]
+    pub throughput_kbps: u32,
+    #[prost(uint64, tag = "9")]
+    pub underruns: u64,
+    #[prost(uint64, tag = "10")]
+    pub decode_errors: u64,
+    /// Read-ahead buffer fill in percent.
+    #[prost(uint32, tag = "11")]
+    pub buffer_fill_percent: u32,
+    /// Decoded layout, e.g. "stereo" or "5.1 → stereo" when downmixed.
+    #[prost(string, tag = "12")]
+    pub channel_layout: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetPlaybackStatsResponse {
+    #[prost(message, optional, tag = "1")]
+    pub stats: ::core::option::Option<PlaybackStats>,
+}
+/// Night mode: a compressor followed by a brick-wall limiter, after the EQ.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct Dynamics {
+    #[prost(bool, tag = "1")]
+    pub night_mode: bool,
+    /// Compressor threshold in dBFS.
+    #[prost(float, tag = "2")]
+    pub threshold_db: f32,
+    /// Compressor ratio, e.g. 4 = 4:1.
+    #[prost(float, tag = "3")]
+    pub ratio: f32,
+    #[prost(float, tag = "4")]
+    pub attack_ms: f32,
+    #[prost(float, tag = "5")]
+    pub release_ms: f32,
+    /// Limiter ceiling in dBFS.
+    #[prost(float, tag = "6")]
+    pub ceiling_db: f32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetDynamicsRequest {}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetDynamicsResponse {
+    #[prost(message, optional, tag = "1")]
+    pub dynamics: ::core::option::Option<Dynamics>,
+}
+/// Unset fields keep their current value.
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SetDynamicsRequest {
+    #[prost(bool, optional, tag = "1")]
+    pub night_mode: ::core::option::Option<bool>,
+    #[prost(float, optional, tag = "2")]
+    pub threshold_db: ::core::option::Option<f32>,
+    #[prost(float, optional, tag = "3")]
+    pub ratio: ::core::option::Option<f32>,
+    #[prost(float, optional, tag = "4")]
+    pub attack_ms: ::core::option::Option<f32>,
+    #[prost(float, optional, tag = "5")]
+    pub release_ms: ::core::option::Option<f32>,
+    #[prost(float, optional, tag = "6")]
+    pub ceiling_db: ::core::option::Option<f32>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SetDynamicsResponse {
+    #[prost(message, optional, tag = "1")]
+    pub dynamics: ::core::option::Option<Dynamics>,
+}
 /// Generated client implementations.
 pub mod playback_service_client {
     #![allow(
@@ -673,6 +758,63 @@ pub mod playback_service_client {
             ));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn get_playback_stats(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetPlaybackStatsRequest>,
+        ) -> std::result::Result<tonic::Response<super::GetPlaybackStatsResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/tunein.v1alpha1.PlaybackService/GetPlaybackStats",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "tunein.v1alpha1.PlaybackService",
+                "GetPlaybackStats",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_dynamics(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetDynamicsRequest>,
+        ) -> std::result::Result<tonic::Response<super::GetDynamicsResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/tunein.v1alpha1.PlaybackService/GetDynamics",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "tunein.v1alpha1.PlaybackService",
+                "GetDynamics",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn set_dynamics(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SetDynamicsRequest>,
+        ) -> std::result::Result<tonic::Response<super::SetDynamicsResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/tunein.v1alpha1.PlaybackService/SetDynamics",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "tunein.v1alpha1.PlaybackService",
+                "SetDynamics",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -700,6 +842,18 @@ pub mod playback_service_server {
             &self,
             request: tonic::Request<super::PlayOrPauseRequest>,
         ) -> std::result::Result<tonic::Response<super::PlayOrPauseResponse>, tonic::Status>;
+        async fn get_playback_stats(
+            &self,
+            request: tonic::Request<super::GetPlaybackStatsRequest>,
+        ) -> std::result::Result<tonic::Response<super::GetPlaybackStatsResponse>, tonic::Status>;
+        async fn get_dynamics(
+            &self,
+            request: tonic::Request<super::GetDynamicsRequest>,
+        ) -> std::result::Result<tonic::Response<super::GetDynamicsResponse>, tonic::Status>;
+        async fn set_dynamics(
+            &self,
+            request: tonic::Request<super::SetDynamicsRequest>,
+        ) -> std::result::Result<tonic::Response<super::SetDynamicsResponse>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct PlaybackServiceServer<T> {
@@ -891,6 +1045,130 @@ pub mod playback_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/tunein.v1alpha1.PlaybackService/GetPlaybackStats" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetPlaybackStatsSvc<T: PlaybackService>(pub Arc<T>);
+                    impl<T: PlaybackService>
+                        tonic::server::UnaryService<super::GetPlaybackStatsRequest>
+                        for GetPlaybackStatsSvc<T>
+                    {
+                        type Response = super::GetPlaybackStatsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetPlaybackStatsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as PlaybackService>::get_playback_stats(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetPlaybackStatsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/tunein.v1alpha1.PlaybackService/GetDynamics" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetDynamicsSvc<T: PlaybackService>(pub Arc<T>);
+                    impl<T: PlaybackService> tonic::server::UnaryService<super::GetDynamicsRequest>
+                        for GetDynamicsSvc<T>
+                    {
+                        type Response = super::GetDynamicsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetDynamicsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as PlaybackService>::get_dynamics(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetDynamicsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/tunein.v1alpha1.PlaybackService/SetDynamics" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetDynamicsSvc<T: PlaybackService>(pub Arc<T>);
+                    impl<T: PlaybackService> tonic::server::UnaryService<super::SetDynamicsRequest>
+                        for SetDynamicsSvc<T>
+                    {
+                        type Response = super::SetDynamicsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SetDynamicsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as PlaybackService>::set_dynamics(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SetDynamicsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => Box::pin(async move {
                     let mut response = http::Response::new(empty_body());
                     let headers = response.headers_mut();
@@ -925,3 +1203,357 @@ pub mod playback_service_server {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ListAlarmsRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListAlarmsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub alarms: ::prost::alloc::vec::Vec<super::super::objects::v1alpha1::Alarm>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct SnoozeAlarmRequest {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(uint32, optional, tag = "2")]
+    pub minutes: ::core::option::Option<u32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SnoozeAlarmResponse {
+    #[prost(message, optional, tag = "1")]
+    pub alarm: ::core::option::Option<super::super::objects::v1alpha1::Alarm>,
+}
+/// Generated client implementations.
+pub mod alarm_service_client {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value
+    )]
+    use tonic::codegen::http::Uri;
+    use tonic::codegen::*;
+    #[derive(Debug, Clone)]
+    pub struct AlarmServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl AlarmServiceClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> AlarmServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + std::marker::Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + std::marker::Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> AlarmServiceClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
+                Into<StdError> + std::marker::Send + std::marker::Sync,
+        {
+            AlarmServiceClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        pub async fn list_alarms(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListAlarmsRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListAlarmsResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/tunein.v1alpha1.AlarmService/ListAlarms");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "tunein.v1alpha1.AlarmService",
+                "ListAlarms",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn snooze_alarm(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SnoozeAlarmRequest>,
+        ) -> std::result::Result<tonic::Response<super::SnoozeAlarmResponse>, tonic::Status>
+        {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path =
+                http::uri::PathAndQuery::from_static("/tunein.v1alpha1.AlarmService/SnoozeAlarm");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new(
+                "tunein.v1alpha1.AlarmService",
+                "SnoozeAlarm",
+            ));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod alarm_service_server {
+    #![allow(
+        unused_variables,
+        dead_code,
+        missing_docs,
+        clippy::wildcard_imports,
+        clippy::let_unit_value
+    )]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with AlarmServiceServer.
+    #[async_trait]
+    pub trait AlarmService: std::marker::Send + std::marker::Sync + 'static {
+        async fn list_alarms(
+            &self,
+            request: tonic::Request<super::ListAlarmsRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListAlarmsResponse>, tonic::Status>;
+        async fn snooze_alarm(
+            &self,
+            request: tonic::Request<super::SnoozeAlarmRequest>,
+        ) -> std::result::Result<tonic::Response<super::SnoozeAlarmResponse>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct AlarmServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> AlarmServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for AlarmServiceServer<T>
+    where
+        T: AlarmService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/tunein.v1alpha1.AlarmService/ListAlarms" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListAlarmsSvc<T: AlarmService>(pub Arc<T>);
+                    impl<T: AlarmService> tonic::server::UnaryService<super::ListAlarmsRequest> for ListAlarmsSvc<T> {
+                        type Response = super::ListAlarmsResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListAlarmsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlarmService>::list_alarms(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListAlarmsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/tunein.v1alpha1.AlarmService/SnoozeAlarm" => {
+                    #[allow(non_camel_case_types)]
+                    struct SnoozeAlarmSvc<T: AlarmService>(pub Arc<T>);
+                    impl<T: AlarmService> tonic::server::UnaryService<super::SnoozeAlarmRequest> for SnoozeAlarmSvc<T> {
+                        type Response = super::SnoozeAlarmResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SnoozeAlarmRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AlarmService>::snooze_alarm(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SnoozeAlarmSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    let mut response = http::Response::new(empty_body());
+                    let headers = response.headers_mut();
+                    headers.insert(
+                        tonic::Status::GRPC_STATUS,
+                        (tonic::Code::Unimplemented as i32).into(),
+                    );
+                    headers.insert(
+                        http::header::CONTENT_TYPE,
+                        tonic::metadata::GRPC_CONTENT_TYPE,
+                    );
+                    Ok(response)
+                }),
+            }
+        }
+    }
+    impl<T> Clone for AlarmServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "tunein.v1alpha1.AlarmService";
+    impl<T> tonic::server::NamedService for AlarmServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
@@ -11,23 +11,28 @@ use std::{
     thread,
     time::{Duration, Instant},
 };
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::mpsc::UnboundedReceiver;
 use tunein_cli::os_media_controls::{self, OsMediaControls};
 
 use crate::{
     decoder::Frame as AudioFrame,
+    dynamics::Dynamics,
     eq_ui::EqPopup,
     extract::get_currently_playing,
     fzf_ui::{FzfOutcome, FzfPopup},
     help_ui::{HelpPopup, Shortcut},
     input::stream_to_matrix,
-    play::SinkCommand,
+    loudness::Loudness,
+    player::{Player, PlayerCommand},
     provider::{radiobrowser::Radiobrowser, tunein::Tunein, Provider},
+    settings::Settings,
+    stats_ui::StatsPanel,
     theme, tui,
     types::Station,
     visualization::{
-        oscilloscope::Oscilloscope, spectroscope::Spectroscope, vectorscope::Vectorscope,
-        Dimension, DisplayMode, GraphConfig,
+        analyzer::Analyzer, meters::Meters, oscilloscope::Oscilloscope, spectrogram::Spectrogram,
+        spectroscope::Spectroscope, tuner::Tuner, vectorscope::Vectorscope, Dimension, DisplayMode,
+        GraphConfig,
     },
 };
 
@@ -35,18 +40,27 @@ use crate::{
 /// a provider search, so fast typing doesn't hammer the network.
 const FZF_DEBOUNCE: Duration = Duration::from_millis(180);
 
+/// Rows of the station info block, below the title.
+const INFO_ROWS: u16 = 6;
+/// Fewest rows worth drawing a visualization in below the station info.
+/// With fewer, the analyzer moves next to the info instead.
+const MIN_VISUALIZATION_ROWS: u16 = 4;
+
 /// Shortcut table shown by the `?` popup in the player TUI.
 const PLAYER_SHORTCUTS: &[Shortcut] = &[
     ("space", "Play / pause"),
     (
         "tab",
-        "Cycle visualization (oscilloscope/vectorscope/spectroscope/off)",
+        "Cycle visualization (oscilloscope/vectorscope/spectroscope/spectrogram/meters/analyzer/tuner/off)",
     ),
     ("↑ / ↓", "Volume up / down"),
     ("← / →", "Show fewer / more samples"),
     ("e", "Open the equalizer"),
     ("/", "Search stations and switch"),
     ("m", "Mute / unmute"),
+    ("L", "Toggle loudness normalisation"),
+    ("n", "Toggle night mode (compressor/limiter)"),
+    ("i", "Show stream stats"),
     ("s", "Toggle scatter mode"),
     ("h", "Toggle the header UI"),
     ("r", "Toggle reference lines"),
@@ -67,8 +81,13 @@ pub struct State {
     pub genre: String,
     pub description: String,
     pub br: String,
+    /// Decoded channel layout, shown next to the bitrate.
+    pub channels: String,
     /// [`Volume`].
     pub volume: Volume,
+    /// The station started at its remembered volume, rather than carrying
+    /// over the previous station's.
+    pub own_volume: bool,
 }
 
 /// Volume of the player.
@@ -143,6 +162,10 @@ pub enum CurrentDisplayMode {
     Oscilloscope,
     Vectorscope,
     Spectroscope,
+    Spectrogram,
+    Meters,
+    Analyzer,
+    Tuner,
     None,
 }
 
@@ -154,6 +177,10 @@ impl std::str::FromStr for CurrentDisplayMode {
             "Oscilloscope" => Ok(Self::Oscilloscope),
             "Vectorscope" => Ok(Self::Vectorscope),
             "Spectroscope" => Ok(Self::Spectroscope),
+            "Spectrogram" => Ok(Self::Spectrogram),
+            "Meters" => Ok(Self::Meters),
+            "Analyzer" => Ok(Self::Analyzer),
+            "Tuner" => Ok(Self::Tuner),
             "None" => Ok(Self::None),
             _ => Err(InvalidDisplayModeError),
         }
@@ -179,9 +206,14 @@ pub struct App {
     oscilloscope: Oscilloscope,
     vectorscope: Vectorscope,
     spectroscope: Spectroscope,
+    spectrogram: Spectrogram,
+    meters: Meters,
+    analyzer: Analyzer,
+    tuner: Tuner,
     mode: CurrentDisplayMode,
     eq_popup: EqPopup,
     help_popup: HelpPopup,
+    stats_panel: StatsPanel,
     /// `/` fuzzy finder for searching and switching stations.
     fzf_popup: FzfPopup,
     /// The finder's query changed and a search is due once it settles.
@@ -191,6 +223,8 @@ pub struct App {
     /// Set when the user picks a station in the finder; [`Self::run`] returns
     /// it so the caller can reload playback.
     next_station: Option<Station>,
+    /// Volume carried over to the next station picked in the finder.
+    volume: Option<Volume>,
     frame_rx: Receiver<AudioFrame>,
     /// [`OsMediaControls`].
     os_media_controls: Option<OsMediaControls>,
@@ -221,7 +255,7 @@ impl App {
         let graph = GraphConfig {
             axis_color: Color::DarkGray,
             labels_color: theme::PRIMARY,
-            palette: vec![Color::Red, Color::Yellow, Color::Green, Color::Magenta],
+            palette: ui.palette.clone(),
             scale: ui.scale as f64,
             width: source.buffer, // TODO also make bit depth customizable
             samples: source.buffer,
@@ -240,19 +274,29 @@ impl App {
         let oscilloscope = Oscilloscope::from_args(source);
         let vectorscope = Vectorscope::from_args(source);
         let spectroscope = Spectroscope::from_args(source);
+        let spectrogram = Spectrogram::from_args(source);
+        let meters = Meters::from_args(source);
+        let analyzer = Analyzer::from_args(source);
+        let tuner = Tuner::from_args(source);
 
         Self {
             graph,
             oscilloscope,
             vectorscope,
             spectroscope,
+            spectrogram,
+            meters,
+            analyzer,
+            tuner,
             mode,
             eq_popup: EqPopup::new(),
             help_popup: HelpPopup::new(PLAYER_SHORTCUTS),
+            stats_panel: StatsPanel::default(),
             fzf_popup: FzfPopup::new(),
             fzf_dirty: false,
             fzf_last_edit: Instant::now(),
             next_station: None,
+            volume: None,
             channels: source.channels as u8,
             frame_rx,
             os_media_controls,
@@ -262,7 +306,8 @@ impl App {
     }
 }
 
-fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame) {
+/// Draw the title and the station info, the info within `info_width`.
+fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame, info_width: u16) {
     let state = state.lock().unwrap();
     let size = frame.size();
 
@@ -285,7 +330,7 @@ fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame) {
         Rect {
             x: size.x,
             y: size.y + 1,
-            width: size.width,
+            width: info_width,
             height: 1,
         },
         frame,
@@ -298,7 +343,7 @@ fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame) {
             Rect {
                 x: size.x,
                 y: size.y + 2,
-                width: size.width,
+                width: info_width,
                 height: 1,
             },
             frame,
@@ -314,7 +359,7 @@ fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame) {
                 true => size.y + 2,
                 false => size.y + 3,
             },
-            width: size.width,
+            width: info_width,
             height: 1,
         },
         frame,
@@ -328,16 +373,18 @@ fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame) {
                 true => size.y + 3,
                 false => size.y + 4,
             },
-            width: size.width,
+            width: info_width,
             height: 1,
         },
         frame,
     );
     render_line(
         "Bitrate ",
-        &match state.br.is_empty() {
-            true => "Unknown".to_string(),
-            false => format!("{} kbps", &state.br),
+        &match (state.br.is_empty(), state.channels.is_empty()) {
+            (true, true) => "Unknown".to_string(),
+            (true, false) => state.channels.clone(),
+            (false, true) => format!("{} kbps", &state.br),
+            (false, false) => format!("{} kbps • {}", &state.br, &state.channels),
         },
         Rect {
             x: size.x,
@@ -345,17 +392,27 @@ fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame) {
                 true => size.y + 4,
                 false => size.y + 5,
             },
-            width: size.width,
+            width: info_width,
             height: 1,
         },
         frame,
     );
     render_line(
         "Volume ",
-        &if state.volume.is_muted() {
-            format!("{}% muted", state.volume.raw_volume_percent())
-        } else {
-            format!("{}%", state.volume.raw_volume_percent())
+        &{
+            let mut volume = if state.volume.is_muted() {
+                format!("{}% muted", state.volume.raw_volume_percent())
+            } else {
+                format!("{}%", state.volume.raw_volume_percent())
+            };
+            let loudness = Loudness::global();
+            if loudness.is_enabled() {
+                volume.push_str(&format!(" • normalised to {} LUFS", loudness.target_lufs()));
+            }
+            if Dynamics::global().is_enabled() {
+                volume.push_str(" • night mode");
+            }
+            volume
         },
         Rect {
             x: size.x,
@@ -363,7 +420,7 @@ fn render_frame(state: Arc<Mutex<State>>, frame: &mut Frame) {
                 true => size.y + 5,
                 false => size.y + 6,
             },
-            width: size.width,
+            width: info_width,
             height: 1,
         },
         frame,
@@ -389,8 +446,8 @@ impl App {
     pub async fn run(
         &mut self,
         terminal: &mut tui::Tui,
-        mut cmd_rx: UnboundedReceiver<State>,
-        mut sink_cmd_tx: UnboundedSender<SinkCommand>,
+        cmd_rx: &mut UnboundedReceiver<State>,
+        mut player: Player,
         id: &str,
         provider_name: &str,
     ) -> Option<Station> {
@@ -401,7 +458,16 @@ impl App {
             _ => None,
         };
 
-        let new_state = cmd_rx.recv().await.unwrap();
+        let mut new_state = cmd_rx.recv().await.unwrap();
+        // The sink outlives a station switch, and so does its volume, unless
+        // the new station has one of its own.
+        if let Some(volume) = self.volume.take() {
+            if !new_state.own_volume {
+                new_state.volume = volume;
+            }
+        }
+        // Frames mixed while the station was connecting are stale by now.
+        while self.frame_rx.try_recv().is_ok() {}
 
         let now_playing = new_state.now_playing.clone();
         let name = new_state.name.clone();
@@ -456,6 +522,11 @@ impl App {
         const METADATA_UPDATE_INTERVAL: Duration = Duration::from_secs(1); // Check every second
 
         loop {
+            // Errors reported while playing replace the description.
+            while let Ok(state) = cmd_rx.try_recv() {
+                new_state.lock().unwrap().description = state.description;
+            }
+
             let channels = if self.graph.pause {
                 None
             } else {
@@ -463,11 +534,9 @@ impl App {
                     // other thread has closed so application has closed
                     return None;
                 };
-                Some(stream_to_matrix(
-                    audio_frame.data.iter().cloned(),
-                    audio_frame.channels,
-                    1.,
-                ))
+                self.graph.sampling_rate = audio_frame.sample_rate as u32;
+                let (samples, channels) = audio_frame.stereo();
+                Some(stream_to_matrix(samples.iter().cloned(), channels, 1.))
             };
 
             fps += 1;
@@ -486,9 +555,21 @@ impl App {
                         datasets.append(&mut current_display.references(&graph));
                     }
                 }
+                if !matches!(self.mode, CurrentDisplayMode::Spectroscope) {
+                    self.eq_popup.set_spectrum(None);
+                }
                 if let Some((current_display, channels)) = self.current_display_mut().zip(channels)
                 {
-                    datasets.append(&mut current_display.process(&graph, &channels));
+                    let mut processed = current_display.process(&graph, &channels);
+                    // The equalizer popup draws the spectrum behind its
+                    // response curve.
+                    if self.eq_popup.visible
+                        && matches!(self.mode, CurrentDisplayMode::Spectroscope)
+                    {
+                        let spectrum = self.spectroscope.levels_db(&processed);
+                        self.eq_popup.set_spectrum(Some(spectrum));
+                    }
+                    datasets.append(&mut processed);
                 }
                 terminal
                     .draw(|f| {
@@ -501,8 +582,27 @@ impl App {
                             height: 1,
                         };
                         size.height = size.height.saturating_sub(1);
-                        render_frame(new_state.clone(), f);
-                        if let Some(current_display) = self.current_display() {
+                        // Too short for the analyzer below the station info:
+                        // it shares the info rows instead.
+                        let beside = matches!(self.mode, CurrentDisplayMode::Analyzer)
+                            && size.height < INFO_ROWS + 2 + MIN_VISUALIZATION_ROWS;
+                        let info_width = match beside {
+                            true => size.width / 2,
+                            false => size.width,
+                        };
+                        render_frame(new_state.clone(), f, info_width);
+                        if beside {
+                            self.analyzer.render(
+                                &self.graph,
+                                f,
+                                Rect {
+                                    x: size.x + info_width,
+                                    y: size.y + 1,
+                                    width: size.width - info_width,
+                                    height: size.height.saturating_sub(1).min(INFO_ROWS),
+                                },
+                            );
+                        } else if let Some(current_display) = self.current_display() {
                             if self.graph.show_ui {
                                 f.render_widget(
                                     make_header(
@@ -522,10 +622,12 @@ impl App {
                                 size.height -= 8;
                                 size.y += 8;
                             }
-                            let chart = Chart::new(datasets.iter().map(|x| x.into()).collect())
-                                .x_axis(current_display.axis(&self.graph, Dimension::X))
-                                .y_axis(current_display.axis(&self.graph, Dimension::Y));
-                            f.render_widget(chart, size)
+                            if !current_display.render(&self.graph, f, size) {
+                                let chart = Chart::new(datasets.iter().map(|x| x.into()).collect())
+                                    .x_axis(current_display.axis(&self.graph, Dimension::X))
+                                    .y_axis(current_display.axis(&self.graph, Dimension::Y));
+                                f.render_widget(chart, size)
+                            }
                         }
                         f.render_widget(
                             Paragraph::new(PLAYER_STATUS_LINE)
@@ -533,6 +635,7 @@ impl App {
                                 .alignment(Alignment::Center),
                             footer_area,
                         );
+                        self.stats_panel.render(f, &player.stats());
                         self.eq_popup.render(f);
                         self.help_popup.render(f);
                         self.fzf_popup.render(f);
@@ -566,7 +669,7 @@ impl App {
                 .as_mut()
                 .and_then(|os_media_controls| os_media_controls.try_recv_os_event())
             {
-                if self.process_os_media_control_event(event, &new_state, &mut sink_cmd_tx) {
+                if self.process_os_media_control_event(event, &new_state, &mut player) {
                     return None;
                 }
             }
@@ -582,11 +685,12 @@ impl App {
                 let event = event::read().unwrap();
 
                 if self
-                    .process_events(event.clone(), new_state.clone(), &mut sink_cmd_tx)
+                    .process_events(event.clone(), new_state.clone(), &mut player)
                     .unwrap()
                 {
                     // Either the user quit (`next_station` is `None`) or picked
                     // a station in the finder (`Some`).
+                    self.volume = Some(new_state.lock().unwrap().volume.clone());
                     return self.next_station.take();
                 }
                 // The finder swallows keys while open; don't also feed them to
@@ -626,6 +730,10 @@ impl App {
             CurrentDisplayMode::Spectroscope => {
                 Some(&mut self.spectroscope as &mut dyn DisplayMode)
             }
+            CurrentDisplayMode::Spectrogram => Some(&mut self.spectrogram as &mut dyn DisplayMode),
+            CurrentDisplayMode::Meters => Some(&mut self.meters as &mut dyn DisplayMode),
+            CurrentDisplayMode::Analyzer => Some(&mut self.analyzer as &mut dyn DisplayMode),
+            CurrentDisplayMode::Tuner => Some(&mut self.tuner as &mut dyn DisplayMode),
             CurrentDisplayMode::None => None,
         }
     }
@@ -635,15 +743,30 @@ impl App {
             CurrentDisplayMode::Oscilloscope => Some(&self.oscilloscope as &dyn DisplayMode),
             CurrentDisplayMode::Vectorscope => Some(&self.vectorscope as &dyn DisplayMode),
             CurrentDisplayMode::Spectroscope => Some(&self.spectroscope as &dyn DisplayMode),
+            CurrentDisplayMode::Spectrogram => Some(&self.spectrogram as &dyn DisplayMode),
+            CurrentDisplayMode::Meters => Some(&self.meters as &dyn DisplayMode),
+            CurrentDisplayMode::Analyzer => Some(&self.analyzer as &dyn DisplayMode),
+            CurrentDisplayMode::Tuner => Some(&self.tuner as &dyn DisplayMode),
             CurrentDisplayMode::None => None,
         }
     }
 
+    /// Remember the `s`, `h` and `r` toggles for the next `play`.
+    fn save_scope_toggles(&self) {
+        let mut settings = Settings::load();
+        settings.scope_scatter = self.graph.scatter;
+        settings.scope_show_ui = self.graph.show_ui;
+        settings.scope_references = self.graph.references;
+        if let Err(err) = settings.save() {
+            eprintln!("warning: failed to save settings: {}", err);
+        }
+    }
+
     fn process_events(
         &mut self,
         event: Event,
         state: Arc<Mutex<State>>,
-        sink_cmd_tx: &mut UnboundedSender<SinkCommand>,
+        player: &mut Player,
     ) -> Result<bool, io::Error> {
         let mut quit = false;
 
@@ -685,10 +808,10 @@ impl App {
                 KeyCode::Up => {
                     // Volume only — must not touch the scope scale, or changing
                     // the volume would visibly rescale the visualization.
-                    raise_volume(&state, self.os_media_controls.as_mut(), sink_cmd_tx);
+                    raise_volume(&state, self.os_media_controls.as_mut(), player);
                 }
                 KeyCode::Down => {
-                    lower_volume(&state, self.os_media_controls.as_mut(), sink_cmd_tx);
+                    lower_volume(&state, self.os_media_controls.as_mut(), player);
                 }
                 KeyCode::Right => update_value_i(
                     &mut self.graph.samples,
@@ -705,22 +828,34 @@ impl App {
                     0..self.graph.width * 2,
                 ),
                 KeyCode::Char('q') => quit = true,
-                KeyCode::Char(' ') => toggle_play_pause(
-                    &mut self.graph,
-                    self.os_media_controls.as_mut(),
-                    sink_cmd_tx,
-                ),
+                KeyCode::Char(' ') => {
+                    toggle_play_pause(&mut self.graph, self.os_media_controls.as_mut(), player)
+                }
                 KeyCode::Char('e') => self.eq_popup.toggle(),
                 KeyCode::Char('?') => self.help_popup.toggle(),
+                KeyCode::Char('i') => self.stats_panel.toggle(),
                 KeyCode::Char('/') => {
                     self.fzf_popup.open(Vec::new());
                     self.fzf_dirty = false;
                 }
-                KeyCode::Char('s') => self.graph.scatter = !self.graph.scatter,
-                KeyCode::Char('h') => self.graph.show_ui = !self.graph.show_ui,
-                KeyCode::Char('r') => self.graph.references = !self.graph.references,
-                KeyCode::Char('m') => {
-                    mute_volume(&state, self.os_media_controls.as_mut(), sink_cmd_tx)
+                KeyCode::Char('s') => {
+                    self.graph.scatter = !self.graph.scatter;
+                    self.save_scope_toggles();
+                }
+                KeyCode::Char('h') => {
+                    self.graph.show_ui = !self.graph.show_ui;
+                    self.save_scope_toggles();
+                }
+                KeyCode::Char('r') => {
+                    self.graph.references = !self.graph.references;
+                    self.save_scope_toggles();
+                }
+                KeyCode::Char('m') => mute_volume(&state, self.os_media_controls.as_mut(), player),
+                KeyCode::Char('L') => {
+                    Loudness::global().toggle();
+                }
+                KeyCode::Char('n') => {
+                    Dynamics::global().toggle();
                 }
                 KeyCode::Esc => {
                     self.graph.samples = self.graph.width;
@@ -739,6 +874,18 @@ impl App {
                             self.mode = CurrentDisplayMode::Spectroscope;
                         }
                         CurrentDisplayMode::Spectroscope => {
+                            self.mode = CurrentDisplayMode::Spectrogram;
+                        }
+                        CurrentDisplayMode::Spectrogram => {
+                            self.mode = CurrentDisplayMode::Meters;
+                        }
+                        CurrentDisplayMode::Meters => {
+                            self.mode = CurrentDisplayMode::Analyzer;
+                        }
+                        CurrentDisplayMode::Analyzer => {
+                            self.mode = CurrentDisplayMode::Tuner;
+                        }
+                        CurrentDisplayMode::Tuner => {
                             self.mode = CurrentDisplayMode::None;
                         }
                         CurrentDisplayMode::None => {
@@ -747,32 +894,26 @@ impl App {
                     }
                 }
                 KeyCode::Media(media_key_code) => match media_key_code {
-                    MediaKeyCode::Play => play(
-                        &mut self.graph,
-                        self.os_media_controls.as_mut(),
-                        sink_cmd_tx,
-                    ),
-                    MediaKeyCode::Pause => pause(
-                        &mut self.graph,
-                        self.os_media_controls.as_mut(),
-                        sink_cmd_tx,
-                    ),
-                    MediaKeyCode::PlayPause => toggle_play_pause(
-                        &mut self.graph,
-                        self.os_media_controls.as_mut(),
-                        sink_cmd_tx,
-                    ),
+                    MediaKeyCode::Play => {
+                        play(&mut self.graph, self.os_media_controls.as_mut(), player)
+                    }
+                    MediaKeyCode::Pause => {
+                        pause(&mut self.graph, self.os_media_controls.as_mut(), player)
+                    }
+                    MediaKeyCode::PlayPause => {
+                        toggle_play_pause(&mut self.graph, self.os_media_controls.as_mut(), player)
+                    }
                     MediaKeyCode::Stop => {
                         quit = true;
                     }
                     MediaKeyCode::LowerVolume => {
-                        lower_volume(&state, self.os_media_controls.as_mut(), sink_cmd_tx)
+                        lower_volume(&state, self.os_media_controls.as_mut(), player)
                     }
                     MediaKeyCode::RaiseVolume => {
-                        raise_volume(&state, self.os_media_controls.as_mut(), sink_cmd_tx)
+                        raise_volume(&state, self.os_media_controls.as_mut(), player)
                     }
                     MediaKeyCode::MuteVolume => {
-                        mute_volume(&state, self.os_media_controls.as_mut(), sink_cmd_tx)
+                        mute_volume(&state, self.os_media_controls.as_mut(), player)
                     }
                     MediaKeyCode::TrackNext
                     | MediaKeyCode::TrackPrevious
@@ -795,31 +936,19 @@ impl App {
         &mut self,
         event: MediaControlEvent,
         state: &Mutex<State>,
-        sink_cmd_tx: &mut UnboundedSender<SinkCommand>,
+        player: &mut Player,
     ) -> bool {
         let mut quit = false;
 
         match event {
             MediaControlEvent::Play => {
-                play(
-                    &mut self.graph,
-                    self.os_media_controls.as_mut(),
-                    sink_cmd_tx,
-                );
+                play(&mut self.graph, self.os_media_controls.as_mut(), player);
             }
             MediaControlEvent::Pause => {
-                pause(
-                    &mut self.graph,
-                    self.os_media_controls.as_mut(),
-                    sink_cmd_tx,
-                );
+                pause(&mut self.graph, self.os_media_controls.as_mut(), player);
             }
             MediaControlEvent::Toggle => {
-                toggle_play_pause(
-                    &mut self.graph,
-                    self.os_media_controls.as_mut(),
-                    sink_cmd_tx,
-                );
+                toggle_play_pause(&mut self.graph, self.os_media_controls.as_mut(), player);
             }
             MediaControlEvent::Stop | MediaControlEvent::Quit => {
                 quit = true;
@@ -829,7 +958,7 @@ impl App {
                     volume as f32,
                     state,
                     self.os_media_controls.as_mut(),
-                    sink_cmd_tx,
+                    player,
                 );
             }
             MediaControlEvent::Next
@@ -906,52 +1035,48 @@ fn make_header<'a>(
 }
 
 /// Play music.
-fn play(
-    graph: &mut GraphConfig,
-    os_media_controls: Option<&mut OsMediaControls>,
-    sink_cmd_tx: &UnboundedSender<SinkCommand>,
-) {
+fn play(graph: &mut GraphConfig, os_media_controls: Option<&mut OsMediaControls>, player: &Player) {
     graph.pause = false;
     send_os_media_controls_command(os_media_controls, os_media_controls::Command::Play);
-    sink_cmd_tx
-        .send(SinkCommand::Play)
-        .expect("receiver never dropped");
+    player
+        .send(PlayerCommand::Resume)
+        .expect("player thread never stops");
 }
 
 /// Pause music.
 fn pause(
     graph: &mut GraphConfig,
     os_media_controls: Option<&mut OsMediaControls>,
-    sink_cmd_tx: &UnboundedSender<SinkCommand>,
+    player: &Player,
 ) {
     graph.pause = true;
     send_os_media_controls_command(os_media_controls, os_media_controls::Command::Pause);
-    sink_cmd_tx
-        .send(SinkCommand::Pause)
-        .expect("receiver never dropped");
+    player
+        .send(PlayerCommand::Pause)
+        .expect("player thread never stops");
 }
 
 /// Toggle between play and pause.
 fn toggle_play_pause(
     graph: &mut GraphConfig,
     os_media_controls: Option<&mut OsMediaControls>,
-    sink_cmd_tx: &UnboundedSender<SinkCommand>,
+    player: &Player,
 ) {
     graph.pause = !graph.pause;
-    let (sink_cmd, os_media_controls_command) = if graph.pause {
-        (SinkCommand::Pause, os_media_controls::Command::Pause)
+    let (command, os_media_controls_command) = if graph.pause {
+        (PlayerCommand::Pause, os_media_controls::Command::Pause)
     } else {
-        (SinkCommand::Play, os_media_controls::Command::Play)
+        (PlayerCommand::Resume, os_media_controls::Command::Play)
     };
     send_os_media_controls_command(os_media_controls, os_media_controls_command);
-    sink_cmd_tx.send(sink_cmd).expect("receiver never dropped");
+    player.send(command).expect("player thread never stops");
 }
 
 /// Lower the volume.
 fn lower_volume(
     state: &Mutex<State>,
     os_media_controls: Option<&mut OsMediaControls>,
-    sink_cmd_tx: &UnboundedSender<SinkCommand>,
+    player: &Player,
 ) {
     let mut state = state.lock().unwrap();
     state.volume.change_volume(-1.0);
@@ -959,16 +1084,16 @@ fn lower_volume(
         os_media_controls,
         os_media_controls::Command::SetVolume(state.volume.volume_ratio() as f64),
     );
-    sink_cmd_tx
-        .send(SinkCommand::SetVolume(state.volume.volume_ratio()))
-        .expect("receiver never dropped");
+    player
+        .send(PlayerCommand::SetVolume(state.volume.volume_ratio()))
+        .expect("player thread never stops");
 }
 
 /// Raise the volume.
 fn raise_volume(
     state: &Mutex<State>,
     os_media_controls: Option<&mut OsMediaControls>,
-    sink_cmd_tx: &UnboundedSender<SinkCommand>,
+    player: &Player,
 ) {
     let mut state = state.lock().unwrap();
     state.volume.change_volume(1.0);
@@ -976,16 +1101,16 @@ fn raise_volume(
         os_media_controls,
         os_media_controls::Command::SetVolume(state.volume.volume_ratio() as f64),
     );
-    sink_cmd_tx
-        .send(SinkCommand::SetVolume(state.volume.volume_ratio()))
-        .expect("receiver never dropped");
+    player
+        .send(PlayerCommand::SetVolume(state.volume.volume_ratio()))
+        .expect("player thread never stops");
 }
 
 /// Mute the volume.
 fn mute_volume(
     state: &Mutex<State>,
     os_media_controls: Option<&mut OsMediaControls>,
-    sink_cmd_tx: &UnboundedSender<SinkCommand>,
+    player: &Player,
 ) {
     let mut state = state.lock().unwrap();
     state.volume.toggle_mute();
@@ -993,9 +1118,9 @@ fn mute_volume(
         os_media_controls,
         os_media_controls::Command::SetVolume(state.volume.volume_ratio() as f64),
     );
-    sink_cmd_tx
-        .send(SinkCommand::SetVolume(state.volume.volume_ratio()))
-        .expect("receiver never dropped");
+    player
+        .send(PlayerCommand::SetVolume(state.volume.volume_ratio()))
+        .expect("player thread never stops");
 }
 
 /// Set the volume to the given volume ratio.
@@ -1003,7 +1128,7 @@ fn set_volume_ratio(
     volume_ratio: f32,
     state: &Mutex<State>,
     os_media_controls: Option<&mut OsMediaControls>,
-    sink_cmd_tx: &UnboundedSender<SinkCommand>,
+    player: &Player,
 ) {
     let mut state = state.lock().unwrap();
     state.volume.set_volume_ratio(volume_ratio);
@@ -1011,9 +1136,9 @@ fn set_volume_ratio(
         os_media_controls,
         os_media_controls::Command::SetVolume(state.volume.volume_ratio() as f64),
     );
-    sink_cmd_tx
-        .send(SinkCommand::SetVolume(state.volume.volume_ratio()))
-        .expect("receiver never dropped");
+    player
+        .send(PlayerCommand::SetVolume(state.volume.volume_ratio()))
+        .expect("player thread never stops");
 }
 
 /// Send [`os_media_controls::Command`].
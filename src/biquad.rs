@@ -0,0 +1,188 @@
+//! Biquad filters: coefficients, processing and magnitude response.
+//!
+//! The band filters follow the RBJ Audio EQ Cookbook, as Rockbox's
+//! `dsp_filter.c` does: band 0 is a low shelf, the last band a high shelf
+//! and the rest peaking filters, each with its own Q. The bass/treble tone
+//! controls are first-order shelves.
+
+use std::f64::consts::PI;
+
+/// Per-channel history of a [`Biquad`] (transposed direct form II, which
+/// copes well with coefficients changing under it).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BiquadState {
+    s1: f64,
+    s2: f64,
+}
+
+/// Normalized biquad (`a0` = 1):
+/// `H(z) = (b0 + b1·z⁻¹ + b2·z⁻²) / (1 + a1·z⁻¹ + a2·z⁻²)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+impl Biquad {
+    /// Peaking filter: `gain_db` at `cutoff`, with a bandwidth set by `q`.
+    pub fn peaking(sample_rate: f64, cutoff: f64, q: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let (cos, alpha) = cos_alpha(sample_rate, cutoff, q);
+        Self::normalized(
+            [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+        )
+    }
+
+    /// Low shelf: `gain_db` below `cutoff`.
+    pub fn low_shelf(sample_rate: f64, cutoff: f64, q: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let (cos, alpha) = cos_alpha(sample_rate, cutoff, q);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::normalized(
+            [
+                a * ((a + 1.0) - (a - 1.0) * cos + k),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - k),
+            ],
+            [
+                (a + 1.0) + (a - 1.0) * cos + k,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - k,
+            ],
+        )
+    }
+
+    /// High shelf: `gain_db` above `cutoff`.
+    pub fn high_shelf(sample_rate: f64, cutoff: f64, q: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let (cos, alpha) = cos_alpha(sample_rate, cutoff, q);
+        let k = 2.0 * a.sqrt() * alpha;
+        Self::normalized(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos + k),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - k),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos + k,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - k,
+            ],
+        )
+    }
+
+    /// First-order low shelf, as used for the bass tone control. Like
+    /// Rockbox's, it is half-way there (in dB) at `cutoff`.
+    pub fn low_shelf_first_order(sample_rate: f64, cutoff: f64, gain_db: f64) -> Self {
+        let g = 10f64.powf(gain_db / 20.0);
+        let k = prewarp(sample_rate, cutoff) / g.sqrt();
+        Self::normalized([k * g + 1.0, k * g - 1.0, 0.0], [k + 1.0, k - 1.0, 0.0])
+    }
+
+    /// First-order high shelf, as used for the treble tone control, also
+    /// half-way there at `cutoff`.
+    pub fn high_shelf_first_order(sample_rate: f64, cutoff: f64, gain_db: f64) -> Self {
+        let g = 10f64.powf(gain_db / 20.0);
+        let k = prewarp(sample_rate, cutoff) * g.sqrt();
+        Self::normalized([g + k, k - g, 0.0], [k + 1.0, k - 1.0, 0.0])
+    }
+
+    fn normalized(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+        }
+    }
+
+    /// Filter one sample, updating `state`.
+    pub fn process(&self, state: &mut BiquadState, x: f64) -> f64 {
+        let y = self.b0 * x + state.s1;
+        state.s1 = self.b1 * x - self.a1 * y + state.s2;
+        state.s2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// Gain in dB at `freq`.
+    pub fn magnitude_db(&self, sample_rate: f64, freq: f64) -> f64 {
+        let w = 2.0 * PI * freq / sample_rate;
+        // z⁻¹ = e^(-jw), z⁻² = e^(-2jw)
+        let (c1, s1) = (w.cos(), -w.sin());
+        let (c2, s2) = ((2.0 * w).cos(), -(2.0 * w).sin());
+        let num = (
+            self.b0 + self.b1 * c1 + self.b2 * c2,
+            self.b1 * s1 + self.b2 * s2,
+        );
+        let den = (
+            1.0 + self.a1 * c1 + self.a2 * c2,
+            self.a1 * s1 + self.a2 * s2,
+        );
+        let power = (num.0 * num.0 + num.1 * num.1) / (den.0 * den.0 + den.1 * den.1);
+        10.0 * power.max(f64::MIN_POSITIVE).log10()
+    }
+}
+
+/// Keep cutoffs below Nyquist so low sample rates don't blow the filters up.
+fn clamp_cutoff(sample_rate: f64, cutoff: f64) -> f64 {
+    cutoff.clamp(1.0, sample_rate * 0.49)
+}
+
+fn cos_alpha(sample_rate: f64, cutoff: f64, q: f64) -> (f64, f64) {
+    let w0 = 2.0 * PI * clamp_cutoff(sample_rate, cutoff) / sample_rate;
+    (w0.cos(), w0.sin() / (2.0 * q.max(0.01)))
+}
+
+/// Bilinear-transform prewarp, `tan(π·fc/fs)`.
+fn prewarp(sample_rate: f64, cutoff: f64) -> f64 {
+    (PI * clamp_cutoff(sample_rate, cutoff) / sample_rate).tan()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 44100.0;
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() < 0.1
+    }
+
+    #[test]
+    fn peaking_hits_its_gain_at_the_cutoff_only() {
+        let filter = Biquad::peaking(RATE, 1000.0, 1.0, -6.0);
+        assert!(close(filter.magnitude_db(RATE, 1000.0), -6.0));
+        assert!(close(filter.magnitude_db(RATE, 20.0), 0.0));
+        assert!(close(filter.magnitude_db(RATE, 20000.0), 0.0));
+        assert!(close(
+            Biquad::peaking(RATE, 1000.0, 1.0, 0.0).magnitude_db(RATE, 1000.0),
+            0.0
+        ));
+    }
+
+    #[test]
+    fn shelves_reach_their_gain_on_their_side() {
+        let low = Biquad::low_shelf(RATE, 200.0, 0.7, 6.0);
+        assert!(close(low.magnitude_db(RATE, 10.0), 6.0));
+        assert!(close(low.magnitude_db(RATE, 15000.0), 0.0));
+
+        let high = Biquad::high_shelf(RATE, 8000.0, 0.7, -4.0);
+        assert!(close(high.magnitude_db(RATE, 20000.0), -4.0));
+        assert!(close(high.magnitude_db(RATE, 50.0), 0.0));
+
+        let bass = Biquad::low_shelf_first_order(RATE, 200.0, 8.0);
+        assert!(close(bass.magnitude_db(RATE, 1.0), 8.0));
+        assert!(close(bass.magnitude_db(RATE, 22000.0), 0.0));
+        assert!(close(bass.magnitude_db(RATE, 200.0), 4.0));
+
+        let treble = Biquad::high_shelf_first_order(RATE, 3500.0, -3.0);
+        assert!(close(treble.magnitude_db(RATE, 22049.0), -3.0));
+        assert!(close(treble.magnitude_db(RATE, 1.0), 0.0));
+        assert!(close(treble.magnitude_db(RATE, 3500.0), -1.5));
+    }
+}
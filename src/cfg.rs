@@ -1,3 +1,6 @@
+use anyhow::{anyhow, Error};
+use ratatui::style::Color;
+
 use crate::music::Note;
 
 /// a simple oscilloscope/vectorscope for your terminal
@@ -23,6 +26,27 @@ pub struct UiOptions {
 
     /// don't use braille dots for drawing lines
     pub no_braille: bool,
+
+    /// colors of the channels, cycled through when there are more channels
+    pub palette: Vec<Color>,
+}
+
+impl UiOptions {
+    /// parse color names (`red`, `lightblue`, ...), indices or `#rrggbb` values into a palette
+    pub fn parse_palette<S: AsRef<str>>(colors: &[S]) -> Result<Vec<Color>, Error> {
+        if colors.is_empty() {
+            return Err(anyhow!("the palette needs at least one color"));
+        }
+        colors
+            .iter()
+            .map(|color| {
+                let color = color.as_ref().trim();
+                color
+                    .parse::<Color>()
+                    .map_err(|_| anyhow!("unknown color '{}' in the palette", color))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1,5 +1,7 @@
+use std::borrow::Cow;
 use std::io::Read;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{anyhow, Error};
@@ -12,28 +14,59 @@ use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 
-use crate::equalizer::EqProcessor;
+use crate::downmix::{ChannelLayout, ChannelPolicy, Downmix};
+use crate::dynamics::DynamicsProcessor;
+use crate::equalizer::{EqProcessor, Equalizer};
+use crate::loudness::LoudnessProcessor;
+use crate::opus;
+use crate::stats::StreamCounters;
+use crate::stereo::StereoProcessor;
 
-/// A chunk of decoded interleaved samples, forwarded to the visualizer.
+/// A chunk of decoded interleaved samples (full scale ±1.0), forwarded to
+/// the visualizer.
 #[derive(Debug, Clone)]
 pub struct Frame {
-    pub data: Vec<i16>,
+    pub data: Vec<f32>,
     pub channels: usize,
     pub sample_rate: i32,
 }
 
-/// Decodes an Icecast/HTTP audio stream (MP3, AAC, Ogg Vorbis, FLAC, WAV, ...)
-/// using symphonia and exposes it as a rodio `Source`.
+impl Frame {
+    /// The samples with at most two channels, surround folded down for the
+    /// visualizers. Returns the channel count alongside.
+    pub fn stereo(&self) -> (Cow<'_, [f32]>, usize) {
+        if self.channels <= 2 {
+            return (Cow::Borrowed(&self.data), self.channels);
+        }
+        let mut stereo = Vec::new();
+        Downmix::new(0, self.channels as u16).process(&self.data, &mut stereo);
+        (Cow::Owned(stereo), 2)
+    }
+}
+
+/// Decodes an Icecast/HTTP audio stream (MP3, AAC, Ogg Vorbis, Opus, FLAC, ...)
+/// using symphonia and exposes it as a rodio `Source` of `f32` samples, so
+/// 24-bit streams keep their resolution through the whole pipeline.
 pub struct StreamDecoder {
     format: Box<dyn FormatReader>,
     decoder: Box<dyn Decoder>,
     track_id: u32,
-    buffer: Vec<i16>,
+    buffer: Vec<f32>,
     offset: usize,
     channels: u16,
     sample_rate: u32,
+    /// Layout of the last decoded packet, and what it is played as.
+    layout: ChannelLayout,
+    /// Matrix folding `layout` down to stereo, kept while it doesn't change.
+    downmix: Option<(ChannelLayout, Downmix)>,
+    /// Short codec name, e.g. `mp3`.
+    codec: String,
     tx: Option<Sender<Frame>>,
-    eq: EqProcessor,
+    counters: Arc<StreamCounters>,
+    loudness: Option<LoudnessProcessor>,
+    eq: Option<EqProcessor>,
+    stereo: Option<StereoProcessor>,
+    dynamics: Option<DynamicsProcessor>,
 }
 
 impl StreamDecoder {
@@ -73,16 +106,19 @@ impl StreamDecoder {
             .ok_or_else(|| anyhow!("no supported audio track found in stream"))?;
         let track_id = track.id;
 
-        let decoder = symphonia::default::get_codecs()
+        let decoder = opus::codecs()
             .make(&track.codec_params, &DecoderOptions::default())
             .map_err(|e| anyhow!("unsupported codec: {}", e))?;
 
-        let channels = track
+        let (mask, channels) = track
             .codec_params
             .channels
-            .map(|c| c.count() as u16)
-            .unwrap_or(2);
+            .map_or((0, 2), |c| (c.bits(), c.count() as u16));
         let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
+        let codec = opus::codecs()
+            .get_codec(track.codec_params.codec)
+            .map(|descriptor| descriptor.short_name.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
 
         let mut this = StreamDecoder {
             format,
@@ -92,8 +128,15 @@ impl StreamDecoder {
             offset: 0,
             channels,
             sample_rate,
+            layout: ChannelLayout::new(mask, channels, channels),
+            downmix: None,
+            codec,
             tx,
-            eq: EqProcessor::new(),
+            counters: Arc::default(),
+            loudness: None,
+            eq: Some(EqProcessor::new()),
+            stereo: Some(StereoProcessor::new()),
+            dynamics: Some(DynamicsProcessor::new()),
         };
 
         // Decode the first packet so channel count and sample rate are accurate
@@ -105,6 +148,48 @@ impl StreamDecoder {
         Ok(this)
     }
 
+    /// Leave the equalizer, stereo controls and night mode to whatever
+    /// consumes this decoder, e.g. a [`crate::mixer::Mixer`] that
+    /// equalizes the mixed output once.
+    pub fn without_eq(mut self) -> Self {
+        self.eq = None;
+        self.stereo = None;
+        self.dynamics = None;
+        self
+    }
+
+    /// Equalize with `equalizer` instead of the global one.
+    pub fn with_equalizer(mut self, equalizer: Arc<Equalizer>) -> Self {
+        self.eq = Some(EqProcessor::with_equalizer(equalizer));
+        self
+    }
+
+    /// Normalise this stream's loudness (when enabled in settings),
+    /// remembering the measurement for `provider`'s station `id`.
+    pub fn with_loudness(mut self, provider: &str, id: &str) -> Self {
+        self.loudness = Some(LoudnessProcessor::new(Some((provider, id))));
+        self
+    }
+
+    /// Report skipped packets and the decoded format to `counters`,
+    /// including the packets skipped while probing the stream.
+    pub fn with_counters(mut self, counters: Arc<StreamCounters>) -> Self {
+        counters.add_decode_errors(self.counters.decode_errors());
+        counters.set_format(self.sample_rate, self.layout);
+        self.counters = counters;
+        self
+    }
+
+    /// Short name of the decoded codec, e.g. `mp3` or `aac`.
+    pub fn codec(&self) -> &str {
+        &self.codec
+    }
+
+    /// The stream's channel layout, and whether it is downmixed.
+    pub fn layout(&self) -> ChannelLayout {
+        self.layout
+    }
+
     /// Decode packets until one yields samples. Returns false at end of stream.
     fn decode_next(&mut self) -> bool {
         loop {
@@ -122,21 +207,61 @@ impl StreamDecoder {
                         continue;
                     }
                     let spec = *decoded.spec();
-                    self.channels = spec.channels.count() as u16;
+                    let source_channels = spec.channels.count() as u16;
+                    self.channels = ChannelPolicy::global().target_channels(source_channels);
                     self.sample_rate = spec.rate;
+                    self.layout =
+                        ChannelLayout::new(spec.channels.bits(), source_channels, self.channels);
+                    self.counters.set_format(self.sample_rate, self.layout);
 
-                    let mut samples = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+                    let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
                     samples.copy_interleaved_ref(decoded);
-                    self.buffer.clear();
-                    self.buffer.extend_from_slice(samples.samples());
                     self.offset = 0;
 
+                    // Fold surround down to stereo before anything else, so
+                    // loudness, the EQ and the visualizer all see two
+                    // channels.
+                    if self.layout.is_downmixed() {
+                        let layout = self.layout;
+                        if self
+                            .downmix
+                            .as_ref()
+                            .is_none_or(|(cached, _)| *cached != layout)
+                        {
+                            self.downmix =
+                                Some((layout, Downmix::new(layout.mask, layout.channels)));
+                        }
+                        if let Some((_, downmix)) = &self.downmix {
+                            downmix.process(samples.samples(), &mut self.buffer);
+                        }
+                    } else {
+                        self.buffer.clear();
+                        self.buffer.extend_from_slice(samples.samples());
+                    }
+
+                    // Level the station first so the EQ sees the same
+                    // loudness whatever is playing (no-op when disabled).
+                    if let Some(loudness) = &mut self.loudness {
+                        loudness.process(&mut self.buffer, self.channels, self.sample_rate);
+                    }
+
                     // Route through the equalizer (no-op when disabled).
                     // Done before the visualizer send so the scope shows
                     // what is actually heard.
-                    self.channels =
-                        self.eq
-                            .process(&mut self.buffer, self.channels, self.sample_rate);
+                    if let Some(eq) = &mut self.eq {
+                        eq.process(&mut self.buffer, self.channels, self.sample_rate);
+                    }
+
+                    // Balance, width and mono (no-op when untouched).
+                    if let Some(stereo) = &mut self.stereo {
+                        stereo.process(&mut self.buffer, self.channels);
+                    }
+
+                    // Night mode compresses what the EQ shaped (no-op when
+                    // off).
+                    if let Some(dynamics) = &mut self.dynamics {
+                        dynamics.process(&mut self.buffer, self.channels, self.sample_rate);
+                    }
 
                     if let Some(tx) = &self.tx {
                         let frame = Frame {
@@ -152,7 +277,10 @@ impl StreamDecoder {
                     return true;
                 }
                 // Skip malformed packets, common at the start of live streams.
-                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(SymphoniaError::DecodeError(_)) => {
+                    self.counters.add_decode_errors(1);
+                    continue;
+                }
                 Err(_) => return false,
             }
         }
@@ -182,10 +310,10 @@ impl Source for StreamDecoder {
 }
 
 impl Iterator for StreamDecoder {
-    type Item = i16;
+    type Item = f32;
 
     #[inline]
-    fn next(&mut self) -> Option<i16> {
+    fn next(&mut self) -> Option<f32> {
         if self.offset >= self.buffer.len() && !self.decode_next() {
             return None;
         }
@@ -197,6 +325,20 @@ impl Iterator for StreamDecoder {
     }
 }
 
+fn extension_for_mime(mime: &str) -> Option<&'static str> {
+    match mime.to_ascii_lowercase().as_str() {
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => Some("aac"),
+        "audio/mp4" | "audio/m4a" => Some("m4a"),
+        "application/ogg" | "audio/ogg" | "audio/x-ogg" => Some("ogg"),
+        "audio/opus" => Some("opus"),
+        "audio/webm" | "video/webm" => Some("webm"),
+        "audio/flac" | "audio/x-flac" => Some("flac"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -241,15 +383,3 @@ mod tests {
         decode_live_stream("http://stream.radioparadise.com/flacm");
     }
 }
-
-fn extension_for_mime(mime: &str) -> Option<&'static str> {
-    match mime.to_ascii_lowercase().as_str() {
-        "audio/mpeg" | "audio/mp3" => Some("mp3"),
-        "audio/aac" | "audio/aacp" | "audio/x-aac" => Some("aac"),
-        "audio/mp4" | "audio/m4a" => Some("m4a"),
-        "application/ogg" | "audio/ogg" | "audio/x-ogg" => Some("ogg"),
-        "audio/flac" | "audio/x-flac" => Some("flac"),
-        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
-        _ => None,
-    }
-}
@@ -0,0 +1,187 @@
+//! Audio output devices: listing them for `tunein devices` and the hub
+//! picker, and opening the one picked with `--device` or the
+//! `output_device` setting instead of always using the system default.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use owo_colors::OwoColorize;
+use rodio::cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::cpal::{Device, FromSample, SampleFormat, SizedSample, Stream, SupportedStreamConfig};
+use rodio::dynamic_mixer::{self, DynamicMixerController};
+use rodio::{OutputStream, OutputStreamHandle, StreamError};
+
+use crate::dither::Dither;
+
+/// One output device as reported by the audio host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputDevice {
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// Every output device of the default audio host, the system default first.
+pub fn list() -> Result<Vec<OutputDevice>, Error> {
+    let host = rodio::cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+    let mut devices: Vec<OutputDevice> = host
+        .output_devices()
+        .map_err(|e| anyhow!("failed to list audio output devices: {}", e))?
+        .filter_map(|device| device.name().ok())
+        .map(|name| OutputDevice {
+            is_default: default.as_deref() == Some(name.as_str()),
+            name,
+        })
+        .collect();
+    devices.sort_by_key(|device| !device.is_default);
+    Ok(devices)
+}
+
+/// An opened output device.
+pub struct OpenDevice {
+    pub stream: DeviceStream,
+    /// The rate the device plays at.
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// The stream feeding an opened device.
+pub enum DeviceStream {
+    /// Played by rodio, which converts the mix to the device's format.
+    Rodio {
+        _stream: OutputStream,
+        handle: OutputStreamHandle,
+    },
+    /// A 16-bit device. rodio would truncate the mix to 16 bits, so it is
+    /// quantized here with TPDF dither instead.
+    Dithered {
+        _stream: Stream,
+        mixer: Arc<DynamicMixerController<f32>>,
+    },
+}
+
+/// Open the output device called `name`, or the system default when `None`.
+///
+/// An exact name match wins; otherwise a unique case-insensitive substring
+/// is accepted, so `--device usb` finds "USB Audio DAC".
+pub fn open(name: Option<&str>) -> Result<OpenDevice, Error> {
+    let host = rodio::cpal::default_host();
+    let Some(name) = name.filter(|name| !name.is_empty()) else {
+        let device = host.default_output_device().ok_or_else(|| {
+            anyhow!("no audio output device found; check that a sound card or audio server is available")
+        })?;
+        return open_device(&device)
+            .map_err(|e| anyhow!("failed to open the default audio output device: {}", e));
+    };
+
+    let devices: Vec<_> = host
+        .output_devices()
+        .map_err(|e| anyhow!("failed to list audio output devices: {}", e))?
+        .filter_map(|device| device.name().ok().map(|n| (n, device)))
+        .collect();
+
+    let needle = name.to_lowercase();
+    let matches: Vec<_> = match devices.iter().find(|(n, _)| n == name) {
+        Some(exact) => vec![exact],
+        None => devices
+            .iter()
+            .filter(|(n, _)| n.to_lowercase().contains(&needle))
+            .collect(),
+    };
+
+    match matches.as_slice() {
+        [(device_name, device)] => open_device(device)
+            .map_err(|e| anyhow!("failed to open audio output device {}: {}", device_name, e)),
+        [] => Err(anyhow!(
+            "no audio output device named \"{}\"; run `tunein devices` to list them",
+            name
+        )),
+        _ => Err(anyhow!(
+            "\"{}\" matches several audio output devices ({}); use the full name",
+            name,
+            matches
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Open `device` at its default configuration, which is what rodio picks.
+fn open_device(device: &Device) -> Result<OpenDevice, StreamError> {
+    let config = device
+        .default_output_config()
+        .map_err(StreamError::DefaultStreamConfigError)?;
+    let sample_rate = config.sample_rate().0;
+    let channels = config.channels();
+    let stream = match config.sample_format() {
+        SampleFormat::I16 => open_dithered::<i16>(device, &config)?,
+        SampleFormat::U16 => open_dithered::<u16>(device, &config)?,
+        _ => {
+            let (stream, handle) = OutputStream::try_from_device_config(device, config)?;
+            DeviceStream::Rodio {
+                _stream: stream,
+                handle,
+            }
+        }
+    };
+    Ok(OpenDevice {
+        stream,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Play a mixer on the 16-bit `device`, dithering every sample of the mix.
+fn open_dithered<T>(
+    device: &Device,
+    config: &SupportedStreamConfig,
+) -> Result<DeviceStream, StreamError>
+where
+    T: SizedSample + FromSample<i16>,
+{
+    let (mixer, mut source) =
+        dynamic_mixer::mixer::<f32>(config.channels(), config.sample_rate().0);
+    let mut dither = Dither::new();
+    let stream = device
+        .build_output_stream::<T, _, _>(
+            &config.config(),
+            move |data, _| {
+                for out in data.iter_mut() {
+                    let sample = source.next().unwrap_or(0.0);
+                    *out = T::from_sample(dither.quantize(sample));
+                }
+            },
+            |err| eprintln!("an error occurred on output stream: {}", err),
+            None,
+        )
+        .map_err(StreamError::BuildStreamError)?;
+    stream.play().map_err(StreamError::PlayStreamError)?;
+    Ok(DeviceStream::Dithered {
+        _stream: stream,
+        mixer,
+    })
+}
+
+/// Print every output device, marking the system default and the one
+/// selected in the settings file.
+pub fn print_list(selected: Option<&str>) -> Result<(), Error> {
+    let devices = list()?;
+    if devices.is_empty() {
+        println!("No audio output devices found");
+        return Ok(());
+    }
+    for device in devices {
+        let mut line = device.name.clone();
+        if device.is_default {
+            line.push_str(" (default)");
+        }
+        if selected == Some(device.name.as_str()) {
+            println!("{} {}", "*".bright_green(), line.bright_green());
+        } else {
+            println!("  {}", line);
+        }
+    }
+    Ok(())
+}
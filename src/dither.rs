@@ -0,0 +1,72 @@
+//! The one lossy step of the f32 pipeline: quantizing to 16-bit PCM.
+//!
+//! Plain truncation turns the rounding error into distortion that follows
+//! the signal, audible on quiet passages and fade-outs. [`Dither`] adds
+//! triangular (TPDF) noise of ±1 LSB first, which decorrelates the error
+//! into a constant, very low noise floor. Digital silence stays silent.
+
+/// Full scale of a 16-bit sample.
+const SCALE: f32 = 32767.0;
+
+/// TPDF ditherer with its own small PRNG, one per output stream.
+pub struct Dither {
+    state: u32,
+}
+
+impl Dither {
+    pub fn new() -> Self {
+        Self { state: 0x9e37_79b9 }
+    }
+
+    /// Quantize `sample` (full scale ±1.0) to 16 bits, clipping anything
+    /// beyond full scale.
+    pub fn quantize(&mut self, sample: f32) -> i16 {
+        if sample == 0.0 {
+            return 0;
+        }
+        let noise = self.uniform() - self.uniform();
+        (sample * SCALE + noise)
+            .round()
+            .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
+    /// Uniform noise in `[0, 1)`, from a xorshift generator.
+    fn uniform(&mut self) -> f32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        (x >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+impl Default for Dither {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_stays_within_one_lsb_and_averages_out() {
+        let mut dither = Dither::new();
+        let sample = 1000.3 / SCALE;
+        let quantized: Vec<i16> = (0..100_000).map(|_| dither.quantize(sample)).collect();
+        assert!(quantized.iter().all(|&q| (999..=1002).contains(&q)));
+        let mean = quantized.iter().map(|&q| q as f64).sum::<f64>() / quantized.len() as f64;
+        assert!((mean - 1000.3).abs() < 0.02, "mean {mean}");
+    }
+
+    #[test]
+    fn clips_at_full_scale_and_keeps_silence() {
+        let mut dither = Dither::new();
+        assert_eq!(dither.quantize(2.0), i16::MAX);
+        assert_eq!(dither.quantize(-2.0), i16::MIN);
+        assert_eq!(dither.quantize(0.0), 0);
+        assert!(dither.quantize(-1234.0 / SCALE).abs_diff(-1234) <= 1);
+    }
+}
@@ -0,0 +1,306 @@
+//! Multichannel streams: folding 5.1 and 7.1 down to stereo.
+//!
+//! The visualizers handle at most two channels, so by default anything
+//! wider is downmixed right after decoding with the ITU-R BS.775 matrix:
+//! centre and surrounds go into their side at -3 dB, the LFE is dropped,
+//! and the whole matrix is scaled so a full-scale input can't clip. In [`Multichannel::Passthrough`] mode the channels are kept when
+//! the output device has at least as many, and the equalizer runs on each
+//! of them.
+//!
+//! Layouts are described by a channel mask using symphonia's `Channels`
+//! bits, which are also the order of the interleaved samples.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+
+const FRONT_LEFT: u32 = 0x1;
+const FRONT_RIGHT: u32 = 0x2;
+const FRONT_CENTRE: u32 = 0x4;
+const LFE1: u32 = 0x8;
+const REAR_LEFT: u32 = 0x10;
+const REAR_RIGHT: u32 = 0x20;
+const REAR_CENTRE: u32 = 0x100;
+const SIDE_LEFT: u32 = 0x200;
+const SIDE_RIGHT: u32 = 0x400;
+const LFE2: u32 = 0x200_0000;
+
+/// Speakers on the left and right of the listener, besides the front pair.
+const LEFT: u32 = 0x0044_9210;
+const RIGHT: u32 = 0x010a_4420;
+/// Front left/right centre and wide speakers, mixed in at full level.
+const FRONT_LEFT_EXTRA: u32 = 0x0010_0040;
+const FRONT_RIGHT_EXTRA: u32 = 0x0020_0080;
+
+/// -3 dB, the BS.775 level for centre and surround channels.
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// What to do with streams that have more than two channels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Multichannel {
+    /// Always fold down to stereo, ahead of the equalizer.
+    #[default]
+    Downmix,
+    /// Keep every channel if the output device can play them all.
+    Passthrough,
+}
+
+impl FromStr for Multichannel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "downmix" => Ok(Self::Downmix),
+            "passthrough" => Ok(Self::Passthrough),
+            other => Err(anyhow!(
+                "unknown multichannel mode '{}', expected downmix or passthrough",
+                other
+            )),
+        }
+    }
+}
+
+/// Process-wide multichannel mode, seeded from the settings file, and the
+/// channel count of the output in use.
+pub struct ChannelPolicy {
+    passthrough: AtomicBool,
+    output_channels: AtomicU16,
+}
+
+static GLOBAL: OnceLock<ChannelPolicy> = OnceLock::new();
+
+impl ChannelPolicy {
+    pub fn global() -> &'static ChannelPolicy {
+        GLOBAL.get_or_init(|| ChannelPolicy {
+            passthrough: AtomicBool::new(
+                Settings::load().multichannel == Multichannel::Passthrough,
+            ),
+            output_channels: AtomicU16::new(2),
+        })
+    }
+
+    /// Override the mode for this run, e.g. from the command line.
+    pub fn set_mode(&self, mode: Multichannel) {
+        self.passthrough
+            .store(mode == Multichannel::Passthrough, Ordering::Relaxed);
+    }
+
+    pub fn set_output_channels(&self, channels: u16) {
+        self.output_channels.store(channels, Ordering::Relaxed);
+    }
+
+    /// Channels a stream with `channels` of its own is played with.
+    pub fn target_channels(&self, channels: u16) -> u16 {
+        let keep = channels <= 2
+            || (self.passthrough.load(Ordering::Relaxed)
+                && channels <= self.output_channels.load(Ordering::Relaxed));
+        if keep {
+            channels
+        } else {
+            2
+        }
+    }
+}
+
+/// Layout of a stream as decoded, and the channel count it is played with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelLayout {
+    /// Speaker mask, `0` when the stream doesn't say.
+    pub mask: u32,
+    pub channels: u16,
+    /// Channels after downmixing.
+    pub output: u16,
+}
+
+impl ChannelLayout {
+    pub fn new(mask: u32, channels: u16, output: u16) -> Self {
+        Self {
+            mask: resolve_mask(mask, channels),
+            channels,
+            output,
+        }
+    }
+
+    pub fn is_downmixed(&self) -> bool {
+        self.output < self.channels
+    }
+}
+
+impl fmt::Display for ChannelLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&layout_name(self.mask, self.channels))?;
+        if self.is_downmixed() {
+            write!(f, " → {}", layout_name(0, self.output))?;
+        }
+        Ok(())
+    }
+}
+
+/// `mono`, `stereo`, or the usual `5.1`-style name: full-range channels and
+/// LFE channels.
+pub fn layout_name(mask: u32, channels: u16) -> String {
+    let mask = resolve_mask(mask, channels);
+    match channels {
+        0 => "-".to_string(),
+        1 => "mono".to_string(),
+        2 if mask & (LFE1 | LFE2) == 0 => "stereo".to_string(),
+        _ => {
+            let lfe = (mask & (LFE1 | LFE2)).count_ones();
+            format!("{}.{}", channels as u32 - lfe, lfe)
+        }
+    }
+}
+
+/// `mask` if it accounts for every channel, otherwise the usual layout for
+/// that many channels.
+fn resolve_mask(mask: u32, channels: u16) -> u32 {
+    if mask.count_ones() == channels as u32 {
+        return mask;
+    }
+    match channels {
+        1 => FRONT_CENTRE,
+        2 => FRONT_LEFT | FRONT_RIGHT,
+        3 => FRONT_LEFT | FRONT_RIGHT | FRONT_CENTRE,
+        4 => FRONT_LEFT | FRONT_RIGHT | REAR_LEFT | REAR_RIGHT,
+        5 => FRONT_LEFT | FRONT_RIGHT | FRONT_CENTRE | REAR_LEFT | REAR_RIGHT,
+        6 => FRONT_LEFT | FRONT_RIGHT | FRONT_CENTRE | LFE1 | REAR_LEFT | REAR_RIGHT,
+        7 => FRONT_LEFT | FRONT_RIGHT | FRONT_CENTRE | LFE1 | REAR_LEFT | REAR_RIGHT | REAR_CENTRE,
+        8 => {
+            FRONT_LEFT
+                | FRONT_RIGHT
+                | FRONT_CENTRE
+                | LFE1
+                | REAR_LEFT
+                | REAR_RIGHT
+                | SIDE_LEFT
+                | SIDE_RIGHT
+        }
+        // Nothing to go by: spread everything evenly.
+        _ => 0,
+    }
+}
+
+/// Downmix matrix for one layout: a `[left, right]` gain per input channel.
+pub struct Downmix {
+    gains: Vec<[f32; 2]>,
+}
+
+impl Downmix {
+    pub fn new(mask: u32, channels: u16) -> Self {
+        let mask = resolve_mask(mask, channels);
+        let mut gains: Vec<[f32; 2]> = match mask {
+            0 => vec![[0.5, 0.5]; channels as usize],
+            _ => (0..32)
+                .map(|bit| 1u32 << bit)
+                .filter(|speaker| mask & speaker != 0)
+                .map(gains_for)
+                .collect(),
+        };
+        let left: f32 = gains.iter().map(|g| g[0]).sum();
+        let right: f32 = gains.iter().map(|g| g[1]).sum();
+        let scale = 1.0 / left.max(right).max(1.0);
+        for gain in &mut gains {
+            gain[0] *= scale;
+            gain[1] *= scale;
+        }
+        Self { gains }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.gains.len()
+    }
+
+    /// Fold interleaved `input` into interleaved stereo in `output`.
+    pub fn process(&self, input: &[f32], output: &mut Vec<f32>) {
+        output.clear();
+        output.reserve(input.len() / self.channels().max(1) * 2);
+        for frame in input.chunks_exact(self.channels().max(1)) {
+            let (mut left, mut right) = (0.0, 0.0);
+            for (sample, gain) in frame.iter().zip(&self.gains) {
+                left += sample * gain[0];
+                right += sample * gain[1];
+            }
+            output.push(left);
+            output.push(right);
+        }
+    }
+}
+
+fn gains_for(speaker: u32) -> [f32; 2] {
+    match speaker {
+        FRONT_LEFT => [1.0, 0.0],
+        FRONT_RIGHT => [0.0, 1.0],
+        FRONT_CENTRE => [MINUS_3DB, MINUS_3DB],
+        LFE1 | LFE2 => [0.0, 0.0],
+        s if s & FRONT_LEFT_EXTRA != 0 => [1.0, 0.0],
+        s if s & FRONT_RIGHT_EXTRA != 0 => [0.0, 1.0],
+        s if s & LEFT != 0 => [MINUS_3DB, 0.0],
+        s if s & RIGHT != 0 => [0.0, MINUS_3DB],
+        // Rear and top centre speakers.
+        _ => [0.5, 0.5],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SURROUND_5_1: u32 = 0x3f;
+
+    #[test]
+    fn names_common_layouts() {
+        assert_eq!(layout_name(0, 1), "mono");
+        assert_eq!(layout_name(0x3, 2), "stereo");
+        assert_eq!(layout_name(SURROUND_5_1, 6), "5.1");
+        assert_eq!(layout_name(0, 8), "7.1");
+        assert_eq!(layout_name(0x33, 4), "4.0");
+        assert_eq!(ChannelLayout::new(0, 6, 2).to_string(), "5.1 → stereo");
+        assert_eq!(ChannelLayout::new(0, 6, 6).to_string(), "5.1");
+    }
+
+    #[test]
+    fn folds_5_1_to_stereo_without_clipping() {
+        let downmix = Downmix::new(SURROUND_5_1, 6);
+        let mut out = Vec::new();
+
+        // FL, FR, FC, LFE, RL, RR
+        downmix.process(&[1.0, 0.0, 0.0, 0.0, 0.0, 0.0], &mut out);
+        let front = out[0];
+        assert!(front > 0.0 && out[1] == 0.0);
+
+        downmix.process(&[0.0, 0.0, 1.0, 0.0, 0.0, 0.0], &mut out);
+        assert_eq!(out[0], out[1]);
+        assert!((out[0] / front - MINUS_3DB).abs() < 1e-6);
+
+        downmix.process(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0], &mut out);
+        assert_eq!(out, [0.0, 0.0]);
+
+        downmix.process(&[0.0, 0.0, 0.0, 0.0, 0.0, 1.0], &mut out);
+        assert!(out[0] == 0.0 && out[1] > 0.0);
+
+        downmix.process(&[1.0; 12], &mut out);
+        assert_eq!(out.len(), 4);
+        assert!(out.iter().all(|&s| s <= 1.0 + 1e-6), "{:?}", out);
+    }
+
+    #[test]
+    fn passthrough_needs_enough_output_channels() {
+        let policy = ChannelPolicy {
+            passthrough: AtomicBool::new(false),
+            output_channels: AtomicU16::new(8),
+        };
+        assert_eq!(policy.target_channels(1), 1);
+        assert_eq!(policy.target_channels(6), 2);
+        policy.set_mode(Multichannel::Passthrough);
+        assert_eq!(policy.target_channels(6), 6);
+        policy.set_output_channels(2);
+        assert_eq!(policy.target_channels(6), 2);
+    }
+}
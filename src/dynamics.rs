@@ -0,0 +1,278 @@
+//! Dynamics processing for late-night listening: a compressor that tames
+//! the gap between a whispering presenter and a shouting ad break, followed
+//! by a brick-wall limiter so nothing ever pokes above the ceiling.
+//!
+//! The stage runs right after the equalizer, wherever the equalizer runs
+//! (the [`crate::mixer::Mixer`] for the player, the
+//! [`crate::decoder::StreamDecoder`] otherwise). The global [`Dynamics`]
+//! holds the "night mode" switch and the compressor parameters, seeded from
+//! the settings file; each stream owns a [`DynamicsProcessor`] with its
+//! envelope state.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use crate::settings::Settings;
+
+/// Release time of the limiter, in seconds. Its attack is instantaneous.
+const LIMITER_RELEASE_SECS: f64 = 0.05;
+/// Floor of the level detector, so silence doesn't produce −∞ dB.
+const SILENCE_DB: f64 = -120.0;
+
+/// Compressor and limiter parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicsParams {
+    /// Level above which the compressor acts, in dBFS.
+    pub threshold_db: f32,
+    /// Input dB above the threshold per output dB, e.g. `4.0` = 4:1.
+    pub ratio: f32,
+    /// Time to clamp down on a louder passage, in milliseconds.
+    pub attack_ms: f32,
+    /// Time to let go once it gets quieter again, in milliseconds.
+    pub release_ms: f32,
+    /// Limiter ceiling, in dBFS.
+    pub ceiling_db: f32,
+}
+
+impl DynamicsParams {
+    /// The one-key night mode: heavy compression with a fast attack so
+    /// ads and jingles don't jump out, and a −1 dBFS ceiling.
+    pub const NIGHT_MODE: Self = Self {
+        threshold_db: -30.0,
+        ratio: 4.0,
+        attack_ms: 5.0,
+        release_ms: 200.0,
+        ceiling_db: -1.0,
+    };
+
+    /// Clamp every parameter to a usable range.
+    pub fn sanitized(self) -> Self {
+        Self {
+            threshold_db: self.threshold_db.clamp(-60.0, 0.0),
+            ratio: self.ratio.clamp(1.0, 20.0),
+            attack_ms: self.attack_ms.clamp(0.1, 200.0),
+            release_ms: self.release_ms.clamp(10.0, 2000.0),
+            ceiling_db: self.ceiling_db.clamp(-12.0, 0.0),
+        }
+    }
+
+    /// Gain added after compression so the average level stays about the
+    /// same: half the reduction a full-scale signal gets.
+    fn makeup_db(&self) -> f64 {
+        -self.threshold_db as f64 * (1.0 - 1.0 / self.ratio as f64) / 2.0
+    }
+}
+
+/// Process-wide night mode switch and parameters, seeded from the settings
+/// file.
+pub struct Dynamics {
+    enabled: AtomicBool,
+    params: Mutex<DynamicsParams>,
+}
+
+static GLOBAL: OnceLock<Dynamics> = OnceLock::new();
+
+impl Dynamics {
+    pub fn global() -> &'static Dynamics {
+        GLOBAL.get_or_init(|| {
+            let settings = Settings::load();
+            let params = DynamicsParams {
+                threshold_db: settings.compressor_threshold,
+                ratio: settings.compressor_ratio,
+                attack_ms: settings.compressor_attack_ms,
+                release_ms: settings.compressor_release_ms,
+                ceiling_db: settings.limiter_ceiling,
+            };
+            Dynamics {
+                enabled: AtomicBool::new(settings.night_mode),
+                params: Mutex::new(params.sanitized()),
+            }
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Flip night mode on or off and persist it. Returns the new state.
+    pub fn toggle(&self) -> bool {
+        let enabled = !self.enabled.fetch_xor(true, Ordering::Relaxed);
+        self.save();
+        enabled
+    }
+
+    /// Switch night mode and replace the parameters (clamped to their
+    /// ranges) in one go, then persist them.
+    pub fn set(&self, enabled: bool, params: DynamicsParams) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        *self.params.lock().unwrap() = params.sanitized();
+        self.save();
+    }
+
+    pub fn params(&self) -> DynamicsParams {
+        *self.params.lock().unwrap()
+    }
+
+    fn save(&self) {
+        let params = self.params();
+        let mut settings = Settings::load();
+        settings.night_mode = self.is_enabled();
+        settings.compressor_threshold = params.threshold_db;
+        settings.compressor_ratio = params.ratio;
+        settings.compressor_attack_ms = params.attack_ms;
+        settings.compressor_release_ms = params.release_ms;
+        settings.limiter_ceiling = params.ceiling_db;
+        if let Err(err) = settings.save() {
+            eprintln!("warning: failed to save settings: {}", err);
+        }
+    }
+}
+
+/// Per-stream compressor and limiter. With night mode off, it leaves
+/// samples untouched.
+pub struct DynamicsProcessor {
+    /// Smoothed gain reduction of the compressor, in dB.
+    reduction_db: f64,
+    /// Current limiter gain, `1.0` = not limiting.
+    limiter_gain: f64,
+}
+
+impl DynamicsProcessor {
+    pub fn new() -> Self {
+        Self {
+            reduction_db: 0.0,
+            limiter_gain: 1.0,
+        }
+    }
+
+    /// When night mode is on, compress and limit one chunk of interleaved
+    /// samples in place.
+    pub fn process(&mut self, buffer: &mut [f32], channels: u16, sample_rate: u32) {
+        let dynamics = Dynamics::global();
+        if !dynamics.is_enabled() {
+            // Start from scratch when it is switched back on.
+            *self = Self::new();
+            return;
+        }
+        self.apply(buffer, channels, sample_rate, &dynamics.params());
+    }
+
+    fn apply(
+        &mut self,
+        buffer: &mut [f32],
+        channels: u16,
+        sample_rate: u32,
+        params: &DynamicsParams,
+    ) {
+        if buffer.is_empty() || channels == 0 || sample_rate == 0 {
+            return;
+        }
+        let rate = sample_rate as f64;
+        let attack = (-1000.0 / (params.attack_ms as f64 * rate)).exp();
+        let release = (-1000.0 / (params.release_ms as f64 * rate)).exp();
+        let limiter_release = (-1.0 / (LIMITER_RELEASE_SECS * rate)).exp();
+        let threshold = params.threshold_db as f64;
+        let slope = 1.0 - 1.0 / params.ratio as f64;
+        let makeup = params.makeup_db();
+        let ceiling = db_to_gain(params.ceiling_db as f64);
+
+        for frame in buffer.chunks_mut(channels as usize) {
+            // Channels are linked so the stereo image doesn't wander.
+            let peak = frame
+                .iter()
+                .fold(0.0f64, |peak, sample| peak.max((*sample as f64).abs()));
+
+            let target_db = (gain_to_db(peak) - threshold).max(0.0) * slope;
+            let coeff = if target_db > self.reduction_db {
+                attack
+            } else {
+                release
+            };
+            self.reduction_db = target_db + coeff * (self.reduction_db - target_db);
+            let gain = db_to_gain(makeup - self.reduction_db);
+
+            // Brick wall: clamp down instantly on anything the compressor
+            // let through above the ceiling, then ease back toward unity.
+            let needed = if peak * gain > ceiling {
+                ceiling / (peak * gain)
+            } else {
+                1.0
+            };
+            self.limiter_gain = (1.0 - (1.0 - self.limiter_gain) * limiter_release).min(needed);
+
+            let gain = gain * self.limiter_gain;
+            let ceiling = ceiling as f32;
+            for sample in frame.iter_mut() {
+                *sample = ((*sample as f64 * gain) as f32).clamp(-ceiling, ceiling);
+            }
+        }
+    }
+}
+
+fn db_to_gain(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+fn gain_to_db(gain: f64) -> f64 {
+    if gain > 0.0 {
+        (20.0 * gain.log10()).max(SILENCE_DB)
+    } else {
+        SILENCE_DB
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 44100;
+
+    fn sine(amplitude: f32, secs: f32) -> Vec<f32> {
+        let frames = (RATE as f32 * secs) as usize;
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f32 / RATE as f32;
+                let sample = amplitude * (2.0 * std::f32::consts::PI * 440.0 * t).sin();
+                [sample, sample]
+            })
+            .collect()
+    }
+
+    fn peak(samples: &[f32]) -> f64 {
+        samples
+            .iter()
+            .fold(0.0f64, |peak, sample| peak.max(sample.abs() as f64))
+    }
+
+    #[test]
+    fn loud_and_quiet_passages_end_up_closer_together() {
+        let params = DynamicsParams::NIGHT_MODE;
+        let mut loud = sine(1.0, 1.0);
+        let mut quiet = sine(0.01, 1.0);
+        DynamicsProcessor::new().apply(&mut loud, 2, RATE, &params);
+        DynamicsProcessor::new().apply(&mut quiet, 2, RATE, &params);
+
+        // Measure once the envelopes have settled.
+        let tail = loud.len() / 2;
+        let apart_db = gain_to_db(peak(&loud[tail..])) - gain_to_db(peak(&quiet[tail..]));
+        assert!(apart_db < 20.0, "still {} dB apart", apart_db);
+    }
+
+    #[test]
+    fn limiter_never_exceeds_the_ceiling() {
+        let params = DynamicsParams {
+            threshold_db: 0.0,
+            ratio: 1.0,
+            ceiling_db: -6.0,
+            ..DynamicsParams::NIGHT_MODE
+        };
+        let quiet = sine(0.2, 0.1);
+        let mut samples = quiet.clone();
+        samples.extend(sine(4.0, 0.1));
+        DynamicsProcessor::new().apply(&mut samples, 2, RATE, &params);
+
+        assert!(peak(&samples) <= db_to_gain(-6.0) + 1e-6);
+        // Below the ceiling, with the compressor neutral, nothing changes.
+        assert_eq!(&samples[..quiet.len()], &quiet[..]);
+    }
+}
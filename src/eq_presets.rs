@@ -0,0 +1,560 @@
+//! Named equalizer presets: a few built-ins plus the user's own, saved
+//! next to `favorites.json`.
+//!
+//! Presets only cover the 10 bands (cutoff, Q and gain), which is what both
+//! exchange formats describe: Rockbox `.cfg` files (`eq band 3 gain: -20`)
+//! and AutoEQ / Equalizer APO `ParametricEQ.txt` files
+//! (`Filter 2: ON PK Fc 250 Hz Gain -2.0 dB Q 1.41`). Band 0 is a low shelf
+//! and band 9 a high shelf, so a parametric file maps its low/high shelf
+//! filters onto those and its peaking filters onto bands 1–8.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Error};
+use directories::ProjectDirs;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{default_eq_band_settings, EqBand, Settings, EQ_BANDS};
+
+/// Gains of the built-in presets in tenths of dB, at the default ISO
+/// cutoffs (32 Hz … 16 kHz) and Q 0.7.
+const BUILT_IN: [(&str, [i32; EQ_BANDS]); 8] = [
+    ("Flat", [0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+    ("Rock", [50, 40, 30, 10, -10, -10, 10, 30, 40, 50]),
+    ("Pop", [-10, 0, 20, 40, 50, 40, 20, 0, -10, -10]),
+    ("Jazz", [40, 30, 10, 20, -15, -15, 0, 15, 30, 40]),
+    ("Classical", [40, 30, 20, 15, -10, -10, 0, 20, 30, 40]),
+    ("Voice", [-60, -40, -20, 10, 30, 40, 40, 30, 0, -20]),
+    ("Bass Boost", [60, 55, 45, 25, 0, 0, 0, 0, 0, 0]),
+    ("Treble Boost", [0, 0, 0, 0, 0, 10, 25, 45, 55, 60]),
+];
+
+/// Gain limit of a band in tenths of dB, as in [`crate::equalizer`].
+const MAX_GAIN: i32 = 240;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NamedPreset {
+    pub name: String,
+    pub bands: Vec<EqBand>,
+    /// Shipped with tunein; can't be overwritten or removed.
+    #[serde(skip)]
+    pub built_in: bool,
+}
+
+/// Exchange format of an exported or imported preset file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetFormat {
+    /// Rockbox `.cfg`.
+    Rockbox,
+    /// AutoEQ / Equalizer APO `ParametricEQ.txt`.
+    Parametric,
+}
+
+impl std::str::FromStr for PresetFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rockbox" | "cfg" => Ok(Self::Rockbox),
+            "autoeq" | "apo" | "parametric" => Ok(Self::Parametric),
+            other => Err(anyhow!(
+                "unknown preset format '{}', expected rockbox or autoeq",
+                other
+            )),
+        }
+    }
+}
+
+impl PresetFormat {
+    /// Guess the format from a file name: `.cfg` is Rockbox, anything else
+    /// parametric.
+    fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("cfg") => Self::Rockbox,
+            _ => Self::Parametric,
+        }
+    }
+
+    /// Guess the format from a file's contents.
+    fn detect(content: &str) -> Self {
+        let rockbox = content
+            .lines()
+            .any(|line| line.trim_start().starts_with("eq band"));
+        if rockbox {
+            Self::Rockbox
+        } else {
+            Self::Parametric
+        }
+    }
+}
+
+pub fn built_in() -> Vec<NamedPreset> {
+    BUILT_IN
+        .iter()
+        .map(|(name, gains)| NamedPreset {
+            name: name.to_string(),
+            bands: default_eq_band_settings()
+                .into_iter()
+                .zip(gains)
+                .map(|(band, &gain)| EqBand { gain, ..band })
+                .collect(),
+            built_in: true,
+        })
+        .collect()
+}
+
+/// File-backed store of the user's presets.
+pub struct PresetStore {
+    path: PathBuf,
+    presets: Vec<NamedPreset>,
+}
+
+impl PresetStore {
+    /// Load the user's presets from disk, falling back to none when the
+    /// file does not exist or is corrupted.
+    pub fn load() -> Result<Self, Error> {
+        let path = presets_path()?;
+        ensure_parent(&path)?;
+
+        let presets = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<Vec<NamedPreset>>(&content) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    eprintln!(
+                        "warning: EQ presets file corrupted ({}), starting fresh",
+                        err
+                    );
+                    Vec::new()
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(Error::from(err).context("failed to read EQ presets file")),
+        };
+
+        Ok(Self { path, presets })
+    }
+
+    /// Built-in presets followed by the user's.
+    pub fn all(&self) -> Vec<NamedPreset> {
+        let mut all = built_in();
+        all.extend(self.presets.iter().cloned());
+        all
+    }
+
+    /// Look a preset up by name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<NamedPreset> {
+        self.all()
+            .into_iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Save a user preset, replacing one with the same name.
+    pub fn save_preset(&mut self, name: &str, bands: Vec<EqBand>) -> Result<(), Error> {
+        if built_in()
+            .iter()
+            .any(|preset| preset.name.eq_ignore_ascii_case(name))
+        {
+            return Err(anyhow!(
+                "'{}' is a built-in preset, pick another name",
+                name
+            ));
+        }
+        self.presets
+            .retain(|preset| !preset.name.eq_ignore_ascii_case(name));
+        self.presets.push(NamedPreset {
+            name: name.to_string(),
+            bands,
+            built_in: false,
+        });
+        self.save()
+    }
+
+    /// Remove a user preset. Returns whether there was one.
+    pub fn remove(&mut self, name: &str) -> Result<bool, Error> {
+        let initial_len = self.presets.len();
+        self.presets
+            .retain(|preset| !preset.name.eq_ignore_ascii_case(name));
+        if self.presets.len() == initial_len {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let serialized = serde_json::to_string_pretty(&self.presets)
+            .context("failed to serialize EQ presets")?;
+        fs::write(&self.path, serialized).context("failed to write EQ presets file")
+    }
+}
+
+fn presets_path() -> Result<PathBuf, Error> {
+    let dirs = ProjectDirs::from("io", "tunein-cli", "tunein-cli")
+        .ok_or_else(|| Error::msg("unable to determine configuration directory"))?;
+    Ok(dirs.config_dir().join("eq_presets.json"))
+}
+
+fn ensure_parent(path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create configuration directory")?;
+    }
+    Ok(())
+}
+
+/// Parse a Rockbox `.cfg` file. Bands it doesn't mention keep the defaults;
+/// other settings (`eq enabled`, `eq precut`, …) are ignored.
+pub fn parse_rockbox(content: &str) -> Result<Vec<EqBand>, Error> {
+    let mut bands = default_eq_band_settings();
+    let mut found = false;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        let Some(rest) = line.strip_prefix("eq band ") else {
+            continue;
+        };
+        let (band, field, value) = parse_band_setting(rest)
+            .ok_or_else(|| anyhow!("line {}: can't parse '{}'", number + 1, line))?;
+        let band = bands
+            .get_mut(band)
+            .ok_or_else(|| anyhow!("line {}: there are only {} bands", number + 1, EQ_BANDS))?;
+        match field {
+            "cutoff" => band.cutoff = value,
+            "q" => band.q = value,
+            "gain" => band.gain = value,
+            other => {
+                return Err(anyhow!(
+                    "line {}: unknown band setting '{}'",
+                    number + 1,
+                    other
+                ))
+            }
+        }
+        found = true;
+    }
+    if !found {
+        return Err(anyhow!("no `eq band` settings found"));
+    }
+    Ok(sanitize(bands))
+}
+
+/// `3 gain: -20` → `(3, "gain", -20)`.
+fn parse_band_setting(setting: &str) -> Option<(usize, &str, i32)> {
+    let (key, value) = setting.split_once(':')?;
+    let mut key = key.split_whitespace();
+    let band = key.next()?.parse::<usize>().ok()?;
+    let field = key.next()?;
+    let value = value.trim().parse::<i32>().ok()?;
+    Some((band, field, value))
+}
+
+/// Write `bands` as a Rockbox `.cfg` file, with a precut that leaves room
+/// for the largest boost.
+pub fn write_rockbox(name: &str, bands: &[EqBand]) -> String {
+    let mut out = format!(
+        "# {}\neq enabled: on\neq precut: {}\n",
+        name,
+        headroom(bands)
+    );
+    for (index, band) in bands.iter().enumerate() {
+        out.push_str(&format!(
+            "eq band {index} cutoff: {}\neq band {index} q: {}\neq band {index} gain: {}\n",
+            band.cutoff, band.q, band.gain
+        ));
+    }
+    out
+}
+
+/// Filter types of a parametric file that fit the Rockbox bands.
+enum FilterKind {
+    LowShelf,
+    Peak,
+    HighShelf,
+}
+
+/// Parse an AutoEQ / Equalizer APO parametric file: at most one low shelf,
+/// eight peaking filters and one high shelf. Disabled filters and the
+/// preamp are ignored; bands left unused are flat.
+pub fn parse_parametric(content: &str) -> Result<Vec<EqBand>, Error> {
+    let mut low = None;
+    let mut high = None;
+    let mut peaks = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        let Some((label, filter)) = line.split_once(':') else {
+            continue;
+        };
+        if !label.trim_start().starts_with("Filter") {
+            continue;
+        }
+        let fields: Vec<&str> = filter.split_whitespace().collect();
+        if fields.first() != Some(&"ON") {
+            continue;
+        }
+        let kind = match fields.get(1).copied() {
+            Some("PK") | Some("PEQ") | Some("Modal") => FilterKind::Peak,
+            Some("LS") | Some("LSC") | Some("LSQ") => FilterKind::LowShelf,
+            Some("HS") | Some("HSC") | Some("HSQ") => FilterKind::HighShelf,
+            Some(other) => {
+                return Err(anyhow!(
+                    "line {}: {} filters can't be mapped onto the equalizer",
+                    number + 1,
+                    other
+                ))
+            }
+            None => continue,
+        };
+        let value = |name: &str| {
+            fields
+                .iter()
+                .position(|field| *field == name)
+                .and_then(|at| fields.get(at + 1))
+                .and_then(|value| value.parse::<f32>().ok())
+        };
+        let cutoff =
+            value("Fc").ok_or_else(|| anyhow!("line {}: filter has no frequency", number + 1))?;
+        let band = EqBand {
+            cutoff: cutoff.round() as i32,
+            q: (value("Q").unwrap_or(0.7) * 10.0).round() as i32,
+            gain: (value("Gain").unwrap_or(0.0) * 10.0).round() as i32,
+        };
+        let slot = match kind {
+            FilterKind::LowShelf => &mut low,
+            FilterKind::HighShelf => &mut high,
+            FilterKind::Peak => {
+                peaks.push(band);
+                continue;
+            }
+        };
+        if slot.replace(band).is_some() {
+            return Err(anyhow!(
+                "line {}: only one low and one high shelf fit",
+                number + 1
+            ));
+        }
+    }
+    if low.is_none() && high.is_none() && peaks.is_empty() {
+        return Err(anyhow!("no enabled filters found"));
+    }
+    if peaks.len() > EQ_BANDS - 2 {
+        return Err(anyhow!(
+            "{} peaking filters don't fit, at most {} can sit between the shelves",
+            peaks.len(),
+            EQ_BANDS - 2
+        ));
+    }
+
+    // Spare bands stay flat at their default cutoffs, in frequency order
+    // with the peaks so the popup's columns keep going up.
+    let defaults = default_eq_band_settings();
+    let flat = |band: &EqBand| EqBand { gain: 0, ..*band };
+    let spare = EQ_BANDS - 2 - peaks.len();
+    peaks.extend(defaults[1..=spare].iter().map(flat));
+    peaks.sort_by_key(|band| band.cutoff);
+    let mut bands = vec![low.unwrap_or_else(|| flat(&defaults[0]))];
+    bands.extend(peaks);
+    bands.push(high.unwrap_or_else(|| flat(&defaults[EQ_BANDS - 1])));
+    Ok(sanitize(bands))
+}
+
+/// Write `bands` as an AutoEQ / Equalizer APO parametric file, with a
+/// preamp that leaves room for the largest boost.
+pub fn write_parametric(bands: &[EqBand]) -> String {
+    let mut out = format!("Preamp: {:.1} dB\n", -(headroom(bands) as f32) / 10.0);
+    let last = bands.len().saturating_sub(1);
+    for (index, band) in bands.iter().enumerate() {
+        let kind = match index {
+            0 => "LSC",
+            i if i == last => "HSC",
+            _ => "PK",
+        };
+        out.push_str(&format!(
+            "Filter {}: ON {} Fc {} Hz Gain {:.1} dB Q {:.2}\n",
+            index + 1,
+            kind,
+            band.cutoff,
+            band.gain as f32 / 10.0,
+            band.q as f32 / 10.0
+        ));
+    }
+    out
+}
+
+/// Largest boost of any band in tenths of dB, `0` when nothing is boosted.
+fn headroom(bands: &[EqBand]) -> i32 {
+    bands.iter().map(|band| band.gain).max().unwrap_or(0).max(0)
+}
+
+/// Keep imported values inside what the DSP accepts.
+fn sanitize(bands: Vec<EqBand>) -> Vec<EqBand> {
+    bands
+        .into_iter()
+        .map(|band| EqBand {
+            cutoff: band.cutoff.clamp(20, 22000),
+            q: band.q.max(1),
+            gain: band.gain.clamp(-MAX_GAIN, MAX_GAIN),
+        })
+        .collect()
+}
+
+/// `tunein eq list`
+pub fn list() -> Result<(), Error> {
+    let store = PresetStore::load()?;
+    let current = Settings::load().eq_band_settings;
+    for preset in store.all() {
+        let mut line = preset.name.bright_green().to_string();
+        if preset.built_in {
+            line.push_str(" | built-in");
+        }
+        if preset.bands == current {
+            line.push_str(" | current");
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// `tunein eq apply <name>`: switch the settings-file EQ to a preset and
+/// turn it on.
+pub fn apply(name: &str) -> Result<(), Error> {
+    let preset = PresetStore::load()?
+        .find(name)
+        .ok_or_else(|| anyhow!("no EQ preset named '{}', see `tunein eq list`", name))?;
+    let mut settings = Settings::load();
+    settings.eq_enabled = true;
+    settings.eq_band_settings = preset.bands;
+    settings.save()?;
+    println!("Equalizer set to {}", preset.name);
+    Ok(())
+}
+
+/// `tunein eq save <name>`: keep the settings-file bands as a preset.
+pub fn save(name: &str) -> Result<(), Error> {
+    PresetStore::load()?.save_preset(name, Settings::load().eq_band_settings)?;
+    println!("Saved EQ preset {}", name);
+    Ok(())
+}
+
+/// `tunein eq remove <name>`
+pub fn remove(name: &str) -> Result<(), Error> {
+    match PresetStore::load()?.remove(name)? {
+        true => println!("EQ preset {} removed", name),
+        false => println!("No user EQ preset named {}", name),
+    }
+    Ok(())
+}
+
+/// `tunein eq export <name> <file>`; the format follows the file extension
+/// unless given.
+pub fn export(name: &str, path: &Path, format: Option<PresetFormat>) -> Result<(), Error> {
+    let preset = PresetStore::load()?
+        .find(name)
+        .ok_or_else(|| anyhow!("no EQ preset named '{}', see `tunein eq list`", name))?;
+    let content = match format.unwrap_or_else(|| PresetFormat::for_path(path)) {
+        PresetFormat::Rockbox => write_rockbox(&preset.name, &preset.bands),
+        PresetFormat::Parametric => write_parametric(&preset.bands),
+    };
+    fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Exported {} to {}", preset.name, path.display());
+    Ok(())
+}
+
+/// `tunein eq import <file>`: save a Rockbox or parametric file as a user
+/// preset, named after the file unless given a name.
+pub fn import(path: &Path, name: Option<&str>, format: Option<PresetFormat>) -> Result<(), Error> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let bands = match format.unwrap_or_else(|| PresetFormat::detect(&content)) {
+        PresetFormat::Rockbox => parse_rockbox(&content),
+        PresetFormat::Parametric => parse_parametric(&content),
+    }
+    .with_context(|| format!("failed to import {}", path.display()))?;
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| {
+                anyhow!(
+                    "can't name the preset after {}, pass --name",
+                    path.display()
+                )
+            })?
+            .to_string(),
+    };
+    PresetStore::load()?.save_preset(&name, bands)?;
+    println!("Imported EQ preset {}", name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rockbox_files_round_trip() {
+        let rock = built_in().remove(1);
+        let cfg = write_rockbox(&rock.name, &rock.bands);
+        assert!(cfg.contains("eq precut: 50\n"));
+        assert!(cfg.contains("eq band 4 gain: -10\n"));
+        assert_eq!(parse_rockbox(&cfg).unwrap(), rock.bands);
+        assert_eq!(PresetFormat::detect(&cfg), PresetFormat::Rockbox);
+    }
+
+    #[test]
+    fn maps_autoeq_filters_onto_the_bands() {
+        let autoeq = "Preamp: -6.4 dB\n\
+            Filter 1: ON LSC Fc 105 Hz Gain 5.8 dB Q 0.70\n\
+            Filter 2: ON PK Fc 3600 Hz Gain 4.1 dB Q 1.74\n\
+            Filter 3: ON PK Fc 150 Hz Gain -2.6 dB Q 0.53\n\
+            Filter 4: OFF PK Fc 900 Hz Gain 1.0 dB Q 1.00\n\
+            Filter 5: ON HSC Fc 10000 Hz Gain -1.5 dB Q 0.70\n";
+        let bands = parse_parametric(autoeq).unwrap();
+        assert_eq!(bands.len(), EQ_BANDS);
+        assert_eq!(
+            bands[0],
+            EqBand {
+                cutoff: 105,
+                q: 7,
+                gain: 58
+            }
+        );
+        let middle = &bands[1..9];
+        assert!(middle
+            .windows(2)
+            .all(|pair| pair[0].cutoff <= pair[1].cutoff));
+        assert!(middle.contains(&EqBand {
+            cutoff: 150,
+            q: 5,
+            gain: -26
+        }));
+        assert!(middle.contains(&EqBand {
+            cutoff: 3600,
+            q: 17,
+            gain: 41
+        }));
+        assert_eq!(middle.iter().filter(|band| band.gain == 0).count(), 6);
+        assert_eq!(
+            bands[9],
+            EqBand {
+                cutoff: 10000,
+                q: 7,
+                gain: -15
+            }
+        );
+
+        let written = write_parametric(&bands);
+        assert!(written.starts_with("Preamp: -5.8 dB\n"));
+        assert_eq!(parse_parametric(&written).unwrap(), bands);
+    }
+
+    #[test]
+    fn rejects_filters_that_do_not_fit() {
+        let peaks: String = (1..=9)
+            .map(|i| format!("Filter {i}: ON PK Fc {} Hz Gain 1.0 dB Q 1.00\n", i * 100))
+            .collect();
+        assert!(parse_parametric(&peaks).is_err());
+        assert!(parse_parametric("Filter 1: ON LP Fc 100 Hz\n").is_err());
+        assert!(parse_parametric("Preamp: -1 dB\n").is_err());
+    }
+}
@@ -1,34 +1,83 @@
 //! Equalizer popup shared by the `play` TUI and interactive mode.
 //!
-//! Owns only view state (visibility + selected band); the actual EQ values
-//! live in [`crate::equalizer::Equalizer::global`], which the audio thread
-//! reads, and every change is persisted straight to the settings file.
+//! Owns only view state (visibility, selected band, edit mode, the preset
+//! list and the spectrum to overlay on the response curve);
+//! the actual EQ values live in [`crate::equalizer::Equalizer::global`]
+//! (and the stereo controls in [`crate::stereo::Stereo::global`]), which
+//! the audio thread reads, and every change is persisted straight to the
+//! settings file.
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Rect},
     style::{Color, Modifier, Style},
+    symbols::Marker,
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph, Widget},
+    widgets::{Axis, Block, Borders, Chart, Clear, Dataset, GraphType, Paragraph, Widget},
     Frame,
 };
 
+use crate::eq_presets::{self, NamedPreset, PresetStore};
 use crate::equalizer::Equalizer;
 use crate::settings::EQ_BANDS;
+use crate::stereo::{self, Stereo};
 use crate::theme;
 
 /// Gain range in dB the vertical sliders map onto (± this many dB).
 const RANGE_DB: i32 = 24;
 
-/// Selectable columns: the 10 EQ bands plus the bass and treble shelves.
+/// Selectable columns: the 10 EQ bands, the bass and treble shelves, then
+/// the stereo controls.
 const BASS_COL: usize = EQ_BANDS;
 const TREBLE_COL: usize = EQ_BANDS + 1;
-const TOTAL_COLS: usize = EQ_BANDS + 2;
+const BALANCE_COL: usize = EQ_BANDS + 2;
+const WIDTH_COL: usize = EQ_BANDS + 3;
+const TOTAL_COLS: usize = EQ_BANDS + 5;
+
+/// Points the response curve is evaluated at, log-spaced over 20 Hz – 20 kHz.
+const CURVE_POINTS: usize = 160;
+const CURVE_RANGE_HZ: (f64, f64) = (20.0, 20_000.0);
+/// The response is drawn for this rate; the DSP runs at the stream's own,
+/// which only matters close to Nyquist.
+const CURVE_SAMPLE_RATE: f64 = 44_100.0;
+
+/// What ↑/↓ changes on the selected column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditMode {
+    Gain,
+    /// Cutoff frequency, in log steps (1/12 octave, 1/3 with shift).
+    Frequency,
+    /// Band Q, in log steps; the tone shelves have none.
+    Q,
+}
+
+impl EditMode {
+    fn next(self) -> Self {
+        match self {
+            Self::Gain => Self::Frequency,
+            Self::Frequency => Self::Q,
+            Self::Q => Self::Gain,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Gain => "gain",
+            Self::Frequency => "frequency",
+            Self::Q => "Q",
+        }
+    }
+}
 
 pub struct EqPopup {
     pub visible: bool,
     selected: usize,
+    mode: EditMode,
+    /// Built-in and saved presets, reloaded each time the popup opens.
+    presets: Vec<NamedPreset>,
+    /// Live spectrum as `(Hz, dB)` points, drawn behind the response curve.
+    spectrum: Option<Vec<(f64, f64)>>,
 }
 
 impl EqPopup {
@@ -36,11 +85,50 @@ impl EqPopup {
         Self {
             visible: false,
             selected: 0,
+            mode: EditMode::Gain,
+            presets: eq_presets::built_in(),
+            spectrum: None,
         }
     }
 
+    /// Overlay a live spectrum on the response curve, or stop with `None`.
+    /// Levels are relative: the loudest point is drawn at the top.
+    pub fn set_spectrum(&mut self, spectrum: Option<Vec<(f64, f64)>>) {
+        self.spectrum = spectrum;
+    }
+
     pub fn toggle(&mut self) {
         self.visible = !self.visible;
+        if self.visible {
+            if let Ok(store) = PresetStore::load() {
+                self.presets = store.all();
+            }
+        }
+    }
+
+    /// The preset the current bands match, if any.
+    fn current_preset(&self) -> Option<usize> {
+        let bands = Equalizer::global().bands();
+        self.presets.iter().position(|preset| preset.bands == bands)
+    }
+
+    /// Switch to the next (or, with `back`, previous) preset and turn the
+    /// EQ on.
+    fn cycle_preset(&mut self, back: bool) {
+        let count = self.presets.len();
+        if count == 0 {
+            return;
+        }
+        let next = match (self.current_preset(), back) {
+            (Some(current), false) => (current + 1) % count,
+            (Some(current), true) => (current + count - 1) % count,
+            (None, false) => 0,
+            (None, true) => count - 1,
+        };
+        let eq = Equalizer::global();
+        eq.set_bands(self.presets[next].bands.clone());
+        eq.set_enabled(true);
+        eq.save();
     }
 
     /// Handle a key while the popup is open. Returns `true` when the key
@@ -60,22 +148,54 @@ impl EqPopup {
             KeyCode::Right => self.selected = (self.selected + 1).min(TOTAL_COLS - 1),
             KeyCode::Up | KeyCode::Down => {
                 let sign = if key.code == KeyCode::Up { 1 } else { -1 };
-                match self.selected {
+                if self.selected >= BALANCE_COL {
+                    // The stereo columns have one value whatever the mode:
+                    // balance in 5% steps (20% coarse, up = right), width
+                    // in 10% steps (40% coarse), mono on with ↑, off with ↓.
+                    let stereo = Stereo::global();
+                    match self.selected {
+                        BALANCE_COL => stereo.adjust_balance(sign * if coarse { 20 } else { 5 }),
+                        WIDTH_COL => stereo.adjust_width(sign * if coarse { 40 } else { 10 }),
+                        _ => stereo.set_mono(sign > 0),
+                    }
+                    stereo.save();
+                    return true;
+                }
+                match (self.mode, self.selected) {
                     // Bands are in tenths of dB: 0.5 dB fine, 2 dB coarse.
-                    0..=9 => {
+                    (EditMode::Gain, 0..=9) => {
                         let step = if coarse { 20 } else { 5 };
                         eq.adjust_band_gain(self.selected, sign * step);
                     }
                     // Tone shelves are in whole dB: 1 dB fine, 4 dB coarse.
-                    BASS_COL => {
+                    (EditMode::Gain, BASS_COL) => {
                         eq.adjust_bass(sign * if coarse { 4 } else { 1 });
                     }
-                    _ => {
+                    (EditMode::Gain, _) => {
                         eq.adjust_treble(sign * if coarse { 4 } else { 1 });
                     }
+                    // Cutoffs move in twelfths of an octave: 1 fine, 4 coarse.
+                    (EditMode::Frequency, 0..=9) => {
+                        eq.adjust_band_cutoff(self.selected, sign * if coarse { 4 } else { 1 });
+                    }
+                    (EditMode::Frequency, BASS_COL) => {
+                        eq.adjust_bass_cutoff(sign * if coarse { 4 } else { 1 });
+                    }
+                    (EditMode::Frequency, _) => {
+                        eq.adjust_treble_cutoff(sign * if coarse { 4 } else { 1 });
+                    }
+                    // Q moves in sixths of a doubling: 1 fine, 3 coarse.
+                    (EditMode::Q, 0..=9) => {
+                        eq.adjust_band_q(self.selected, sign * if coarse { 3 } else { 1 });
+                    }
+                    (EditMode::Q, _) => return true,
                 }
                 eq.save();
             }
+            KeyCode::Char('m') | KeyCode::Tab => self.mode = self.mode.next(),
+            KeyCode::Char('g') => self.mode = EditMode::Gain,
+            KeyCode::Char('f') => self.mode = EditMode::Frequency,
+            KeyCode::Char('q') => self.mode = EditMode::Q,
             KeyCode::Char(' ') | KeyCode::Char('t') => {
                 eq.set_enabled(!eq.is_enabled());
                 eq.save();
@@ -84,6 +204,11 @@ impl EqPopup {
                 eq.reset_gains();
                 eq.save();
             }
+            KeyCode::Char('s') => {
+                eq.toggle_station_preset();
+            }
+            KeyCode::Char('p') => self.cycle_preset(false),
+            KeyCode::Char('P') => self.cycle_preset(true),
             _ => {}
         }
 
@@ -100,34 +225,78 @@ impl EqPopup {
         let enabled = eq.is_enabled();
 
         // Bands are stored in tenths of dB, the tone shelves in whole dB;
-        // normalize everything to tenths for the slider columns.
+        // normalize everything to tenths for the slider columns. The value
+        // above each bar is whatever the edit mode changes.
+        let mode = self.mode;
         let mut columns: Vec<SliderColumn> = eq
             .bands()
             .iter()
             .map(|b| SliderColumn {
                 gain_tenths: b.gain,
+                value: match mode {
+                    EditMode::Gain => format!("{:+.1}", b.gain as f32 / 10.0),
+                    EditMode::Frequency => fmt_hz(b.cutoff),
+                    EditMode::Q => format!("Q{:.1}", b.q as f32 / 10.0),
+                },
                 label: fmt_hz(b.cutoff),
                 dimmed: !enabled,
             })
             .collect();
+        let shelf_value = |gain: i32, cutoff: i32| match mode {
+            EditMode::Gain => format!("{:+.1}", gain as f32),
+            EditMode::Frequency => fmt_hz(cutoff),
+            EditMode::Q => "-".to_string(),
+        };
         columns.push(SliderColumn {
             gain_tenths: eq.bass() * 10,
+            value: shelf_value(eq.bass(), eq.bass_cutoff()),
             label: "Bass".to_string(),
             dimmed: eq.bass() == 0,
         });
         columns.push(SliderColumn {
             gain_tenths: eq.treble() * 10,
+            value: shelf_value(eq.treble(), eq.treble_cutoff()),
             label: "Treble".to_string(),
             dimmed: eq.treble() == 0,
         });
 
-        let area = centered_rect(frame.size(), 80, 20);
+        // Balance and width share the ±RANGE_DB scale of the bars: full
+        // height is 100% to one side, or 0% / 200% width.
+        let stereo = Stereo::global();
+        let full = RANGE_DB * 10;
+        columns.push(SliderColumn {
+            gain_tenths: stereo.balance() * full / 100,
+            value: stereo::fmt_balance(stereo.balance()),
+            label: "Bal".to_string(),
+            dimmed: stereo.balance() == 0,
+        });
+        columns.push(SliderColumn {
+            gain_tenths: (stereo.width() - 100) * full / 100,
+            value: format!("{}%", stereo.width()),
+            label: "Width".to_string(),
+            dimmed: stereo.is_mono() || stereo.width() == 100,
+        });
+        columns.push(SliderColumn {
+            gain_tenths: if stereo.is_mono() { full } else { 0 },
+            value: if stereo.is_mono() { "on" } else { "off" }.to_string(),
+            label: "Mono".to_string(),
+            dimmed: !stereo.is_mono(),
+        });
+
+        let area = centered_rect(frame.size(), 96, 30);
         frame.render_widget(Clear, area);
 
+        let mut scope = String::new();
+        if let Some(preset) = self.current_preset() {
+            scope.push_str(&format!(" · {}", self.presets[preset].name));
+        }
+        if eq.has_station_preset() {
+            scope.push_str(" · station preset");
+        }
         let title = if enabled {
-            format!(" Equalizer — ON (±{} dB) ", RANGE_DB)
+            format!(" Equalizer — ON (±{} dB){} ", RANGE_DB, scope)
         } else {
-            format!(" Equalizer — OFF (±{} dB) ", RANGE_DB)
+            format!(" Equalizer — OFF (±{} dB){} ", RANGE_DB, scope)
         };
         let block = Block::new()
             .borders(Borders::ALL)
@@ -146,7 +315,7 @@ impl EqPopup {
             return;
         }
 
-        // Bottom row of the popup: key hints.
+        // Bottom row of the popup: the edit mode and key hints.
         let hints = Rect {
             x: inner.x,
             y: inner.y + inner.height - 1,
@@ -154,19 +323,43 @@ impl EqPopup {
             height: 1,
         };
         frame.render_widget(
-            Paragraph::new(Line::from(Span::styled(
-                "←/→ select  ↑/↓ adjust (shift: coarse)  space eq on/off  0 reset  esc close",
-                Style::default().fg(Color::DarkGray),
-            )))
+            Paragraph::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", mode.name()),
+                    Style::default()
+                        .fg(theme::ACCENT)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    "←/→ ↑/↓ adjust (shift: coarse)  m mode  space on/off  0 reset  p preset  s station",
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
             .alignment(Alignment::Center),
             hints,
         );
 
+        // The response curve takes the top third when there is room for it.
+        let curve_height = if inner.height >= 18 {
+            (inner.height / 3).min(10)
+        } else {
+            0
+        };
+        if curve_height > 0 {
+            self.render_curve(
+                frame,
+                Rect {
+                    height: curve_height,
+                    ..inner
+                },
+            );
+        }
+
         let sliders_area = Rect {
             x: inner.x,
-            y: inner.y,
+            y: inner.y + curve_height,
             width: inner.width,
-            height: inner.height - 1,
+            height: inner.height - 1 - curve_height,
         };
         frame.render_widget(
             EqSliders {
@@ -178,15 +371,92 @@ impl EqPopup {
     }
 }
 
-/// One slider column, drawn with vertical block characters: a dB value
-/// above the bar, a Hz/kHz (or "Bass"/"Treble") label below, and a
+impl EqPopup {
+    /// Draw the combined response of every active filter on a log frequency
+    /// axis, over the live spectrum if there is one.
+    fn render_curve(&self, frame: &mut Frame, area: Rect) {
+        let eq = Equalizer::global();
+        let (low, high) = (CURVE_RANGE_HZ.0.ln(), CURVE_RANGE_HZ.1.ln());
+        let freqs: Vec<f64> = (0..CURVE_POINTS)
+            .map(|i| (low + (high - low) * i as f64 / (CURVE_POINTS - 1) as f64).exp())
+            .collect();
+        let range = RANGE_DB as f64;
+        let response: Vec<(f64, f64)> = freqs
+            .iter()
+            .zip(eq.response_db(CURVE_SAMPLE_RATE, &freqs))
+            .map(|(freq, db)| (freq.ln(), db.clamp(-range, range)))
+            .collect();
+        let zero = [(low, 0.0), (high, 0.0)];
+        let spectrum: Vec<(f64, f64)> = match &self.spectrum {
+            Some(points) => {
+                let peak = points
+                    .iter()
+                    .map(|&(_, db)| db)
+                    .fold(f64::NEG_INFINITY, f64::max);
+                points
+                    .iter()
+                    .filter(|&&(freq, _)| freq >= CURVE_RANGE_HZ.0 && freq <= CURVE_RANGE_HZ.1)
+                    .map(|&(freq, db)| (freq.ln(), (db - peak + range).clamp(-range, range)))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        let mut datasets = vec![Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::DarkGray))
+            .data(&zero)];
+        if !spectrum.is_empty() {
+            datasets.push(
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(theme::ACCENT))
+                    .data(&spectrum),
+            );
+        }
+        datasets.push(
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(
+                    Style::default()
+                        .fg(theme::PRIMARY)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .data(&response),
+        );
+
+        let label = |text: &'static str| Span::styled(text, Style::default().fg(Color::DarkGray));
+        let chart = Chart::new(datasets)
+            .x_axis(Axis::default().bounds([low, high]).labels(vec![
+                label("20"),
+                label("200"),
+                label("2k"),
+                label("20k"),
+            ]))
+            .y_axis(Axis::default().bounds([-range, range]).labels(vec![
+                label("-24"),
+                label("0"),
+                label("+24"),
+            ]));
+        frame.render_widget(chart, area);
+    }
+}
+
+/// One slider column, drawn with vertical block characters: a value
+/// above the bar, a Hz/kHz (or "Bass"/"Treble", "Bal"…) label below, and a
 /// highlight on the selected column.
 struct SliderColumn {
     /// Gain in tenths of dB.
     gain_tenths: i32,
+    /// Text above the bar: the gain, cutoff or Q, by edit mode.
+    value: String,
     /// Text under the bar.
     label: String,
-    /// Draw the bar muted (EQ off for bands, 0 dB for the tone shelves).
+    /// Draw the bar muted (EQ off for bands, 0 dB for the tone shelves,
+    /// neutral for the stereo controls).
     dimmed: bool,
 }
 
@@ -220,8 +490,9 @@ impl<'a> Widget for EqSliders<'a> {
                 break;
             }
 
-            // Separate the tone shelves from the EQ bands visually.
-            if i == EQ_BANDS {
+            // Separate the tone shelves and the stereo controls from the
+            // EQ bands visually.
+            if i == BASS_COL || i == BALANCE_COL {
                 for r in area.y..area.y + area.height {
                     buf.get_mut(col_x, r)
                         .set_char('┆')
@@ -275,7 +546,7 @@ impl<'a> Widget for EqSliders<'a> {
                 Style::default()
             };
 
-            Paragraph::new(Span::styled(format!("{:+.1}", gain_db), label_style))
+            Paragraph::new(Span::styled(column.value.clone(), label_style))
                 .alignment(Alignment::Center)
                 .render(Rect::new(col_x, area.y, col_w, 1), buf);
 
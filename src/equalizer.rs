@@ -1,15 +1,46 @@
-//! Process-wide 10-band equalizer backed by the Rockbox DSP.
+//! 10-band equalizer with Rockbox-style bass and treble shelves.
 //!
-//! The UI mutates one global [`Equalizer`]; every [`crate::decoder::StreamDecoder`]
-//! owns an [`EqProcessor`] that watches the global's version counter and
-//! routes decoded packets through `rockbox_dsp::Dsp` when the EQ is enabled.
+//! The UI mutates one global [`Equalizer`]; each audio pipeline (a
+//! [`crate::mixer::Mixer`], or a bare [`crate::decoder::StreamDecoder`])
+//! owns an [`EqProcessor`] that watches its equalizer's version counter and
+//! runs decoded packets through a chain of [`Biquad`]s when the EQ is
+//! active. Filter state is per processor and everything stays in f32, so
+//! any number of pipelines run concurrently without sharing a lock, and a
+//! pipeline can follow its own [`Equalizer`] instead of the global one
+//! (e.g. a relay channel with its own preset).
+//!
+//! A station can have its own [`EqPreset`], kept in
+//! [`crate::station_memory`]: it replaces the settings-file EQ while that
+//! station plays, and edits made meanwhile are saved to it instead.
 
 use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
 
-use rockbox_dsp::{eq_band_setting, Dsp, EQ_NUM_BANDS};
+use crate::biquad::{Biquad, BiquadState};
+use crate::settings::{EqBand, Settings};
+use crate::station_memory::StationMemory;
+
+/// The user-adjustable part of the equalizer, as saved for a station.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EqPreset {
+    pub enabled: bool,
+    pub bands: Vec<EqBand>,
+    pub bass: i32,
+    pub treble: i32,
+}
 
-use crate::settings::{EqBand, Settings, EQ_BANDS};
+impl EqPreset {
+    fn from_settings(settings: &Settings) -> Self {
+        Self {
+            enabled: settings.eq_enabled,
+            bands: settings.eq_band_settings.clone(),
+            bass: settings.bass,
+            treble: settings.treble,
+        }
+    }
+}
 
 /// Shared equalizer state. Cheap to read from the audio thread: the
 /// enabled flag and version counter are atomics, so the per-packet hot
@@ -22,31 +53,69 @@ pub struct Equalizer {
     /// is independent of the EQ on/off switch: 0 dB means off.
     bass: AtomicI32,
     treble: AtomicI32,
-    /// Shelf cutoffs in Hz, 0 = Rockbox defaults (200 / 3500). Only
-    /// settable via the settings file.
+    /// Shelf cutoffs in Hz, 0 = Rockbox defaults (200 / 3500).
     bass_cutoff: AtomicI32,
     treble_cutoff: AtomicI32,
+    /// Provider and id of the station on air.
+    station: Mutex<Option<(String, String)>>,
+    /// Whether the EQ in use is that station's own preset.
+    station_preset: AtomicBool,
 }
 
-static GLOBAL: OnceLock<Equalizer> = OnceLock::new();
+static GLOBAL: OnceLock<Arc<Equalizer>> = OnceLock::new();
+
+/// Band cutoff range in Hz.
+const CUTOFF_RANGE: (i32, i32) = (20, 20_000);
+/// Shelf cutoff ranges in Hz, and the Rockbox defaults a `0` stands for.
+const BASS_CUTOFF_RANGE: (i32, i32) = (20, 1_000);
+const TREBLE_CUTOFF_RANGE: (i32, i32) = (1_000, 20_000);
+const DEFAULT_BASS_CUTOFF: i32 = 200;
+const DEFAULT_TREBLE_CUTOFF: i32 = 3500;
+/// Q range in tenths (0.1 … 6.4), as Rockbox allows.
+const Q_RANGE: (i32, i32) = (1, 64);
+/// Cutoff steps per octave, and Q steps per doubling.
+const CUTOFF_STEPS_PER_OCTAVE: f32 = 12.0;
+const Q_STEPS_PER_DOUBLING: f32 = 6.0;
 
 impl Equalizer {
     /// The process-wide equalizer, seeded from the settings file on first use.
     pub fn global() -> &'static Equalizer {
+        Self::shared_ref()
+    }
+
+    /// The process-wide equalizer, for an [`EqProcessor`] to hold on to.
+    pub fn shared() -> Arc<Equalizer> {
+        Self::shared_ref().clone()
+    }
+
+    fn shared_ref() -> &'static Arc<Equalizer> {
         GLOBAL.get_or_init(|| {
             let settings = Settings::load();
-            Equalizer {
-                enabled: AtomicBool::new(settings.eq_enabled),
-                version: AtomicU64::new(0),
-                bands: Mutex::new(settings.eq_band_settings),
-                bass: AtomicI32::new(settings.bass),
-                treble: AtomicI32::new(settings.treble),
-                bass_cutoff: AtomicI32::new(settings.bass_cutoff),
-                treble_cutoff: AtomicI32::new(settings.treble_cutoff),
-            }
+            let eq = Self::with_preset(&EqPreset::from_settings(&settings));
+            eq.bass_cutoff
+                .store(settings.bass_cutoff, Ordering::Relaxed);
+            eq.treble_cutoff
+                .store(settings.treble_cutoff, Ordering::Relaxed);
+            Arc::new(eq)
         })
     }
 
+    /// An equalizer of its own running `preset`, with the default shelf
+    /// cutoffs. Not persisted anywhere.
+    pub fn with_preset(preset: &EqPreset) -> Self {
+        Equalizer {
+            enabled: AtomicBool::new(preset.enabled),
+            version: AtomicU64::new(0),
+            bands: Mutex::new(preset.bands.clone()),
+            bass: AtomicI32::new(preset.bass.clamp(-24, 24)),
+            treble: AtomicI32::new(preset.treble.clamp(-24, 24)),
+            bass_cutoff: AtomicI32::new(0),
+            treble_cutoff: AtomicI32::new(0),
+            station: Mutex::new(None),
+            station_preset: AtomicBool::new(false),
+        }
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.enabled.load(Ordering::Relaxed)
     }
@@ -114,6 +183,145 @@ impl Equalizer {
         gain
     }
 
+    /// Move one band's cutoff by `steps` twelfths of an octave, within
+    /// 20 Hz – 20 kHz and strictly between its neighbours' cutoffs so the
+    /// bands stay in order. Returns the new cutoff in Hz.
+    pub fn adjust_band_cutoff(&self, band: usize, steps: i32) -> i32 {
+        let mut bands = self.bands.lock().unwrap();
+        let Some(current) = bands.get(band).map(|b| b.cutoff) else {
+            return 0;
+        };
+        let cutoff = step_cutoff(&bands, band, steps);
+        bands[band].cutoff = cutoff;
+        drop(bands);
+        if cutoff != current {
+            self.version.fetch_add(1, Ordering::Relaxed);
+        }
+        cutoff
+    }
+
+    /// Scale one band's Q by `steps` sixths of a doubling, within 0.1 – 6.4.
+    /// Returns the new Q × 10.
+    pub fn adjust_band_q(&self, band: usize, steps: i32) -> i32 {
+        let mut bands = self.bands.lock().unwrap();
+        let q = if let Some(b) = bands.get_mut(band) {
+            b.q = log_step(b.q, steps, Q_STEPS_PER_DOUBLING, Q_RANGE);
+            b.q
+        } else {
+            0
+        };
+        drop(bands);
+        self.version.fetch_add(1, Ordering::Relaxed);
+        q
+    }
+
+    /// Bass shelf cutoff in Hz, with the Rockbox default filled in.
+    pub fn bass_cutoff(&self) -> i32 {
+        match self.bass_cutoff.load(Ordering::Relaxed) {
+            0 => DEFAULT_BASS_CUTOFF,
+            hz => hz,
+        }
+    }
+
+    /// Treble shelf cutoff in Hz, with the Rockbox default filled in.
+    pub fn treble_cutoff(&self) -> i32 {
+        match self.treble_cutoff.load(Ordering::Relaxed) {
+            0 => DEFAULT_TREBLE_CUTOFF,
+            hz => hz,
+        }
+    }
+
+    /// Move the bass shelf cutoff by `steps` twelfths of an octave, within
+    /// 20 Hz – 1 kHz.
+    pub fn adjust_bass_cutoff(&self, steps: i32) {
+        let hz = log_step(
+            self.bass_cutoff(),
+            steps,
+            CUTOFF_STEPS_PER_OCTAVE,
+            BASS_CUTOFF_RANGE,
+        );
+        self.bass_cutoff.store(hz, Ordering::Relaxed);
+        self.version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Move the treble shelf cutoff by `steps` twelfths of an octave, within
+    /// 1 – 20 kHz.
+    pub fn adjust_treble_cutoff(&self, steps: i32) {
+        let hz = log_step(
+            self.treble_cutoff(),
+            steps,
+            CUTOFF_STEPS_PER_OCTAVE,
+            TREBLE_CUTOFF_RANGE,
+        );
+        self.treble_cutoff.store(hz, Ordering::Relaxed);
+        self.version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Every filter stage at `sample_rate`, in processing order: one per
+    /// band, then the bass and treble shelves. Inactive stages (the bands
+    /// while the EQ is off or flat, a shelf at 0 dB) are `None`, so a stage
+    /// keeps its position while others come and go.
+    fn stages(&self, sample_rate: f64) -> Vec<Option<Biquad>> {
+        let enabled = self.is_enabled();
+        let bands = self.bands();
+        let last = bands.len().saturating_sub(1);
+        let mut stages: Vec<Option<Biquad>> = bands
+            .iter()
+            .enumerate()
+            .map(|(i, band)| {
+                if !enabled || band.gain == 0 {
+                    return None;
+                }
+                let (cutoff, q, gain) = (
+                    band.cutoff as f64,
+                    band.q.max(1) as f64 / 10.0,
+                    band.gain as f64 / 10.0,
+                );
+                Some(match i {
+                    0 => Biquad::low_shelf(sample_rate, cutoff, q, gain),
+                    i if i == last => Biquad::high_shelf(sample_rate, cutoff, q, gain),
+                    _ => Biquad::peaking(sample_rate, cutoff, q, gain),
+                })
+            })
+            .collect();
+        stages.push((self.bass() != 0).then(|| {
+            Biquad::low_shelf_first_order(
+                sample_rate,
+                self.bass_cutoff() as f64,
+                self.bass() as f64,
+            )
+        }));
+        stages.push((self.treble() != 0).then(|| {
+            Biquad::high_shelf_first_order(
+                sample_rate,
+                self.treble_cutoff() as f64,
+                self.treble() as f64,
+            )
+        }));
+        stages
+    }
+
+    /// The filters an [`EqProcessor`] runs at `sample_rate`: the bands when
+    /// the EQ is on, then whichever tone shelves are nonzero. Flat bands
+    /// are left out.
+    pub fn filters(&self, sample_rate: f64) -> Vec<Biquad> {
+        self.stages(sample_rate).into_iter().flatten().collect()
+    }
+
+    /// Combined gain in dB of every active stage at each of `freqs`.
+    pub fn response_db(&self, sample_rate: f64, freqs: &[f64]) -> Vec<f64> {
+        let filters = self.filters(sample_rate);
+        freqs
+            .iter()
+            .map(|&freq| {
+                filters
+                    .iter()
+                    .map(|filter| filter.magnitude_db(sample_rate, freq))
+                    .sum()
+            })
+            .collect()
+    }
+
     /// Reset every band's gain and the tone shelves to 0 dB (cutoffs and
     /// Q are kept).
     pub fn reset_gains(&self) {
@@ -127,98 +335,214 @@ impl Equalizer {
         self.version.fetch_add(1, Ordering::Relaxed);
     }
 
-    /// Persist the current state to the settings file.
-    pub fn save(&self) {
-        let settings = Settings {
-            eq_enabled: self.is_enabled(),
-            eq_band_settings: self.bands(),
+    /// Snapshot of the gains and the on/off switch.
+    pub fn preset(&self) -> EqPreset {
+        EqPreset {
+            enabled: self.is_enabled(),
+            bands: self.bands(),
             bass: self.bass(),
             treble: self.treble(),
-            bass_cutoff: self.bass_cutoff.load(Ordering::Relaxed),
-            treble_cutoff: self.treble_cutoff.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Switch to `preset` (the shelf cutoffs are kept).
+    pub fn apply(&self, preset: &EqPreset) {
+        self.enabled.store(preset.enabled, Ordering::Relaxed);
+        *self.bands.lock().unwrap() = preset.bands.clone();
+        self.bass
+            .store(preset.bass.clamp(-24, 24), Ordering::Relaxed);
+        self.treble
+            .store(preset.treble.clamp(-24, 24), Ordering::Relaxed);
+        self.version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A station started: load its preset if it has one, otherwise go back
+    /// to the settings-file EQ when the previous station had its own.
+    pub fn use_station(&self, provider: &str, id: &str) {
+        *self.station.lock().unwrap() = Some((provider.to_string(), id.to_string()));
+        let preset = StationMemory::load()
+            .ok()
+            .and_then(|memory| memory.get(provider, id).and_then(|s| s.eq.clone()));
+        match preset {
+            Some(preset) => {
+                self.apply(&preset);
+                self.station_preset.store(true, Ordering::Relaxed);
+            }
+            None if self.has_station_preset() => {
+                self.apply(&EqPreset::from_settings(&Settings::load()));
+                self.station_preset.store(false, Ordering::Relaxed);
+            }
+            None => {}
+        }
+    }
+
+    /// Whether the EQ in use belongs to the station on air.
+    pub fn has_station_preset(&self) -> bool {
+        self.station_preset.load(Ordering::Relaxed)
+    }
+
+    /// Save the current EQ as the station's own preset, or forget the
+    /// station's preset and go back to the settings-file EQ. Returns
+    /// whether the station now has a preset, `None` when nothing plays.
+    pub fn toggle_station_preset(&self) -> Option<bool> {
+        let (provider, id) = self.station.lock().unwrap().clone()?;
+        let mut memory = match StationMemory::load() {
+            Ok(memory) => memory,
+            Err(err) => {
+                eprintln!("warning: failed to load station settings: {}", err);
+                return None;
+            }
         };
+        let linked = !self.has_station_preset();
+        let preset = linked.then(|| self.preset());
+        if let Err(err) = memory.set_eq(&provider, &id, preset) {
+            eprintln!("warning: failed to save station settings: {}", err);
+            return None;
+        }
+        if !linked {
+            self.apply(&EqPreset::from_settings(&Settings::load()));
+        }
+        self.station_preset.store(linked, Ordering::Relaxed);
+        Some(linked)
+    }
+
+    /// Persist the current state: to the station's preset when it has one,
+    /// otherwise to the settings file, keeping the non-EQ settings as they
+    /// are on disk.
+    pub fn save(&self) {
+        if self.has_station_preset() {
+            if let Some((provider, id)) = self.station.lock().unwrap().clone() {
+                let saved = StationMemory::load()
+                    .and_then(|mut memory| memory.set_eq(&provider, &id, Some(self.preset())));
+                if let Err(err) = saved {
+                    eprintln!("warning: failed to save station settings: {}", err);
+                }
+                return;
+            }
+        }
+        let mut settings = Settings::load();
+        settings.eq_enabled = self.is_enabled();
+        settings.eq_band_settings = self.bands();
+        settings.bass = self.bass();
+        settings.treble = self.treble();
+        settings.bass_cutoff = self.bass_cutoff.load(Ordering::Relaxed);
+        settings.treble_cutoff = self.treble_cutoff.load(Ordering::Relaxed);
         if let Err(err) = settings.save() {
             eprintln!("warning: failed to save settings: {}", err);
         }
     }
 }
 
-/// `rockbox_dsp::Dsp` wraps a process-wide singleton and holds a raw
-/// pointer, so it is not `Send`. Decoding happens on whichever thread
-/// rodio pulls samples from, so we need to move it there; `DSP_CALL`
-/// serializes every use across threads, which makes that sound.
-struct SendDsp(Dsp);
-unsafe impl Send for SendDsp {}
+/// `value` scaled by `steps` of `1 / per_doubling` of a doubling, moving by
+/// at least one unit per step so small values don't get stuck, and clamped
+/// to `range`.
+fn log_step(value: i32, steps: i32, per_doubling: f32, range: (i32, i32)) -> i32 {
+    let scaled = (value.max(1) as f32 * 2f32.powf(steps as f32 / per_doubling)).round() as i32;
+    let stepped = match steps.signum() {
+        1 => scaled.max(value + 1),
+        -1 => scaled.min(value - 1),
+        _ => value,
+    };
+    stepped.clamp(range.0, range.1)
+}
 
-/// Serializes all access to the Rockbox DSP singleton. Two decoders can
-/// briefly coexist during a station switch; without this their configure
-/// and process calls could interleave on the shared C state.
-static DSP_CALL: Mutex<()> = Mutex::new(());
+/// The cutoff band `band` of `bands` moves to by `steps` twelfths of an
+/// octave: inside [`CUTOFF_RANGE`] and strictly between its neighbours. A
+/// band already squeezed between neighbours 1 Hz apart stays put.
+fn step_cutoff(bands: &[EqBand], band: usize, steps: i32) -> i32 {
+    let current = bands[band].cutoff;
+    let low = match band {
+        0 => CUTOFF_RANGE.0,
+        _ => (bands[band - 1].cutoff + 1).max(CUTOFF_RANGE.0),
+    };
+    let high = match bands.get(band + 1) {
+        Some(next) => (next.cutoff - 1).min(CUTOFF_RANGE.1),
+        None => CUTOFF_RANGE.1,
+    };
+    if low > high {
+        return current;
+    }
+    log_step(current, steps, CUTOFF_STEPS_PER_OCTAVE, CUTOFF_RANGE).clamp(low, high)
+}
 
-/// Per-decoder handle that lazily instantiates the DSP and keeps it in
-/// sync with the global [`Equalizer`].
+/// Per-pipeline equalizer: the filter chain of one [`Equalizer`] with its
+/// own per-channel history, rebuilt whenever the settings change.
 pub struct EqProcessor {
-    dsp: Option<SendDsp>,
-    dsp_rate: u32,
+    equalizer: Arc<Equalizer>,
+    sample_rate: u32,
+    channels: u16,
     applied_version: u64,
-    out: Vec<i16>,
+    stages: Vec<Option<Biquad>>,
+    /// Filter history, `[stage][channel]`.
+    states: Vec<Vec<BiquadState>>,
 }
 
 impl EqProcessor {
+    /// A processor following the global [`Equalizer`].
     pub fn new() -> Self {
+        Self::with_equalizer(Equalizer::shared())
+    }
+
+    /// A processor following `equalizer` instead of the global one.
+    pub fn with_equalizer(equalizer: Arc<Equalizer>) -> Self {
         Self {
-            dsp: None,
-            dsp_rate: 0,
+            equalizer,
+            sample_rate: 0,
+            channels: 0,
             applied_version: u64::MAX,
-            out: Vec::new(),
+            stages: Vec::new(),
+            states: Vec::new(),
         }
     }
 
-    /// Run one decoded packet of interleaved samples through the EQ if it
-    /// is enabled. `buffer` is replaced with the processed samples and the
-    /// (possibly upmixed) channel count is returned; mono input comes back
-    /// stereo because the Rockbox pipeline is configured for interleaved
-    /// stereo.
-    pub fn process(&mut self, buffer: &mut Vec<i16>, channels: u16, sample_rate: u32) -> u16 {
-        let eq = Equalizer::global();
-        if !eq.is_active() || buffer.is_empty() || channels == 0 || channels > 2 {
-            return channels;
+    /// Run one decoded packet of interleaved samples through the EQ in
+    /// place, if it is active. Every channel is filtered the same way.
+    pub fn process(&mut self, buffer: &mut [f32], channels: u16, sample_rate: u32) {
+        if !self.equalizer.is_active() || buffer.is_empty() || channels == 0 {
+            return;
         }
 
-        let _guard = DSP_CALL.lock().unwrap();
-
-        // (Re)create the DSP when first needed or when the stream's sample
-        // rate changed; creation resets the singleton config, so the
-        // settings must be reapplied afterwards.
-        let version = eq.version();
-        if self.dsp.is_none() || self.dsp_rate != sample_rate {
-            let mut dsp = Dsp::new(sample_rate);
-            apply_settings(&mut dsp, eq);
-            self.dsp = Some(SendDsp(dsp));
-            self.dsp_rate = sample_rate;
-            self.applied_version = version;
-        } else if self.applied_version != version {
-            let dsp = &mut self.dsp.as_mut().unwrap().0;
-            apply_settings(dsp, eq);
+        if self.sample_rate != sample_rate || self.channels != channels {
+            // A different stream: its history has nothing to do with ours.
+            self.sample_rate = sample_rate;
+            self.channels = channels;
+            self.stages.clear();
+            self.states.clear();
+            self.applied_version = u64::MAX;
+        }
+        let version = self.equalizer.version();
+        if self.applied_version != version {
+            self.update_stages();
             self.applied_version = version;
         }
 
-        // The pipeline expects interleaved stereo; duplicate mono samples.
-        if channels == 1 {
-            let mono = std::mem::take(buffer);
-            buffer.reserve(mono.len() * 2);
-            for s in mono {
-                buffer.push(s);
-                buffer.push(s);
+        for frame in buffer.chunks_exact_mut(channels as usize) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let mut x = *sample as f64;
+                for (stage, states) in self.stages.iter().zip(self.states.iter_mut()) {
+                    if let Some(filter) = stage {
+                        x = filter.process(&mut states[channel], x);
+                    }
+                }
+                *sample = x as f32;
             }
         }
+    }
 
-        let dsp = &mut self.dsp.as_mut().unwrap().0;
-        self.out.clear();
-        dsp.process(buffer, &mut self.out);
-        std::mem::swap(buffer, &mut self.out);
-
-        2
+    /// Recompute the coefficients. A stage that keeps running keeps its
+    /// history, so moving a slider doesn't click; one that just came on
+    /// starts from silence.
+    fn update_stages(&mut self) {
+        let stages = self.equalizer.stages(self.sample_rate as f64);
+        let fresh = vec![BiquadState::default(); self.channels as usize];
+        self.states.resize(stages.len(), fresh.clone());
+        for (i, stage) in stages.iter().enumerate() {
+            let was_running = self.stages.get(i).is_some_and(Option::is_some);
+            if stage.is_some() && !was_running {
+                self.states[i] = fresh.clone();
+            }
+        }
+        self.stages = stages;
     }
 }
 
@@ -227,22 +551,106 @@ mod tests {
     use super::*;
     use crate::settings::default_eq_band_settings;
 
-    fn sine_stereo(freq_hz: f64, rate: u32, frames: usize) -> Vec<i16> {
+    fn sine_stereo(freq_hz: f64, rate: u32, frames: usize) -> Vec<f32> {
         let mut pcm = Vec::with_capacity(frames * 2);
         for n in 0..frames {
             let t = n as f64 / rate as f64;
-            let s = (0.25 * (2.0 * std::f64::consts::PI * freq_hz * t).sin() * 32767.0) as i16;
+            let s = (0.25 * (2.0 * std::f64::consts::PI * freq_hz * t).sin()) as f32;
             pcm.push(s);
             pcm.push(s);
         }
         pcm
     }
 
-    fn rms(pcm: &[i16]) -> f64 {
+    #[test]
+    fn cutoff_steps_are_logarithmic_and_keep_bands_in_order() {
+        let bands = default_eq_band_settings();
+        // 12 steps = one octave.
+        assert_eq!(step_cutoff(&bands, 5, 12), 1999);
+        assert_eq!(step_cutoff(&bands, 5, -12), 501);
+        assert_eq!(step_cutoff(&bands, 5, 4), 1260);
+        assert_eq!(step_cutoff(&bands, 0, -48), 20);
+        assert_eq!(step_cutoff(&bands, 9, 24), 20_000);
+
+        let mut squeezed = bands;
+        squeezed[4].cutoff = 999;
+        assert_eq!(step_cutoff(&squeezed, 5, -1), 1000);
+
+        assert_eq!(log_step(7, 6, Q_STEPS_PER_DOUBLING, Q_RANGE), 14);
+        assert_eq!(log_step(1, 1, Q_STEPS_PER_DOUBLING, Q_RANGE), 2);
+        assert_eq!(log_step(2, -3, Q_STEPS_PER_DOUBLING, Q_RANGE), 1);
+        assert_eq!(log_step(60, 12, Q_STEPS_PER_DOUBLING, Q_RANGE), 64);
+    }
+
+    fn rms(pcm: &[f32]) -> f64 {
         let sum: f64 = pcm.iter().map(|&s| (s as f64) * (s as f64)).sum();
         (sum / pcm.len() as f64).sqrt()
     }
 
+    #[test]
+    fn processors_with_their_own_equalizers_run_side_by_side() {
+        let mut bands = default_eq_band_settings();
+        bands[5].gain = -120;
+        let cut = Arc::new(Equalizer::with_preset(&EqPreset {
+            enabled: true,
+            bands,
+            bass: 0,
+            treble: 0,
+        }));
+        let flat = Arc::new(Equalizer::with_preset(&EqPreset {
+            enabled: false,
+            bands: default_eq_band_settings(),
+            bass: 0,
+            treble: 0,
+        }));
+
+        let original = sine_stereo(1000.0, 44100, 44100);
+        let run = |eq: Arc<Equalizer>| {
+            let mut samples = original.clone();
+            std::thread::spawn(move || {
+                let mut processor = EqProcessor::with_equalizer(eq);
+                for chunk in samples.chunks_mut(1024) {
+                    processor.process(chunk, 2, 44100);
+                }
+                samples
+            })
+        };
+        let (cut, flat) = (run(cut), run(flat));
+        let (cut, flat) = (cut.join().unwrap(), flat.join().unwrap());
+
+        assert_eq!(flat, original);
+        assert!(rms(&original) / rms(&cut) > 2.0);
+    }
+
+    #[test]
+    fn surround_streams_are_equalized_on_every_channel() {
+        let mut bands = default_eq_band_settings();
+        bands[5].gain = -120;
+        let mut processor =
+            EqProcessor::with_equalizer(Arc::new(Equalizer::with_preset(&EqPreset {
+                enabled: true,
+                bands,
+                bass: 0,
+                treble: 0,
+            })));
+
+        // A 1 kHz tone on all six channels of a 5.1 stream.
+        let original: Vec<f32> = sine_stereo(1000.0, 48000, 48000)
+            .iter()
+            .step_by(2)
+            .flat_map(|&s| [s; 6])
+            .collect();
+        let mut cut = original.clone();
+        for chunk in cut.chunks_mut(6 * 1024) {
+            processor.process(chunk, 6, 48000);
+        }
+        for channel in 0..6 {
+            let before: Vec<f32> = original.iter().skip(channel).step_by(6).cloned().collect();
+            let after: Vec<f32> = cut.iter().skip(channel).step_by(6).cloned().collect();
+            assert!(rms(&before) / rms(&after) > 2.0, "channel {channel}");
+        }
+    }
+
     /// One test rather than several: it mutates the process-wide equalizer,
     /// so splitting it up would race under the parallel test runner.
     #[test]
@@ -256,7 +664,7 @@ mod tests {
         eq.set_enabled(false);
         let original = sine_stereo(1000.0, 44100, 44100);
         let mut buf = original.clone();
-        assert_eq!(processor.process(&mut buf, 2, 44100), 2);
+        processor.process(&mut buf, 2, 44100);
         assert_eq!(buf, original);
 
         // −12 dB on the 1 kHz band should clearly attenuate a 1 kHz tone.
@@ -264,18 +672,18 @@ mod tests {
         assert_eq!(eq.bands()[5].cutoff, 1000);
         eq.adjust_band_gain(5, -120);
         let mut cut = original.clone();
-        assert_eq!(processor.process(&mut cut, 2, 44100), 2);
+        processor.process(&mut cut, 2, 44100);
         let ratio = rms(&original) / rms(&cut);
         assert!(
             ratio > 2.0 && ratio < 8.0,
             "expected ~4x attenuation at 1 kHz, got {ratio:.2}x"
         );
 
-        // Mono input comes back upmixed to interleaved stereo.
-        let mono: Vec<i16> = original.iter().step_by(2).cloned().collect();
+        // Mono input is filtered as one channel.
+        let mono: Vec<f32> = original.iter().step_by(2).cloned().collect();
         let mut mono_buf = mono.clone();
-        assert_eq!(processor.process(&mut mono_buf, 1, 44100), 2);
-        assert_eq!(mono_buf.len(), mono.len() * 2);
+        processor.process(&mut mono_buf, 1, 44100);
+        assert!(rms(&mono) / rms(&mono_buf) > 2.0);
 
         // Bass shelf works even with the band EQ switched off: a −12 dB
         // bass cut should clearly attenuate a 100 Hz tone.
@@ -285,7 +693,7 @@ mod tests {
         assert!(eq.is_active());
         let low = sine_stereo(100.0, 44100, 44100);
         let mut low_cut = low.clone();
-        assert_eq!(processor.process(&mut low_cut, 2, 44100), 2);
+        processor.process(&mut low_cut, 2, 44100);
         let ratio = rms(&low) / rms(&low_cut);
         assert!(
             ratio > 2.0 && ratio < 8.0,
@@ -296,30 +704,3 @@ mod tests {
         eq.set_enabled(false);
     }
 }
-
-fn apply_settings(dsp: &mut Dsp, eq: &Equalizer) {
-    for (i, band) in eq
-        .bands()
-        .iter()
-        .take(EQ_NUM_BANDS.min(EQ_BANDS))
-        .enumerate()
-    {
-        dsp.set_eq_band_raw(
-            i,
-            eq_band_setting {
-                cutoff: band.cutoff,
-                q: band.q,
-                gain: band.gain,
-            },
-        );
-    }
-    dsp.eq_enable(eq.is_enabled());
-
-    // Cutoffs must be set BEFORE gains — set_tone runs the prescale step
-    // that recomputes the shelf coefficients from the active cutoff.
-    dsp.set_tone_cutoffs(
-        eq.bass_cutoff.load(Ordering::Relaxed),
-        eq.treble_cutoff.load(Ordering::Relaxed),
-    );
-    dsp.set_tone(eq.bass(), eq.treble());
-}
@@ -12,13 +12,20 @@ use tokio::sync::mpsc;
 use tunein_cli::os_media_controls::{self, OsMediaControls};
 
 use crate::app::send_os_media_controls_command;
-use crate::audio::{AudioController, PlaybackEvent, PlaybackState};
+use crate::device::{self, OutputDevice};
+use crate::dynamics::Dynamics;
 use crate::eq_ui::EqPopup;
 use crate::extract::get_currently_playing;
 use crate::favorites::{FavoriteStation, FavoritesStore};
 use crate::fzf_ui::{FzfOutcome, FzfPopup};
 use crate::help_ui::{HelpPopup, Shortcut};
+use crate::loudness::Loudness;
+use crate::output::OutputSpec;
+use crate::player::{PlaybackState, Player, PlayerCommand, PlayerEvent, PlayerOptions};
 use crate::provider::{radiobrowser::Radiobrowser, tunein::Tunein, Provider};
+use crate::readahead::BufferOptions;
+use crate::settings::Settings;
+use crate::stats_ui::StatsPanel;
 use crate::theme;
 use crate::tui;
 use crate::types::Station;
@@ -29,6 +36,7 @@ const MENU_OPTIONS: &[&str] = &[
     "Play Station",
     "Favourites",
     "Resume Last Station",
+    "Output Device",
     "Quit",
 ];
 
@@ -41,6 +49,9 @@ const HUB_SHORTCUTS: &[Shortcut] = &[
     ("d / delete", "Remove favourite (favourites screen)"),
     ("x", "Stop playback"),
     ("+ / -", "Volume up / down"),
+    ("L", "Toggle loudness normalisation"),
+    ("n", "Toggle night mode (compressor/limiter)"),
+    ("i", "Show stream stats"),
     ("/", "Open the fuzzy finder"),
     ("esc", "Back to the menu"),
     ("?", "Show this help"),
@@ -64,9 +75,31 @@ struct FzfSearchRequest {
     query: String,
 }
 
-pub async fn run(provider_name: &str) -> Result<(), Error> {
+pub async fn run(
+    provider_name: &str,
+    crossfade: Duration,
+    buffer: BufferOptions,
+    resample: bool,
+    output: OutputSpec,
+) -> Result<(), Error> {
+    if output.is_stdout() {
+        return Err(anyhow!(
+            "`--output stdout` needs the terminal, which interactive mode uses for its UI; run `tunein --output stdout server` instead"
+        ));
+    }
     let provider = resolve_provider(provider_name).await?;
-    let (audio, mut audio_events) = AudioController::new()?;
+    let output_device = match &output {
+        OutputSpec::Device(name) => name.clone(),
+        _ => None,
+    };
+    let (player, mut player_events) = Player::new(PlayerOptions {
+        output,
+        crossfade,
+        buffer,
+        resample,
+        volume: 1.0,
+        frames: None,
+    })?;
     let favorites = FavoritesStore::load()?;
     let (metadata_tx, mut metadata_rx) = mpsc::unbounded_channel::<HubMessage>();
     let (fzf_tx, mut fzf_rx) = mpsc::unbounded_channel::<FzfSearchRequest>();
@@ -88,7 +121,8 @@ pub async fn run(provider_name: &str) -> Result<(), Error> {
     let mut app = HubApp::new(
         provider_name.to_string(),
         provider,
-        audio,
+        player,
+        output_device,
         favorites,
         metadata_tx,
         fzf_tx,
@@ -106,7 +140,7 @@ pub async fn run(provider_name: &str) -> Result<(), Error> {
                     Action::None => {}
                 }
             }
-            Some(event) = audio_events.recv() => {
+            Some(event) = player_events.recv() => {
                 app.handle_playback_event(event);
             }
             Some(message) = metadata_rx.recv() => {
@@ -140,7 +174,9 @@ fn spawn_input_thread(tx: mpsc::UnboundedSender<Event>) {
 struct HubApp {
     provider_name: String,
     provider: Box<dyn Provider>,
-    audio: AudioController,
+    player: Player,
+    /// Output device in use, `None` for the system default.
+    output_device: Option<String>,
     favorites: FavoritesStore,
     ui: UiState,
     current_station: Option<StationRecord>,
@@ -154,6 +190,7 @@ struct HubApp {
     os_media_controls: Option<OsMediaControls>,
     eq_popup: EqPopup,
     help_popup: HelpPopup,
+    stats_panel: StatsPanel,
     fzf_popup: FzfPopup,
     fzf_tx: mpsc::UnboundedSender<FzfSearchRequest>,
     /// Monotonic tag for the newest fuzzy-finder search; lets in-flight
@@ -162,10 +199,12 @@ struct HubApp {
 }
 
 impl HubApp {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         provider_name: String,
         provider: Box<dyn Provider>,
-        audio: AudioController,
+        player: Player,
+        output_device: Option<String>,
         favorites: FavoritesStore,
         metadata_tx: mpsc::UnboundedSender<HubMessage>,
         fzf_tx: mpsc::UnboundedSender<FzfSearchRequest>,
@@ -176,7 +215,8 @@ impl HubApp {
         Self {
             provider_name,
             provider,
-            audio,
+            player,
+            output_device,
             favorites,
             ui,
             current_station: None,
@@ -190,6 +230,7 @@ impl HubApp {
             os_media_controls,
             eq_popup: EqPopup::new(),
             help_popup: HelpPopup::new(HUB_SHORTCUTS),
+            stats_panel: StatsPanel::default(),
             fzf_popup: FzfPopup::new(),
             fzf_tx,
             fzf_generation: Arc::new(AtomicUsize::new(0)),
@@ -214,6 +255,7 @@ impl HubApp {
         self.render_divider(frame, areas[1]);
         self.render_main(frame, areas[2]);
         frame.render_widget(self.render_footer(), areas[3]);
+        self.stats_panel.render(frame, &self.player.stats());
         self.eq_popup.render(frame);
         self.help_popup.render(frame);
         self.fzf_popup.render(frame);
@@ -338,10 +380,23 @@ impl HubApp {
                 })
             })
             .unwrap_or_else(|| "Unknown".to_string());
+        let bitrate = match self.current_playback.as_ref() {
+            Some(p) if !p.channel_layout.is_empty() => {
+                format!("{} • {}", bitrate, p.channel_layout)
+            }
+            _ => bitrate,
+        };
         self.render_labeled_line(frame, area, row, "Bitrate ", &bitrate);
         row += 1;
 
-        let volume_display = format!("{}%", self.volume as u32);
+        let mut volume_display = format!("{}%", self.volume as u32);
+        let loudness = Loudness::global();
+        if loudness.is_enabled() {
+            volume_display.push_str(&format!(" • normalised to {} LUFS", loudness.target_lufs()));
+        }
+        if Dynamics::global().is_enabled() {
+            volume_display.push_str(" • night mode");
+        }
         self.render_labeled_line(frame, area, row, "Volume ", &volume_display);
     }
 
@@ -460,6 +515,22 @@ impl HubApp {
                     );
                 frame.render_stateful_widget(list, area, &mut self.ui.favourites_state);
             }
+            Screen::Devices => {
+                let items = self.device_items();
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .title("Output Device")
+                            .borders(Borders::ALL),
+                    )
+                    .highlight_symbol("➜ ")
+                    .highlight_style(
+                        Style::default()
+                            .fg(theme::ACCENT)
+                            .add_modifier(Modifier::BOLD),
+                    );
+                frame.render_stateful_widget(list, area, &mut self.ui.devices_state);
+            }
             Screen::Loading => {
                 let message = self
                     .ui
@@ -572,6 +643,33 @@ impl HubApp {
         }
     }
 
+    /// "System default" followed by every device, the one in use ticked.
+    fn device_items(&self) -> Vec<ListItem<'static>> {
+        let entry = |label: String, selected: bool| {
+            if selected {
+                ListItem::new(format!("{} ✓", label))
+            } else {
+                ListItem::new(label)
+            }
+        };
+        std::iter::once(entry(
+            "System default".to_string(),
+            self.output_device.is_none(),
+        ))
+        .chain(self.ui.devices.iter().map(|device| {
+            let label = if device.is_default {
+                format!("{} (default)", device.name)
+            } else {
+                device.name.clone()
+            };
+            entry(
+                label,
+                self.output_device.as_deref() == Some(device.name.as_str()),
+            )
+        }))
+        .collect()
+    }
+
     fn handle_favourite_action(&mut self) -> Result<bool, Error> {
         match self.ui.screen {
             Screen::SearchResults => {
@@ -658,7 +756,7 @@ impl HubApp {
     }
 
     fn stop_playback(&mut self) -> Result<(), Error> {
-        self.audio.stop()?;
+        self.player.send(PlayerCommand::Stop)?;
         self.set_status("Playback stopped");
         Ok(())
     }
@@ -682,6 +780,9 @@ impl HubApp {
             Screen::SearchInput | Screen::PlayInput => {
                 "Type to edit • Enter submit • x stop playback • Esc cancel • +/- volume".to_string()
             }
+            Screen::Devices => {
+                "↑/↓ navigate • Enter switch device • x stop playback • Esc back • +/- volume".to_string()
+            }
             Screen::Loading => "Please wait… • x stop playback • Esc cancel • +/- volume".to_string(),
             Screen::Menu => {
                 "↑/↓ navigate • Enter select • / search • e equalizer • x stop • Esc back • +/- volume • ? help"
@@ -726,6 +827,11 @@ impl HubApp {
                 "Enter  • Start playback".to_string(),
                 "Esc    • Cancel".to_string(),
             ],
+            Screen::Devices => vec![
+                "Output Device".to_string(),
+                "Enter  • Play on highlighted device".to_string(),
+                "Esc    • Return to main menu".to_string(),
+            ],
             Screen::Loading => vec!["Loading…".to_string(), "Esc    • Cancel".to_string()],
             Screen::Menu => vec![
                 "Main Menu".to_string(),
@@ -799,6 +905,34 @@ impl HubApp {
                 self.help_popup.toggle();
                 return Ok(Action::None);
             }
+            KeyCode::Char('i')
+                if !matches!(self.ui.screen, Screen::SearchInput | Screen::PlayInput) =>
+            {
+                self.stats_panel.toggle();
+                return Ok(Action::None);
+            }
+            KeyCode::Char('L')
+                if !matches!(self.ui.screen, Screen::SearchInput | Screen::PlayInput) =>
+            {
+                let status = if Loudness::global().toggle() {
+                    "Loudness normalisation on"
+                } else {
+                    "Loudness normalisation off"
+                };
+                self.set_status(status);
+                return Ok(Action::None);
+            }
+            KeyCode::Char('n')
+                if !matches!(self.ui.screen, Screen::SearchInput | Screen::PlayInput) =>
+            {
+                let status = if Dynamics::global().toggle() {
+                    "Night mode on"
+                } else {
+                    "Night mode off"
+                };
+                self.set_status(status);
+                return Ok(Action::None);
+            }
             KeyCode::Char('+') | KeyCode::Char('=') => {
                 self.adjust_volume(5.0)?;
                 return Ok(Action::None);
@@ -831,6 +965,7 @@ impl HubApp {
             Screen::Categories => self.handle_categories_keys(key),
             Screen::BrowseStations { .. } => self.handle_station_list_keys(key, ListKind::Browse),
             Screen::Favourites => self.handle_favourites_keys(key),
+            Screen::Devices => self.handle_devices_keys(key),
             Screen::Loading => Ok(Action::None),
         }
     }
@@ -972,6 +1107,26 @@ impl HubApp {
                         Ok(Action::None)
                     }
                 }
+                "Output Device" => {
+                    match device::list() {
+                        Ok(devices) => self.ui.devices = devices,
+                        Err(err) => {
+                            self.ui.devices.clear();
+                            self.set_status(err.to_string());
+                        }
+                    }
+                    let current = self.output_device.as_deref().and_then(|name| {
+                        self.ui
+                            .devices
+                            .iter()
+                            .position(|device| device.name == name)
+                    });
+                    self.ui
+                        .devices_state
+                        .select(Some(current.map_or(0, |index| index + 1)));
+                    self.ui.screen = Screen::Devices;
+                    Ok(Action::None)
+                }
                 "Quit" => Ok(Action::Quit),
                 _ => Ok(Action::None),
             },
@@ -1134,9 +1289,62 @@ impl HubApp {
         }
     }
 
+    fn handle_devices_keys(&mut self, key: KeyEvent) -> Result<Action, Error> {
+        // Entry 0 is "System default".
+        let len = self.ui.devices.len() + 1;
+        let current = self.ui.devices_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Up => {
+                let new = current.saturating_sub(1);
+                self.ui.devices_state.select(Some(new));
+                Ok(Action::None)
+            }
+            KeyCode::Down => {
+                let new = (current + 1).min(len - 1);
+                self.ui.devices_state.select(Some(new));
+                Ok(Action::None)
+            }
+            KeyCode::Enter => {
+                let output_device = match current {
+                    0 => None,
+                    index => self
+                        .ui
+                        .devices
+                        .get(index - 1)
+                        .map(|device| device.name.clone()),
+                };
+                self.switch_device(output_device)?;
+                Ok(Action::None)
+            }
+            KeyCode::Esc => {
+                self.ui.screen = Screen::Menu;
+                Ok(Action::None)
+            }
+            _ => Ok(Action::None),
+        }
+    }
+
+    /// Move playback to `output_device` and remember it in the settings file.
+    fn switch_device(&mut self, output_device: Option<String>) -> Result<(), Error> {
+        self.player
+            .send(PlayerCommand::SetOutput(OutputSpec::Device(
+                output_device.clone(),
+            )))?;
+        let mut settings = Settings::load();
+        settings.output_device = output_device.clone();
+        settings.save()?;
+        self.set_status(format!(
+            "Output device: {}",
+            output_device.as_deref().unwrap_or("system default")
+        ));
+        self.output_device = output_device;
+        Ok(())
+    }
+
     fn adjust_volume(&mut self, delta: f32) -> Result<(), Error> {
         self.volume = (self.volume + delta).clamp(0.0, 150.0);
-        self.audio.set_volume(self.volume)?;
+        self.player
+            .send(PlayerCommand::SetVolume(self.volume / 100.0))?;
         self.set_status(&format!("Volume set to {}%", self.volume as u32));
         Ok(())
     }
@@ -1166,9 +1374,12 @@ impl HubApp {
         Ok(())
     }
 
-    fn handle_playback_event(&mut self, event: PlaybackEvent) {
+    fn handle_playback_event(&mut self, event: PlayerEvent) {
         match event {
-            PlaybackEvent::Started(state) => {
+            PlayerEvent::Started(state) => {
+                if let Some(volume) = state.volume {
+                    self.volume = volume * 100.0;
+                }
                 self.current_playback = Some(state.clone());
                 if let Some(station) = self.current_station.as_mut() {
                     station.station.playing = Some(state.now_playing.clone());
@@ -1177,16 +1388,22 @@ impl HubApp {
                 self.set_status(&format!("Now playing {}", state.stream_name));
                 self.prepare_now_playing_poll();
             }
-            PlaybackEvent::Error(err) => {
-                self.current_playback = None;
+            // The previous station, if any, keeps playing.
+            PlayerEvent::LoadFailed { station, error } => {
+                self.set_status(format!("Failed to play {}: {}", station.name, error));
+            }
+            PlayerEvent::Error(err) => {
                 self.set_status(&format!("Playback error: {}", err));
-                self.now_playing_station_id = None;
             }
-            PlaybackEvent::Stopped => {
+            PlayerEvent::Stopped => {
                 self.current_playback = None;
                 self.set_status("Playback stopped");
                 self.now_playing_station_id = None;
             }
+            PlayerEvent::Underrun { total } => {
+                self.set_status(format!("Stream stalled (underrun #{})", total));
+            }
+            PlayerEvent::Connecting(_) => {}
         }
     }
 
@@ -1292,7 +1509,10 @@ impl HubApp {
             }
         }
 
-        self.audio.play(record.station.clone(), self.volume)?;
+        self.player.send(PlayerCommand::Play {
+            station: record.station.clone(),
+            provider: record.provider.clone(),
+        })?;
         self.current_station = Some(record.clone());
         self.last_station = Some(record);
         self.prepare_now_playing_poll();
@@ -1359,6 +1579,8 @@ struct UiState {
     browse_results: Vec<Station>,
     browse_state: ListState,
     favourites_state: ListState,
+    devices: Vec<OutputDevice>,
+    devices_state: ListState,
     loading_message: Option<String>,
 }
 
@@ -1376,6 +1598,8 @@ impl Default for UiState {
             browse_results: Vec::new(),
             browse_state: ListState::default(),
             favourites_state: ListState::default(),
+            devices: Vec::new(),
+            devices_state: ListState::default(),
             loading_message: None,
         }
     }
@@ -1390,6 +1614,7 @@ enum Screen {
     Categories,
     BrowseStations { category: String },
     Favourites,
+    Devices,
     Loading,
 }
 
@@ -0,0 +1,603 @@
+//! Loudness normalisation, so switching stations doesn't mean reaching for
+//! the volume keys.
+//!
+//! Every [`crate::decoder::StreamDecoder`] that knows its station owns a
+//! [`LoudnessProcessor`]. It measures short-term loudness the EBU R128 way
+//! (ITU-R BS.1770 K-weighting, 100 ms blocks, 3 s window) and eases a gain
+//! toward the target set in the global [`Loudness`]. Each station's measured
+//! loudness is remembered in `loudness.json`, keyed by provider and station
+//! id, so the next time it plays it starts at the right gain instead of
+//! adapting from 0 dB.
+//!
+//! [`LoudnessMeter`] takes the same measurements, ungated, for the level
+//! meters.
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+use anyhow::{Context, Error};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::biquad::{Biquad, BiquadState};
+use crate::input::Matrix;
+use crate::settings::Settings;
+
+/// Largest boost applied to a quiet station, in dB.
+pub const MAX_BOOST_DB: f32 = 12.0;
+/// Largest cut applied to a loud station, in dB.
+pub const MAX_CUT_DB: f32 = 12.0;
+/// A boost never takes a sample above this level (-1 dBFS), so a quiet
+/// station with loud peaks is only brought up as far as they allow.
+const BOOST_CEILING: f64 = 0.891;
+
+/// Blocks quieter than this (silence, station idents fading out) are not
+/// measured, so they never drive the gain up. BS.1770 absolute gate.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Short-term window: 30 blocks of 100 ms.
+const SHORT_TERM_BLOCKS: usize = 30;
+/// Momentary window: 4 blocks of 100 ms.
+const MOMENTARY_BLOCKS: usize = 4;
+/// Measured blocks needed before the short-term value is trusted.
+const MIN_BLOCKS: usize = 5;
+/// Time constant of the gain smoothing, in seconds. Slow enough not to
+/// pump on the music's own dynamics.
+const GAIN_SMOOTHING_SECS: f64 = 3.0;
+/// How often the station's loudness is written back, in blocks (30 s).
+const REMEMBER_EVERY_BLOCKS: u64 = 300;
+
+/// Process-wide loudness switch and target, seeded from the settings file.
+pub struct Loudness {
+    enabled: AtomicBool,
+    /// Target loudness in LUFS, stored as `f32` bits.
+    target: AtomicU32,
+}
+
+static GLOBAL: OnceLock<Loudness> = OnceLock::new();
+
+impl Loudness {
+    pub fn global() -> &'static Loudness {
+        GLOBAL.get_or_init(|| {
+            let settings = Settings::load();
+            Loudness {
+                enabled: AtomicBool::new(settings.loudness_enabled),
+                target: AtomicU32::new(settings.loudness_target.to_bits()),
+            }
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Flip normalisation on or off and persist it. Returns the new state.
+    pub fn toggle(&self) -> bool {
+        let enabled = !self.enabled.fetch_xor(true, Ordering::Relaxed);
+        self.save();
+        enabled
+    }
+
+    pub fn target_lufs(&self) -> f32 {
+        f32::from_bits(self.target.load(Ordering::Relaxed))
+    }
+
+    fn save(&self) {
+        let mut settings = Settings::load();
+        settings.loudness_enabled = self.is_enabled();
+        settings.loudness_target = self.target_lufs();
+        if let Err(err) = settings.save() {
+            eprintln!("warning: failed to save settings: {}", err);
+        }
+    }
+}
+
+/// The BS.1770 K-weighting filter for `sample_rate`: a high shelf
+/// modelling the head, then the RLB high-pass. Coefficients are derived
+/// from the analog prototypes so any rate works, not just 48 kHz.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b0: (vh + vb * k / q + k * k) / a0,
+        b1: 2.0 * (k * k - vh) / a0,
+        b2: (vh - vb * k / q + k * k) / a0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+    };
+
+    [shelf, high_pass]
+}
+
+/// K-weight one sample of a channel whose filter history is `state`.
+fn k_weight(filters: &[Biquad; 2], state: &mut [BiquadState; 2], x: f64) -> f64 {
+    let shelved = filters[0].process(&mut state[0], x);
+    filters[1].process(&mut state[1], shelved)
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Per-stream normaliser. Disabled globally, it leaves samples untouched.
+pub struct LoudnessProcessor {
+    /// Provider and id the measurement is remembered under; `None`
+    /// forgets it.
+    station: Option<(String, String)>,
+    sample_rate: u32,
+    channels: u16,
+    filters: [Biquad; 2],
+    /// K-weighting history, per channel.
+    states: Vec<[BiquadState; 2]>,
+    /// Weighted sum of squares and frame count of the block being measured.
+    block_energy: f64,
+    block_frames: usize,
+    block_len: usize,
+    /// Sample peak of the block being measured.
+    block_peak: f64,
+    /// Mean-square energies of the last [`SHORT_TERM_BLOCKS`] gated blocks.
+    window: VecDeque<f64>,
+    /// Sample peaks of the blocks in `window`.
+    peaks: VecDeque<f64>,
+    /// Running sum over every gated block, for the remembered loudness.
+    total_energy: f64,
+    total_blocks: u64,
+    /// Loudness remembered from a previous session, used until enough of
+    /// this one has been measured.
+    remembered: Option<f64>,
+    gain: f64,
+    target_gain: f64,
+}
+
+impl LoudnessProcessor {
+    /// A processor for the station with this provider and id, starting
+    /// from its remembered loudness.
+    pub fn new(station: Option<(&str, &str)>) -> Self {
+        let remembered = station.and_then(|(provider, id)| remembered_loudness(provider, id));
+        Self {
+            station: station.map(|(provider, id)| (provider.to_string(), id.to_string())),
+            sample_rate: 0,
+            channels: 0,
+            // Redone for the actual rate once audio arrives.
+            filters: k_weighting(48000),
+            states: Vec::new(),
+            block_energy: 0.0,
+            block_frames: 0,
+            block_len: 0,
+            block_peak: 0.0,
+            window: VecDeque::with_capacity(SHORT_TERM_BLOCKS),
+            peaks: VecDeque::with_capacity(SHORT_TERM_BLOCKS),
+            total_energy: 0.0,
+            total_blocks: 0,
+            remembered: remembered.map(f64::from),
+            gain: f64::NAN,
+            target_gain: 1.0,
+        }
+    }
+
+    /// When normalisation is enabled, measure one decoded packet of
+    /// interleaved samples and apply the smoothed gain in place.
+    pub fn process(&mut self, buffer: &mut [f32], channels: u16, sample_rate: u32) {
+        let loudness = Loudness::global();
+        if !loudness.is_enabled() {
+            return;
+        }
+        self.apply(buffer, channels, sample_rate, loudness.target_lufs());
+    }
+
+    /// The short-term loudness in LUFS, once enough has been measured.
+    pub fn short_term_lufs(&self) -> Option<f64> {
+        (self.window.len() >= MIN_BLOCKS)
+            .then(|| energy_to_lufs(self.window.iter().sum::<f64>() / self.window.len() as f64))
+    }
+
+    fn apply(&mut self, buffer: &mut [f32], channels: u16, sample_rate: u32, target: f32) {
+        if buffer.is_empty() || channels == 0 || sample_rate == 0 {
+            return;
+        }
+        if self.sample_rate != sample_rate || self.channels != channels {
+            self.reset(channels, sample_rate);
+        }
+        if self.gain.is_nan() {
+            // First packet: jump straight to the remembered station's gain.
+            self.target_gain = self.gain_for(target);
+            self.gain = self.target_gain;
+        }
+
+        let smoothing = 1.0 - (-1.0 / (GAIN_SMOOTHING_SECS * sample_rate as f64)).exp();
+        for frame in buffer.chunks_mut(channels as usize) {
+            let mut peak = 0f64;
+            for (sample, state) in frame.iter().zip(self.states.iter_mut()) {
+                let weighted = k_weight(&self.filters, state, *sample as f64);
+                self.block_energy += weighted * weighted;
+                peak = peak.max((*sample as f64).abs());
+            }
+            self.block_peak = self.block_peak.max(peak);
+            self.block_frames += 1;
+            if self.block_frames >= self.block_len {
+                self.finish_block(target);
+            }
+
+            self.gain += (self.target_gain - self.gain) * smoothing;
+            // A peak the boost would take past the ceiling pulls the gain
+            // down at once; the smoothing brings it back up.
+            if self.gain > 1.0 && peak * self.gain > BOOST_CEILING {
+                self.gain = (BOOST_CEILING / peak).max(1.0);
+            }
+            for sample in frame.iter_mut() {
+                *sample = (*sample as f64 * self.gain) as f32;
+            }
+        }
+    }
+
+    fn reset(&mut self, channels: u16, sample_rate: u32) {
+        self.channels = channels;
+        self.sample_rate = sample_rate;
+        self.filters = k_weighting(sample_rate);
+        self.states = vec![Default::default(); channels as usize];
+        self.block_energy = 0.0;
+        self.block_frames = 0;
+        self.block_peak = 0.0;
+        self.block_len = (sample_rate / 10).max(1) as usize;
+    }
+
+    fn finish_block(&mut self, target: f32) {
+        let energy = self.block_energy / self.block_frames as f64;
+        let peak = self.block_peak;
+        self.block_energy = 0.0;
+        self.block_frames = 0;
+        self.block_peak = 0.0;
+
+        if energy <= 0.0 || energy_to_lufs(energy) < ABSOLUTE_GATE_LUFS {
+            return;
+        }
+        if self.window.len() == SHORT_TERM_BLOCKS {
+            self.window.pop_front();
+            self.peaks.pop_front();
+        }
+        self.window.push_back(energy);
+        self.peaks.push_back(peak);
+        self.total_energy += energy;
+        self.total_blocks += 1;
+        self.target_gain = self.gain_for(target);
+
+        if self.total_blocks.is_multiple_of(REMEMBER_EVERY_BLOCKS) {
+            self.remember();
+        }
+    }
+
+    /// Linear gain that brings the measured (or remembered) loudness to
+    /// `target`, within the boost/cut limits and without boosting the
+    /// window's peaks past [`BOOST_CEILING`].
+    fn gain_for(&self, target: f32) -> f64 {
+        let Some(measured) = self.short_term_lufs().or(self.remembered) else {
+            return 1.0;
+        };
+        let gain_db = (target as f64 - measured).clamp(-MAX_CUT_DB as f64, MAX_BOOST_DB as f64);
+        let gain = 10f64.powf(gain_db / 20.0);
+        match self.peaks.iter().copied().reduce(f64::max) {
+            Some(peak) if peak > 0.0 => gain.min((BOOST_CEILING / peak).max(1.0)),
+            _ => gain,
+        }
+    }
+
+    /// Store this session's gated average for the station.
+    fn remember(&self) {
+        let Some((provider, id)) = &self.station else {
+            return;
+        };
+        if self.total_blocks < SHORT_TERM_BLOCKS as u64 {
+            return;
+        }
+        let lufs = energy_to_lufs(self.total_energy / self.total_blocks as f64);
+        remember_loudness(provider, id, lufs as f32);
+    }
+}
+
+/// Momentary (400 ms) and short-term (3 s) loudness of a signal, updated
+/// every 100 ms. Unlike the normaliser it doesn't gate, so silence reads as
+/// silence.
+pub struct LoudnessMeter {
+    sample_rate: u32,
+    filters: [Biquad; 2],
+    /// K-weighting history, per channel.
+    states: Vec<[BiquadState; 2]>,
+    block_energy: f64,
+    block_frames: usize,
+    block_len: usize,
+    /// Mean-square energies of the last [`SHORT_TERM_BLOCKS`] blocks.
+    blocks: VecDeque<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new() -> Self {
+        Self {
+            sample_rate: 0,
+            // Redone for the actual rate once audio arrives.
+            filters: k_weighting(48000),
+            states: Vec::new(),
+            block_energy: 0.0,
+            block_frames: 0,
+            block_len: 0,
+            blocks: VecDeque::with_capacity(SHORT_TERM_BLOCKS),
+        }
+    }
+
+    /// Measure one chunk, one sample vector per channel.
+    pub fn process(&mut self, channels: &Matrix<f64>, sample_rate: u32) {
+        if channels.is_empty() || sample_rate == 0 {
+            return;
+        }
+        if self.sample_rate != sample_rate || self.states.len() != channels.len() {
+            *self = Self::new();
+            self.sample_rate = sample_rate;
+            self.filters = k_weighting(sample_rate);
+            self.states = vec![Default::default(); channels.len()];
+            self.block_len = (sample_rate / 10).max(1) as usize;
+        }
+        let frames = channels.iter().map(Vec::len).min().unwrap_or(0);
+        for n in 0..frames {
+            for (chan, state) in channels.iter().zip(self.states.iter_mut()) {
+                let weighted = k_weight(&self.filters, state, chan[n]);
+                self.block_energy += weighted * weighted;
+            }
+            self.block_frames += 1;
+            if self.block_frames >= self.block_len {
+                if self.blocks.len() == SHORT_TERM_BLOCKS {
+                    self.blocks.pop_front();
+                }
+                self.blocks
+                    .push_back(self.block_energy / self.block_frames as f64);
+                self.block_energy = 0.0;
+                self.block_frames = 0;
+            }
+        }
+    }
+
+    pub fn momentary_lufs(&self) -> Option<f64> {
+        self.window_lufs(MOMENTARY_BLOCKS)
+    }
+
+    pub fn short_term_lufs(&self) -> Option<f64> {
+        self.window_lufs(SHORT_TERM_BLOCKS)
+    }
+
+    /// Loudness over the last `blocks` blocks, once that many were measured.
+    fn window_lufs(&self, blocks: usize) -> Option<f64> {
+        (self.blocks.len() >= blocks).then(|| {
+            let energy = self.blocks.iter().rev().take(blocks).sum::<f64>() / blocks as f64;
+            energy_to_lufs(energy.max(f64::MIN_POSITIVE))
+        })
+    }
+}
+
+impl Drop for LoudnessProcessor {
+    fn drop(&mut self) {
+        self.remember();
+    }
+}
+
+/// A station's loudness as remembered in `loudness.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct StationLoudness {
+    id: String,
+    provider: String,
+    /// Gated average loudness, in LUFS.
+    lufs: f32,
+}
+
+/// Station loudness remembered across sessions (`loudness.json`).
+static MEMORY: OnceLock<Mutex<Vec<StationLoudness>>> = OnceLock::new();
+
+fn memory() -> &'static Mutex<Vec<StationLoudness>> {
+    MEMORY.get_or_init(|| {
+        let entries = loudness_path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| {
+                serde_json::from_str(&content).unwrap_or_else(|err| {
+                    eprintln!("warning: loudness file corrupted ({}), starting fresh", err);
+                    Vec::new()
+                })
+            })
+            .unwrap_or_default();
+        Mutex::new(entries)
+    })
+}
+
+fn remembered_loudness(provider: &str, id: &str) -> Option<f32> {
+    memory()
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|entry| entry.id == id && entry.provider == provider)
+        .map(|entry| entry.lufs)
+}
+
+fn remember_loudness(provider: &str, id: &str, lufs: f32) {
+    let mut entries = memory().lock().unwrap();
+    match entries
+        .iter_mut()
+        .find(|entry| entry.id == id && entry.provider == provider)
+    {
+        Some(entry) => entry.lufs = lufs,
+        None => entries.push(StationLoudness {
+            id: id.to_string(),
+            provider: provider.to_string(),
+            lufs,
+        }),
+    }
+    drop(entries);
+    request_save();
+}
+
+/// Wakes the thread writing `loudness.json`; processors measure on the
+/// audio path, which must not wait for the disk.
+static SAVES: OnceLock<Sender<()>> = OnceLock::new();
+
+fn request_save() {
+    let saves = SAVES.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            while rx.recv().is_ok() {
+                // One write covers every request made in the meantime.
+                while rx.try_recv().is_ok() {}
+                let entries = memory().lock().unwrap().clone();
+                if let Err(err) = save_memory(&entries) {
+                    eprintln!("warning: failed to save station loudness: {}", err);
+                }
+            }
+        });
+        tx
+    });
+    let _ = saves.send(());
+}
+
+fn save_memory(entries: &[StationLoudness]) -> Result<(), Error> {
+    let path = loudness_path()?;
+    ensure_parent(&path)?;
+    let serialized =
+        serde_json::to_string_pretty(entries).context("failed to serialize station loudness")?;
+    fs::write(&path, serialized).context("failed to write loudness file")
+}
+
+fn loudness_path() -> Result<PathBuf, Error> {
+    let dirs = ProjectDirs::from("io", "tunein-cli", "tunein-cli")
+        .ok_or_else(|| Error::msg("unable to determine configuration directory"))?;
+    Ok(dirs.config_dir().join("loudness.json"))
+}
+
+fn ensure_parent(path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create loudness directory")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interleaved stereo sine at `amplitude` (full scale = 1.0).
+    fn sine(freq_hz: f64, amplitude: f64, rate: u32, secs: f64) -> Vec<f32> {
+        let frames = (rate as f64 * secs) as usize;
+        let mut pcm = Vec::with_capacity(frames * 2);
+        for n in 0..frames {
+            let t = n as f64 / rate as f64;
+            let s = (amplitude * (2.0 * PI * freq_hz * t).sin()) as f32;
+            pcm.push(s);
+            pcm.push(s);
+        }
+        pcm
+    }
+
+    fn measure(pcm: &[f32], rate: u32) -> f64 {
+        // The meter reads the input before the gain, so the target is moot.
+        let mut processor = LoudnessProcessor::new(None);
+        let mut copy = pcm.to_vec();
+        processor.apply(&mut copy, 2, rate, 0.0);
+        processor.short_term_lufs().unwrap()
+    }
+
+    #[test]
+    fn meter_matches_bs1770_reference() {
+        // A 1 kHz sine at -6 dBFS in both channels reads about -6 LUFS
+        // (the K-weighting adds ~0.7 dB at 1 kHz, cancelling the -0.691
+        // offset), at both common rates.
+        for rate in [44100, 48000] {
+            let lufs = measure(&sine(1000.0, 0.5, rate, 4.0), rate);
+            assert!(
+                (lufs + 6.0).abs() < 0.3,
+                "{rate} Hz: measured {lufs:.2} LUFS"
+            );
+        }
+    }
+
+    #[test]
+    fn meter_reads_momentary_and_short_term() {
+        let rate = 48000;
+        let pcm = sine(1000.0, 0.5, rate, 4.0);
+        let channels = crate::input::stream_to_matrix(pcm.iter().copied(), 2, 1.0);
+        let mut meter = LoudnessMeter::new();
+        meter.process(&channels, rate);
+        for lufs in [meter.momentary_lufs(), meter.short_term_lufs()] {
+            let lufs = lufs.unwrap();
+            assert!((lufs + 6.0).abs() < 0.3, "measured {lufs:.2} LUFS");
+        }
+
+        // Silence isn't gated away: the momentary value drops right down.
+        let silence = vec![vec![0.0; rate as usize]; 2];
+        meter.process(&silence, rate);
+        assert!(meter.momentary_lufs().unwrap() < -70.0);
+    }
+
+    #[test]
+    fn quiet_station_is_brought_to_target() {
+        let rate = 44100;
+        // Roughly -26 LUFS, normalised toward -18.
+        let mut pcm = sine(1000.0, 0.05, rate, 20.0);
+        let mut processor = LoudnessProcessor::new(None);
+        processor.apply(&mut pcm, 2, rate, -18.0);
+
+        let tail = &pcm[pcm.len() - rate as usize * 2 * 4..];
+        let lufs = measure(tail, rate);
+        assert!((lufs + 18.0).abs() < 1.0, "normalised to {lufs:.2} LUFS");
+    }
+
+    #[test]
+    fn gain_is_limited_and_silence_is_not_boosted() {
+        let rate = 44100;
+        // ~-46 LUFS would need +28 dB; only MAX_BOOST_DB is applied.
+        let mut processor = LoudnessProcessor::new(None);
+        processor.apply(&mut sine(1000.0, 0.005, rate, 6.0), 2, rate, -18.0);
+        let max = 10f64.powf(MAX_BOOST_DB as f64 / 20.0);
+        assert!((processor.target_gain - max).abs() < 1e-9);
+
+        let mut silence = vec![0f32; rate as usize * 2 * 5];
+        let mut processor = LoudnessProcessor::new(None);
+        processor.apply(&mut silence, 2, rate, -18.0);
+        assert!(processor.short_term_lufs().is_none());
+        assert_eq!(processor.target_gain, 1.0);
+    }
+
+    #[test]
+    fn boost_keeps_peaks_below_the_ceiling() {
+        let rate = 44100;
+        // A quiet station (~-32 LUFS) with a click at -6 dBFS every second:
+        // the full boost would take the clicks well past full scale.
+        let mut pcm = sine(1000.0, 0.025, rate, 10.0);
+        for click in pcm.chunks_mut(rate as usize * 2) {
+            click[..2].fill(0.5);
+        }
+        let mut processor = LoudnessProcessor::new(None);
+        processor.apply(&mut pcm, 2, rate, -18.0);
+
+        let peak = pcm.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak as f64 <= BOOST_CEILING + 1e-6, "peak {peak}");
+        assert!(processor.gain > 1.0, "the station is still boosted");
+    }
+}
@@ -1,7 +1,9 @@
+use std::path::Path;
 use std::time::Duration;
 
 use anyhow::Error;
 use app::CurrentDisplayMode;
+use cfg::{SourceOptions, UiOptions};
 use clap::{
     arg,
     builder::{
@@ -10,28 +12,48 @@ use clap::{
     },
     Command,
 };
+use output::OutputSpec;
+use readahead::BufferOptions;
 
+mod alarm;
 mod app;
-mod audio;
+mod biquad;
 mod browse;
 mod cfg;
 mod decoder;
+mod device;
+mod dither;
+mod downmix;
+mod dynamics;
+mod eq_presets;
 mod eq_ui;
 mod equalizer;
 mod extract;
 mod favorites;
 mod fzf_ui;
 mod help_ui;
+mod icecast;
 mod input;
 mod interactive;
+mod loudness;
+mod mixer;
 mod music;
+mod opus;
+mod output;
 mod play;
 mod player;
 mod provider;
+mod readahead;
+mod rebroadcast;
+mod resample;
 mod search;
 mod server;
 mod service;
 mod settings;
+mod station_memory;
+mod stats;
+mod stats_ui;
+mod stereo;
 mod tags;
 mod theme;
 mod tui;
@@ -75,6 +97,27 @@ A simple CLI to listen to radio stations"#,
         .arg(
             arg!(-p --provider <PROVIDER> "The radio provider to use, can be 'tunein' or 'radiobrowser'. Default is 'tunein'").default_value("tunein")
         )
+        .arg(
+            arg!(--crossfade <MS> "Crossfade length in milliseconds when switching stations, 0 to disable. Default is taken from the settings file (2000)")
+        )
+        .arg(
+            arg!(--buffer <KB> "Size of the read-ahead buffer between the network and the decoder, in KiB. Default is taken from the settings file (256)")
+        )
+        .arg(
+            arg!(--prebuffer <KB> "KiB to buffer before a station starts playing, and again after the buffer ran dry. Default is taken from the settings file (32)")
+        )
+        .arg(
+            arg!(--resample "Resample to the output's native rate with a high-quality windowed-sinc filter. Default is taken from the settings file (off)")
+        )
+        .arg(
+            arg!(--multichannel <MODE> "What to do with 5.1/7.1 streams: 'downmix' to stereo ahead of the equalizer, or 'passthrough' when the output device has enough channels. Default is taken from the settings file (downmix)")
+        )
+        .arg(
+            arg!(--device <NAME> "Audio output device to play on (see `tunein devices`). Default is taken from the settings file, else the system default")
+        )
+        .arg(
+            arg!(--output <OUTPUT> "Where to send audio: 'device', 'wav:<path>', 'stdout' (raw s16le) or 'fifo:<path>' (raw s16le). Headless outputs are 16-bit stereo 44.1 kHz").default_value("device")
+        )
         .subcommand(
             Command::new("search")
                 .about("Search for a radio station")
@@ -84,12 +127,22 @@ A simple CLI to listen to radio stations"#,
             Command::new("play")
                 .about("Play a radio station")
                 .arg(arg!(<station> "The station to play"))
-                .arg(arg!(--volume <VOLUME> "Set the initial volume (as a percent)").default_value("100"))
-                .arg(clap::Arg::new("display-mode").long("display-mode").help("Set the display mode to start with").default_value("Spectroscope"))
+                .arg(arg!(--volume <VOLUME> "Set the initial volume (as a percent), remembered for the station. Default is the station's last volume, or 100"))
+                .arg(clap::Arg::new("display-mode").long("display-mode").help("Set the display mode to start with: Oscilloscope, Vectorscope, Spectroscope, Spectrogram, Meters, Analyzer, Tuner or None").default_value("Spectroscope"))
+                .arg(arg!(--tune <NOTE> "Fit the oscilloscope window to one period of a note, e.g. A4 or C#3, and show it in the tuner"))
+                .arg(arg!(--scale <SCALE> "Vertical scale of the scope, 1.0 fits full scale. Default is taken from the settings file (1.0)"))
+                .arg(clap::Arg::new("scatter").long("scatter").help("Draw the scope as dots instead of lines, --scatter=false to turn it off. Default is taken from the settings file (false)").num_args(0..=1).require_equals(true).default_missing_value("true").value_parser(ValueParser::bool()))
+                .arg(clap::Arg::new("references").long("references").help("Draw reference lines, --references=false to turn them off. Default is taken from the settings file (false)").num_args(0..=1).require_equals(true).default_missing_value("true").value_parser(ValueParser::bool()))
+                .arg(clap::Arg::new("show-ui").long("show-ui").help("Draw the header line and axis labels, --show-ui=false to turn them off. Default is taken from the settings file (false)").num_args(0..=1).require_equals(true).default_missing_value("true").value_parser(ValueParser::bool()))
+                .arg(clap::Arg::new("braille").long("braille").help("Draw lines with braille dots instead of whole cells, --braille=false to turn it off. Default is taken from the settings file (true)").num_args(0..=1).require_equals(true).default_missing_value("true").value_parser(ValueParser::bool()))
+                .arg(arg!(--samples <SAMPLES> "Samples per channel the scope shows, and the spectrum size. Default is taken from the settings file (1152)"))
+                .arg(clap::Arg::new("sample-rate").long("sample-rate").help("Sample rate the spectrum modes assume until the stream reports its own. Default is taken from the settings file (44100)"))
+                .arg(arg!(--palette <COLORS> "Comma-separated channel colors: names like red or lightblue, indices or #rrggbb. Default is taken from the settings file (red,yellow,green,magenta)"))
                 .arg(clap::Arg::new("enable-os-media-controls").long("enable-os-media-controls").help("Should enable OS media controls?").default_value("true").value_parser(ValueParser::bool()))
                 .arg(clap::Arg::new("poll-events-every").long("poll-events-every").help("Poll for events every specified milliseconds.").default_value("16"))
                 .arg(clap::Arg::new("poll-events-every-while-paused").long("poll-events-every-while-paused").help("Poll for events every specified milliseconds while player is paused.").default_value("100")),
         )
+        .subcommand(Command::new("devices").about("List audio output devices"))
         .subcommand(
             Command::new("browse")
                 .about("Browse radio stations")
@@ -97,15 +150,81 @@ A simple CLI to listen to radio stations"#,
                 .arg(arg!(--offset <OFFSET> "The offset to start from").default_value("0"))
                 .arg(arg!(--limit <LIMIT> "The number of results to show").default_value("100")),
         )
+        .subcommand(
+            Command::new("alarm")
+                .about("Wake up to a radio station (rung by the `server` daemon)")
+                .args_conflicts_with_subcommands(true)
+                .arg(arg!([time] "Time to ring at, HH:MM (24h, local time)"))
+                .arg(arg!(--station <STATION> "The station to play"))
+                .arg(arg!(--days <DAYS> "Comma-separated days to ring on, e.g. mon,tue,fri. Default is every day"))
+                .arg(arg!(--volume <VOLUME> "Volume to ramp up to (as a percent)").default_value("100"))
+                .arg(arg!(--ramp <SECONDS> "How long the volume takes to ramp up. Default is 180"))
+                .arg(arg!(--fallback <FILE> "Local sound file to loop if the stream fails"))
+                .subcommand(Command::new("list").about("List alarms"))
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove an alarm")
+                        .arg(arg!(<id> "The alarm id")),
+                )
+                .subcommand(
+                    Command::new("snooze")
+                        .about("Snooze a ringing alarm")
+                        .arg(arg!(<id> "The alarm id"))
+                        .arg(arg!(--minutes <MINUTES> "How long to snooze for. Default is 9")),
+                ),
+        )
+        .subcommand(
+            Command::new("eq")
+                .about("Manage equalizer presets")
+                .subcommand(Command::new("list").about("List built-in and saved presets"))
+                .subcommand(
+                    Command::new("apply")
+                        .about("Switch the equalizer to a preset and turn it on")
+                        .arg(arg!(<name> "The preset name")),
+                )
+                .subcommand(
+                    Command::new("save")
+                        .about("Save the current equalizer bands as a preset")
+                        .arg(arg!(<name> "The preset name")),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove a saved preset")
+                        .arg(arg!(<name> "The preset name")),
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("Write a preset as a Rockbox .cfg or AutoEQ ParametricEQ.txt file")
+                        .arg(arg!(<name> "The preset name"))
+                        .arg(arg!(<file> "The file to write"))
+                        .arg(arg!(--format <FORMAT> "'rockbox' or 'autoeq'. Default follows the file extension (.cfg is rockbox)")),
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("Save a Rockbox .cfg or AutoEQ ParametricEQ.txt file as a preset")
+                        .arg(arg!(<file> "The file to read"))
+                        .arg(arg!(--name <NAME> "The preset name. Default is the file name"))
+                        .arg(arg!(--format <FORMAT> "'rockbox' or 'autoeq'. Default is detected from the contents")),
+                ),
+        )
         .subcommand(
             Command::new("server")
                 .about("Start the server")
-                .arg(arg!([port] "The port to listen on").default_value("8090")),
+                .arg(arg!([port] "The port to listen on").default_value("8090"))
+                .arg(arg!(--"relay-port" <PORT> "The port of the /listen HTTP stream relay").default_value("8091")),
+        )
+        .subcommand(
+            Command::new("relay")
+                .about("Rebroadcast a station to an Icecast server as a source client")
+                .arg(arg!(<station> "The station to relay"))
+                .arg(arg!(--to <URL> "The Icecast mount, icecast://[user:password@]host[:port]/mount").required(true))
+                .arg(arg!(--eq "Decode, equalize and re-encode the stream as WAV instead of passing it through")),
         )
         .subcommand(
             Command::new("web")
                 .about("Start the web UI & GraphQL API server")
-                .arg(arg!([port] "The port to listen on").default_value("8881")),
+                .arg(arg!([port] "The port to listen on").default_value("8881"))
+                .arg(arg!(--server <URL> "gRPC address of the `tunein server` daemon, for playback stats and night mode").default_value("http://localhost:8090")),
         )
         .subcommand(
             Command::new("service")
@@ -128,7 +247,35 @@ A simple CLI to listen to radio stations"#,
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     let matches = cli().get_matches();
+    let settings = settings::Settings::load();
     let provider = matches.get_one::<String>("provider").unwrap().to_string();
+    let crossfade = match matches.get_one::<String>("crossfade") {
+        Some(ms) => Duration::from_millis(ms.parse::<u64>()?),
+        None => settings.crossfade(),
+    };
+    let buffer = {
+        let size = match matches.get_one::<String>("buffer") {
+            Some(kb) => kb.parse::<usize>()?,
+            None => settings.buffer_kb,
+        };
+        let prebuffer = match matches.get_one::<String>("prebuffer") {
+            Some(kb) => kb.parse::<usize>()?,
+            None => settings.prebuffer_kb,
+        };
+        BufferOptions::from_kib(size, prebuffer)
+    };
+    let resample = matches.get_flag("resample") || settings.resample;
+    if let Some(mode) = matches.get_one::<String>("multichannel") {
+        downmix::ChannelPolicy::global().set_mode(mode.parse()?);
+    }
+    let output_device = matches
+        .get_one::<String>("device")
+        .cloned()
+        .or_else(|| settings.output_device.clone());
+    let output = match matches.get_one::<String>("output").unwrap().parse()? {
+        OutputSpec::Device(None) => OutputSpec::Device(output_device.clone()),
+        output => output,
+    };
 
     match matches.subcommand() {
         Some(("search", args)) => {
@@ -139,14 +286,56 @@ async fn main() -> Result<(), Error> {
             let station = args.get_one::<String>("station").unwrap();
             let volume = args
                 .get_one::<String>("volume")
-                .unwrap()
-                .parse::<f32>()
-                .unwrap();
+                .map(|volume| volume.parse::<f32>())
+                .transpose()?;
             let display_mode = args
                 .get_one::<String>("display-mode")
                 .unwrap()
                 .parse::<CurrentDisplayMode>()
                 .unwrap();
+            let tune = args.get_one::<String>("tune").cloned();
+            if let Some(note) = &tune {
+                note.parse::<music::Note>().map_err(|_| {
+                    anyhow::anyhow!("unrecognized note '{}', expected e.g. A4 or C#3", note)
+                })?;
+            }
+            let scale = match args.get_one::<String>("scale") {
+                Some(scale) => scale.parse::<f32>()?,
+                None => settings.scope_scale,
+            };
+            let samples = match args.get_one::<String>("samples") {
+                Some(samples) => samples.parse::<u32>()?,
+                None => settings.scope_samples,
+            };
+            let sample_rate = match args.get_one::<String>("sample-rate") {
+                Some(rate) => rate.parse::<u32>()?,
+                None => settings.scope_sample_rate,
+            };
+            if scale <= 0.0 || samples == 0 || sample_rate == 0 {
+                return Err(Error::msg(
+                    "the scope's scale, samples and sample rate must be above 0",
+                ));
+            }
+            let palette = match args.get_one::<String>("palette") {
+                Some(colors) => UiOptions::parse_palette(&colors.split(',').collect::<Vec<_>>())?,
+                None => UiOptions::parse_palette(&settings.scope_palette)?,
+            };
+            let flag =
+                |name: &str, default: bool| args.get_one::<bool>(name).copied().unwrap_or(default);
+            let ui = UiOptions {
+                scale,
+                scatter: flag("scatter", settings.scope_scatter),
+                no_reference: !flag("references", settings.scope_references),
+                no_ui: !flag("show-ui", settings.scope_show_ui),
+                no_braille: !flag("braille", settings.scope_braille),
+                palette,
+            };
+            let source = SourceOptions {
+                channels: 2,
+                buffer: samples,
+                sample_rate,
+                tune,
+            };
             let enable_os_media_controls = args.get_one("enable-os-media-controls").unwrap();
             let poll_events_every = Duration::from_millis(
                 args.get_one::<String>("poll-events-every")
@@ -165,12 +354,19 @@ async fn main() -> Result<(), Error> {
                 provider.as_str(),
                 volume,
                 display_mode,
+                ui,
+                source,
                 *enable_os_media_controls,
                 poll_events_every,
                 poll_events_every_while_paused,
+                crossfade,
+                buffer,
+                resample,
+                output,
             )
             .await?;
         }
+        Some(("devices", _)) => device::print_list(output_device.as_deref())?,
         Some(("browse", args)) => {
             let category = args.get_one::<String>("category").map(|s| s.as_str());
             let offset = args.get_one::<String>("offset").unwrap();
@@ -183,15 +379,96 @@ async fn main() -> Result<(), Error> {
             )
             .await?;
         }
+        Some(("alarm", args)) => match args.subcommand() {
+            Some(("list", _)) => alarm::list()?,
+            Some(("remove", args)) => {
+                let id = args.get_one::<String>("id").unwrap().parse::<u32>()?;
+                alarm::remove(id)?;
+            }
+            Some(("snooze", args)) => {
+                let id = args.get_one::<String>("id").unwrap().parse::<u32>()?;
+                let minutes = match args.get_one::<String>("minutes") {
+                    Some(minutes) => minutes.parse::<u32>()?,
+                    None => alarm::DEFAULT_SNOOZE_MINUTES,
+                };
+                alarm::snooze(id, minutes)?;
+            }
+            _ => match args.get_one::<String>("time") {
+                Some(time) => {
+                    let station = args
+                        .get_one::<String>("station")
+                        .ok_or_else(|| anyhow::anyhow!("--station is required to set an alarm"))?;
+                    let days = match args.get_one::<String>("days") {
+                        Some(days) => alarm::normalize_days(days)?,
+                        None => Vec::new(),
+                    };
+                    alarm::add(alarm::Alarm {
+                        id: 0,
+                        time: time.to_string(),
+                        days,
+                        station: station.to_string(),
+                        provider: provider.clone(),
+                        volume: args.get_one::<String>("volume").unwrap().parse::<f32>()?,
+                        ramp_secs: match args.get_one::<String>("ramp") {
+                            Some(ramp) => ramp.parse::<u64>()?,
+                            None => alarm::DEFAULT_RAMP_SECS,
+                        },
+                        fallback: args.get_one::<String>("fallback").cloned(),
+                        enabled: true,
+                        snoozed_until: None,
+                        ringing: false,
+                    })?;
+                }
+                None => alarm::list()?,
+            },
+        },
+        Some(("eq", args)) => match args.subcommand() {
+            Some(("apply", args)) => eq_presets::apply(args.get_one::<String>("name").unwrap())?,
+            Some(("save", args)) => eq_presets::save(args.get_one::<String>("name").unwrap())?,
+            Some(("remove", args)) => eq_presets::remove(args.get_one::<String>("name").unwrap())?,
+            Some(("export", args)) => {
+                let format = args
+                    .get_one::<String>("format")
+                    .map(|format| format.parse())
+                    .transpose()?;
+                eq_presets::export(
+                    args.get_one::<String>("name").unwrap(),
+                    Path::new(args.get_one::<String>("file").unwrap()),
+                    format,
+                )?;
+            }
+            Some(("import", args)) => {
+                let format = args
+                    .get_one::<String>("format")
+                    .map(|format| format.parse())
+                    .transpose()?;
+                eq_presets::import(
+                    Path::new(args.get_one::<String>("file").unwrap()),
+                    args.get_one::<String>("name").map(|name| name.as_str()),
+                    format,
+                )?;
+            }
+            _ => eq_presets::list()?,
+        },
         Some(("server", args)) => {
             let port = args.get_one::<String>("port").unwrap();
             let port = port.parse::<u16>().unwrap();
-            server::exec(port).await?;
+            let relay_port = args
+                .get_one::<String>("relay-port")
+                .unwrap()
+                .parse::<u16>()?;
+            server::exec(port, relay_port, crossfade, buffer, resample, output).await?;
+        }
+        Some(("relay", args)) => {
+            let station = args.get_one::<String>("station").unwrap();
+            let target = args.get_one::<String>("to").unwrap().parse()?;
+            icecast::exec(station, provider.as_str(), target, args.get_flag("eq")).await?;
         }
         Some(("web", args)) => {
             let port = args.get_one::<String>("port").unwrap();
             let port = port.parse::<u16>().unwrap();
-            webserver::exec(port).await?;
+            let daemon = args.get_one::<String>("server").unwrap().to_string();
+            webserver::exec(port, daemon).await?;
         }
         Some(("service", sub_m)) => match sub_m.subcommand() {
             Some(("install", _)) => service::install()?,
@@ -203,7 +480,7 @@ async fn main() -> Result<(), Error> {
             }
         },
         None => {
-            interactive::run(provider.as_str()).await?;
+            interactive::run(provider.as_str(), crossfade, buffer, resample, output).await?;
         }
         Some((other, _)) => {
             eprintln!(
@@ -0,0 +1,534 @@
+//! Station-switch crossfader.
+//!
+//! A [`Mixer`] is appended to the output sink once and plays for the whole
+//! session. New streams are connected and pre-buffered by the caller, then
+//! handed over through a [`MixerHandle`]; the mixer fades the old stream out
+//! and the new one in with an equal-power curve, so switching stations never
+//! leaves a gap of silence. The equalizer and the visualizer feed sit after
+//! the mix, so both streams of a crossfade are equalized once, together.
+//! Everything is mixed in f32, so two overlapping streams can't clip before
+//! the output's final conversion.
+//!
+//! The mixer also counts underruns: chunks of the current stream that took
+//! longer to decode than they take to play, i.e. the output went dry
+//! waiting on the network.
+
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_PI_2;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rodio::source::UniformSourceIterator;
+use rodio::Source;
+
+use crate::decoder::Frame;
+use crate::dynamics::DynamicsProcessor;
+use crate::equalizer::EqProcessor;
+use crate::stereo::StereoProcessor;
+
+/// Any stream the mixer can play.
+pub type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+/// Frames mixed (and equalized) per chunk.
+const CHUNK_FRAMES: usize = 1024;
+
+/// Format of the silence played while no stream is loaded.
+const IDLE_CHANNELS: u16 = 2;
+const IDLE_SAMPLE_RATE: u32 = 44100;
+
+/// How much audio to decode before a new stream is handed to the mixer.
+pub const PREBUFFER: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct Pending {
+    source: Option<(BoxedSource, Duration)>,
+    stop: bool,
+}
+
+/// Cheap, cloneable control handle for a [`Mixer`] living inside a sink.
+#[derive(Clone)]
+pub struct MixerHandle {
+    pending: Arc<Mutex<Pending>>,
+    underruns: Arc<AtomicU64>,
+}
+
+impl MixerHandle {
+    /// Fade whatever is playing out and `source` in over `fade`. A zero
+    /// `fade`, or nothing playing, switches immediately.
+    pub fn crossfade_to(&self, source: BoxedSource, fade: Duration) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.source = Some((source, fade));
+        pending.stop = false;
+    }
+
+    /// Drop every stream; the mixer plays silence until the next one.
+    pub fn stop(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.source = None;
+        pending.stop = true;
+    }
+
+    /// Underruns of the current stream so far; reset on every switch.
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+}
+
+/// The stream being faded out, converted to the incoming stream's format.
+struct Outgoing {
+    source: UniformSourceIterator<Unframed, f32>,
+    position: usize,
+    length: usize,
+}
+
+pub struct Mixer {
+    pending: Arc<Mutex<Pending>>,
+    underruns: Arc<AtomicU64>,
+    current: Option<BoxedSource>,
+    outgoing: Option<Outgoing>,
+    eq: Option<EqProcessor>,
+    stereo: Option<StereoProcessor>,
+    dynamics: Option<DynamicsProcessor>,
+    tx: Option<Sender<Frame>>,
+    buffer: Vec<f32>,
+    offset: usize,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Mixer {
+    /// Create an idle mixer. Each mixed chunk is forwarded to `tx` for the
+    /// visualizer, after the equalizer, stereo controls and night mode.
+    pub fn new(tx: Option<Sender<Frame>>) -> (Self, MixerHandle) {
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let underruns = Arc::new(AtomicU64::new(0));
+        let mixer = Self {
+            pending: pending.clone(),
+            underruns: underruns.clone(),
+            current: None,
+            outgoing: None,
+            eq: Some(EqProcessor::new()),
+            stereo: Some(StereoProcessor::new()),
+            dynamics: Some(DynamicsProcessor::new()),
+            tx,
+            buffer: Vec::new(),
+            offset: 0,
+            channels: IDLE_CHANNELS,
+            sample_rate: IDLE_SAMPLE_RATE,
+        };
+        (mixer, MixerHandle { pending, underruns })
+    }
+
+    /// Pick up a stream or stop request queued through the handle.
+    fn take_pending(&mut self) {
+        let mut pending = self.pending.lock().unwrap();
+        if std::mem::take(&mut pending.stop) {
+            self.current = None;
+            self.outgoing = None;
+            self.underruns.store(0, Ordering::Relaxed);
+        }
+        let Some((source, fade)) = pending.source.take() else {
+            return;
+        };
+        drop(pending);
+        self.underruns.store(0, Ordering::Relaxed);
+
+        let length = (fade.as_secs_f64() * source.sample_rate() as f64) as usize;
+        self.outgoing = match self.current.take() {
+            Some(old) if length > 0 => Some(Outgoing {
+                source: UniformSourceIterator::new(
+                    Unframed(old),
+                    source.channels(),
+                    source.sample_rate(),
+                ),
+                position: 0,
+                length,
+            }),
+            _ => None,
+        };
+        self.current = Some(source);
+    }
+
+    /// Mix the next chunk into `buffer`.
+    fn fill(&mut self) {
+        self.take_pending();
+        self.buffer.clear();
+        self.offset = 0;
+
+        let (channels, sample_rate) = match self.current.as_mut() {
+            Some(current) => {
+                let channels = current.channels().max(1);
+                let sample_rate = current.sample_rate();
+                // Stop at the decoder's frame boundary so a format change
+                // never lands in the middle of a chunk.
+                let len = match current.current_frame_len() {
+                    Some(frame_len) if frame_len > 0 => {
+                        frame_len.min(CHUNK_FRAMES * channels as usize)
+                    }
+                    _ => CHUNK_FRAMES * channels as usize,
+                };
+                let started = Instant::now();
+                self.buffer.extend(current.by_ref().take(len));
+                if self.buffer.is_empty() {
+                    // End of stream (dropped connection): fall back to silence.
+                    self.current = None;
+                } else {
+                    let frames = self.buffer.len() / channels as usize;
+                    let plays_for = Duration::from_secs_f64(frames as f64 / sample_rate as f64);
+                    if started.elapsed() > plays_for {
+                        self.underruns.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                (channels, sample_rate)
+            }
+            None => {
+                self.outgoing = None;
+                (IDLE_CHANNELS, self.sample_rate)
+            }
+        };
+
+        // Fade over what the new stream actually gave, which is less than a
+        // full chunk when its frames are shorter.
+        if let Some(outgoing) = &mut self.outgoing {
+            let length = outgoing.length as f32;
+            for (i, frame) in self.buffer.chunks_mut(channels as usize).enumerate() {
+                let t = ((outgoing.position + i) as f32 / length).min(1.0);
+                let (fade_in, fade_out) = ((t * FRAC_PI_2).sin(), (t * FRAC_PI_2).cos());
+                for sample in frame {
+                    let old = outgoing.source.next().unwrap_or(0.0);
+                    *sample = *sample * fade_in + old * fade_out;
+                }
+            }
+            outgoing.position += self.buffer.len() / channels as usize;
+            if outgoing.position >= outgoing.length {
+                self.outgoing = None;
+            }
+        }
+
+        self.channels = channels;
+        if self.buffer.is_empty() {
+            self.buffer
+                .resize(CHUNK_FRAMES * IDLE_CHANNELS as usize, 0.0);
+            self.channels = IDLE_CHANNELS;
+        } else {
+            if let Some(eq) = &mut self.eq {
+                eq.process(&mut self.buffer, channels, sample_rate);
+            }
+            if let Some(stereo) = &mut self.stereo {
+                stereo.process(&mut self.buffer, self.channels);
+            }
+            if let Some(dynamics) = &mut self.dynamics {
+                dynamics.process(&mut self.buffer, self.channels, sample_rate);
+            }
+        }
+        self.sample_rate = sample_rate;
+
+        if let Some(tx) = &self.tx {
+            let frame = Frame {
+                data: self.buffer.clone(),
+                channels: self.channels as usize,
+                sample_rate: self.sample_rate as i32,
+            };
+            if tx.send(frame).is_err() {
+                // The visualizer is gone; keep playing without it.
+                self.tx = None;
+            }
+        }
+    }
+}
+
+impl Source for Mixer {
+    #[inline]
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.buffer.len().saturating_sub(self.offset))
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Iterator for Mixer {
+    type Item = f32;
+
+    /// Never ends: silence is played while no stream is loaded.
+    #[inline]
+    fn next(&mut self) -> Option<f32> {
+        if self.offset >= self.buffer.len() {
+            self.fill();
+        }
+
+        let v = self.buffer[self.offset];
+        self.offset += 1;
+
+        Some(v)
+    }
+}
+
+/// Hides the inner frame boundaries from `UniformSourceIterator`, which
+/// otherwise stops at the first empty frame a decoder reports between two
+/// packets. The outgoing stream's format is frozen for the fade anyway.
+struct Unframed(BoxedSource);
+
+impl Source for Unframed {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.0.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.0.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Iterator for Unframed {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.0.next()
+    }
+}
+
+/// A stream with its first `duration` of audio already decoded, so the
+/// handover to the mixer does not stall on the network.
+pub struct Prebuffered<S> {
+    buffered: VecDeque<f32>,
+    channels: u16,
+    sample_rate: u32,
+    inner: S,
+}
+
+impl<S> Prebuffered<S>
+where
+    S: Source<Item = f32>,
+{
+    /// Decode `duration` of `inner` up front. Blocks until it is available.
+    pub fn new(mut inner: S, duration: Duration) -> Self {
+        let channels = inner.channels();
+        let sample_rate = inner.sample_rate();
+        let len = (duration.as_secs_f64() * sample_rate as f64) as usize * channels as usize;
+        let buffered = inner.by_ref().take(len).collect();
+        Self {
+            buffered,
+            channels,
+            sample_rate,
+            inner,
+        }
+    }
+}
+
+impl<S> Source for Prebuffered<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.buffered.len() {
+            0 => self.inner.current_frame_len(),
+            len => Some(len),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self.buffered.is_empty() {
+            true => self.inner.channels(),
+            false => self.channels,
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self.buffered.is_empty() {
+            true => self.inner.sample_rate(),
+            false => self.sample_rate,
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<S> Iterator for Prebuffered<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.buffered.pop_front().or_else(|| self.inner.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    /// A mixer without the DSP stages: the equalizer tests mutate the
+    /// process-wide EQ concurrently, and all of them are seeded from the
+    /// user's settings.
+    fn mixer() -> (Mixer, MixerHandle) {
+        let (mut mixer, handle) = Mixer::new(None);
+        mixer.eq = None;
+        mixer.stereo = None;
+        mixer.dynamics = None;
+        (mixer, handle)
+    }
+
+    fn constant(value: f32, frames: usize) -> BoxedSource {
+        Box::new(SamplesBuffer::new(2, 44100, vec![value; frames * 2]))
+    }
+
+    #[test]
+    fn idle_mixer_plays_silence() {
+        let (mut mixer, _handle) = mixer();
+        assert!(mixer.by_ref().take(10_000).all(|s| s == 0.0));
+        assert_eq!(mixer.channels(), IDLE_CHANNELS);
+    }
+
+    #[test]
+    fn crossfade_moves_from_old_to_new() {
+        let (mut mixer, handle) = mixer();
+        handle.crossfade_to(constant(0.3, 100_000), Duration::ZERO);
+        assert_eq!(mixer.next(), Some(0.3));
+        // Finish the chunk so the next one picks up the switch.
+        let rest = mixer.current_frame_len().unwrap();
+        mixer.by_ref().take(rest).for_each(drop);
+
+        // 4410 frames = 100 ms at 44.1 kHz.
+        handle.crossfade_to(constant(-0.3, 100_000), Duration::from_millis(100));
+        let faded: Vec<f32> = mixer.by_ref().take(4410 * 2).collect();
+        assert!(faded[0] > 0.27, "starts on the old stream: {}", faded[0]);
+        let middle = faded[4410];
+        assert!(
+            middle.abs() < 0.015,
+            "both at equal power mid-fade: {middle}"
+        );
+        assert!(faded[4410 * 2 - 1] < -0.27);
+
+        // Once the fade is over only the new stream is heard.
+        assert!(mixer.by_ref().take(4096).all(|s| s == -0.3));
+    }
+
+    #[test]
+    fn crossfade_into_short_frames_has_no_gaps() {
+        /// A constant stream in 960-frame packets, like Opus.
+        struct ShortFrames(usize);
+        impl Iterator for ShortFrames {
+            type Item = f32;
+            fn next(&mut self) -> Option<f32> {
+                self.0 += 1;
+                Some(0.3)
+            }
+        }
+        impl Source for ShortFrames {
+            fn current_frame_len(&self) -> Option<usize> {
+                Some(960 * 2 - self.0 % (960 * 2))
+            }
+            fn channels(&self) -> u16 {
+                2
+            }
+            fn sample_rate(&self) -> u32 {
+                44100
+            }
+            fn total_duration(&self) -> Option<Duration> {
+                None
+            }
+        }
+
+        let (mut mixer, handle) = mixer();
+        handle.crossfade_to(constant(0.3, 100_000), Duration::ZERO);
+        mixer.next();
+        let rest = mixer.current_frame_len().unwrap();
+        mixer.by_ref().take(rest).for_each(drop);
+
+        // Two equal streams never dip below either of them at equal power.
+        handle.crossfade_to(Box::new(ShortFrames(0)), Duration::from_millis(100));
+        let faded: Vec<f32> = mixer.by_ref().take(4410 * 2).collect();
+        assert!(faded.iter().all(|&s| s >= 0.299), "gap in the fade");
+        // 90% in, the old stream is still there: the fade didn't run ahead.
+        assert!(faded[4000 * 2] > 0.33, "{}", faded[4000 * 2]);
+        assert!(mixer.by_ref().take(4096).all(|s| s == 0.3));
+    }
+
+    #[test]
+    fn stop_silences_and_keeps_running() {
+        let (mut mixer, handle) = mixer();
+        handle.crossfade_to(constant(0.15, 100_000), Duration::ZERO);
+        assert_eq!(mixer.next(), Some(0.15));
+        let rest = mixer.current_frame_len().unwrap();
+        mixer.by_ref().take(rest).for_each(drop);
+
+        handle.stop();
+        assert!(mixer.by_ref().take(4096).all(|s| s == 0.0));
+    }
+
+    #[test]
+    fn prebuffer_keeps_every_sample() {
+        let samples: Vec<f32> = (0..20_000).map(|i| i as f32 / 20_000.0).collect();
+        let source = SamplesBuffer::new(2, 8000, samples.clone());
+        let prebuffered = Prebuffered::new(source, Duration::from_millis(500));
+        assert_eq!(prebuffered.buffered.len(), 8000);
+        assert_eq!(prebuffered.current_frame_len(), Some(8000));
+        assert_eq!(prebuffered.collect::<Vec<_>>(), samples);
+    }
+
+    #[test]
+    fn a_stalling_stream_counts_as_underrun() {
+        /// Plays silence but sleeps before every chunk, like a starved
+        /// network connection.
+        struct Stalling(usize);
+        impl Iterator for Stalling {
+            type Item = f32;
+            fn next(&mut self) -> Option<f32> {
+                self.0 += 1;
+                if self.0 % 2048 == 1 {
+                    std::thread::sleep(Duration::from_millis(40));
+                }
+                Some(0.0)
+            }
+        }
+        impl Source for Stalling {
+            fn current_frame_len(&self) -> Option<usize> {
+                Some(2048 - (self.0 % 2048))
+            }
+            fn channels(&self) -> u16 {
+                2
+            }
+            fn sample_rate(&self) -> u32 {
+                44100
+            }
+            fn total_duration(&self) -> Option<Duration> {
+                None
+            }
+        }
+
+        let (mut mixer, handle) = mixer();
+        handle.crossfade_to(constant(0.0, 100_000), Duration::ZERO);
+        mixer.by_ref().take(4096).for_each(drop);
+        assert_eq!(handle.underruns(), 0);
+
+        handle.crossfade_to(Box::new(Stalling(0)), Duration::ZERO);
+        mixer.by_ref().take(2048 * 3).for_each(drop);
+        assert!(handle.underruns() >= 2, "{}", handle.underruns());
+    }
+}
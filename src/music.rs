@@ -1,4 +1,4 @@
-use std::{num::ParseIntError, str::FromStr};
+use std::{fmt, num::ParseIntError, str::FromStr};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Tone {
@@ -73,7 +73,53 @@ impl FromStr for Tone {
     }
 }
 
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.tone, self.octave)
+    }
+}
+
+impl fmt::Display for Tone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Tone::C => "C",
+            Tone::Db => "C#",
+            Tone::D => "D",
+            Tone::Eb => "D#",
+            Tone::E => "E",
+            Tone::F => "F",
+            Tone::Gb => "F#",
+            Tone::G => "G",
+            Tone::Ab => "G#",
+            Tone::A => "A",
+            Tone::Bb => "A#",
+            Tone::B => "B",
+        };
+        f.write_str(name)
+    }
+}
+
 impl Note {
+    pub fn freq(&self) -> f32 {
+        self.tone.freq(self.octave)
+    }
+
+    /// The note closest to `freq`, and how far off `freq` is in cents.
+    /// `None` below C0.
+    pub fn nearest(freq: f32) -> Option<(Note, f32)> {
+        let semitones = (12.0 * (freq / Tone::C.freq(0)).log2()).round();
+        if semitones.is_nan() || semitones < 0.0 {
+            return None;
+        }
+        let semitones = semitones as usize;
+        let note = Note {
+            tone: Tone::ALL[semitones % 12].clone(),
+            octave: (semitones / 12) as u32,
+        };
+        let cents = 1200.0 * (freq / note.freq()).log2();
+        Some((note, cents))
+    }
+
     pub fn tune_buffer_size(&self, sample_rate: u32) -> u32 {
         let t = 1.0 / self.tone.freq(self.octave); // periodo ?
         let buf = (sample_rate as f32) * t;
@@ -82,6 +128,22 @@ impl Note {
 }
 
 impl Tone {
+    /// The twelve tones of an octave, from C up.
+    pub const ALL: [Tone; 12] = [
+        Tone::C,
+        Tone::Db,
+        Tone::D,
+        Tone::Eb,
+        Tone::E,
+        Tone::F,
+        Tone::Gb,
+        Tone::G,
+        Tone::Ab,
+        Tone::A,
+        Tone::Bb,
+        Tone::B,
+    ];
+
     pub fn freq(&self, octave: u32) -> f32 {
         match octave {
             0 => match self {
@@ -108,3 +170,25 @@ impl Tone {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_note_and_cents() {
+        let (note, cents) = Note::nearest(440.0).unwrap();
+        assert_eq!(note, "A4".parse().unwrap());
+        assert!(cents.abs() < 0.1);
+
+        // A quarter tone above C#3, and a touch flat of E2.
+        let (note, cents) = Note::nearest(138.59 * 2f32.powf(0.25 / 12.0)).unwrap();
+        assert_eq!(note.to_string(), "C#3");
+        assert!((cents - 25.0).abs() < 0.5, "{cents}");
+        let (note, cents) = Note::nearest(82.0).unwrap();
+        assert_eq!(note.to_string(), "E2");
+        assert!(cents < 0.0);
+
+        assert!(Note::nearest(10.0).is_none());
+    }
+}
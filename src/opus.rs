@@ -0,0 +1,443 @@
+//! Opus decoding, which symphonia 0.5 doesn't ship.
+//!
+//! [`OpusDecoder`] puts libopus' multistream decoder (which also covers
+//! plain mono and stereo streams) behind symphonia's `Decoder` trait, and
+//! [`codecs`] registers it next to symphonia's own codecs. Symphonia's Ogg
+//! and Matroska demuxers already recognise Opus tracks, so Ogg/Opus and
+//! WebM mounts then play through [`crate::decoder::StreamDecoder`] like any
+//! other stream, equalizer and visualizers included.
+
+use std::os::raw::c_int;
+use std::sync::OnceLock;
+
+use audiopus_sys as ffi;
+use symphonia::core::audio::{
+    AsAudioBufferRef, AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec,
+};
+use symphonia::core::codecs::{
+    CodecDescriptor, CodecParameters, CodecRegistry, Decoder, DecoderOptions, FinalizeResult,
+    CODEC_TYPE_OPUS,
+};
+use symphonia::core::errors::{decode_error, unsupported_error, Result};
+use symphonia::core::formats::Packet;
+
+/// Opus always decodes at 48 kHz.
+const SAMPLE_RATE: u32 = 48_000;
+
+/// Longest packet Opus allows: 120 ms.
+const MAX_FRAMES: usize = 5760;
+
+/// Symphonia's codec registry plus Opus. Use it wherever
+/// `symphonia::default::get_codecs()` would be.
+pub fn codecs() -> &'static CodecRegistry {
+    static CODECS: OnceLock<CodecRegistry> = OnceLock::new();
+    CODECS.get_or_init(|| {
+        let mut registry = CodecRegistry::new();
+        symphonia::default::register_enabled_codecs(&mut registry);
+        registry.register_all::<OpusDecoder>();
+        registry
+    })
+}
+
+/// The `OpusHead` identification header (RFC 7845 §5.1), which both the
+/// Ogg and the Matroska demuxer pass on as the track's extra data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Head {
+    channels: u8,
+    /// Samples of decoder warm-up to drop at the start.
+    pre_skip: u16,
+    /// Output gain in dB, Q7.8.
+    gain: i16,
+    streams: u8,
+    coupled: u8,
+    /// Decoded channel for each output channel.
+    mapping: Vec<u8>,
+    /// Speakers of the output channels, in the order libopus produces them.
+    speakers: Vec<Channels>,
+}
+
+impl Head {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 19 || &data[..8] != b"OpusHead" {
+            return decode_error("opus: invalid identification header");
+        }
+        let channels = data[9];
+        let pre_skip = u16::from_le_bytes([data[10], data[11]]);
+        let gain = i16::from_le_bytes([data[16], data[17]]);
+        let family = data[18];
+        if channels == 0 {
+            return decode_error("opus: no channels");
+        }
+
+        if family == 0 {
+            if channels > 2 {
+                return decode_error("opus: more than two channels without a mapping table");
+            }
+            return Ok(Self::stereo(channels, pre_skip, gain));
+        }
+
+        let Some(table) = data.get(19..21 + channels as usize) else {
+            return decode_error("opus: truncated channel mapping table");
+        };
+        let speakers = match family {
+            1 => match vorbis_order(channels) {
+                Some(speakers) => speakers,
+                None => return unsupported_error("opus: more than 8 channels"),
+            },
+            // Ambisonics and unknown families: keep the channels, with no
+            // speaker positions to go by.
+            _ => (0..channels)
+                .map(|i| Channels::from_bits_truncate(1 << i))
+                .collect(),
+        };
+        Ok(Self {
+            channels,
+            pre_skip,
+            gain,
+            streams: table[0],
+            coupled: table[1],
+            mapping: table[2..].to_vec(),
+            speakers,
+        })
+    }
+
+    /// Mono or stereo, as a single stream. Also what a track without an
+    /// identification header is assumed to be.
+    fn stereo(channels: u8, pre_skip: u16, gain: i16) -> Self {
+        let speakers = match channels {
+            1 => vec![Channels::FRONT_LEFT],
+            _ => vec![Channels::FRONT_LEFT, Channels::FRONT_RIGHT],
+        };
+        Self {
+            channels: speakers.len() as u8,
+            pre_skip,
+            gain,
+            streams: 1,
+            coupled: speakers.len() as u8 - 1,
+            mapping: (0..speakers.len() as u8).collect(),
+            speakers,
+        }
+    }
+
+    fn layout(&self) -> Channels {
+        self.speakers
+            .iter()
+            .fold(Channels::empty(), |layout, &speaker| layout | speaker)
+    }
+
+    /// For each channel libopus decodes, its plane in a symphonia buffer,
+    /// which orders channels by speaker bit.
+    fn planes(&self) -> Vec<usize> {
+        self.speakers
+            .iter()
+            .map(|speaker| (self.layout().bits() & (speaker.bits() - 1)).count_ones() as usize)
+            .collect()
+    }
+}
+
+/// Channel order of mapping family 1, which is Vorbis'.
+fn vorbis_order(channels: u8) -> Option<Vec<Channels>> {
+    use Channels as C;
+    let order = match channels {
+        1 => vec![C::FRONT_LEFT],
+        2 => vec![C::FRONT_LEFT, C::FRONT_RIGHT],
+        3 => vec![C::FRONT_LEFT, C::FRONT_CENTRE, C::FRONT_RIGHT],
+        4 => vec![C::FRONT_LEFT, C::FRONT_RIGHT, C::REAR_LEFT, C::REAR_RIGHT],
+        5 => vec![
+            C::FRONT_LEFT,
+            C::FRONT_CENTRE,
+            C::FRONT_RIGHT,
+            C::REAR_LEFT,
+            C::REAR_RIGHT,
+        ],
+        6 => vec![
+            C::FRONT_LEFT,
+            C::FRONT_CENTRE,
+            C::FRONT_RIGHT,
+            C::REAR_LEFT,
+            C::REAR_RIGHT,
+            C::LFE1,
+        ],
+        7 => vec![
+            C::FRONT_LEFT,
+            C::FRONT_CENTRE,
+            C::FRONT_RIGHT,
+            C::SIDE_LEFT,
+            C::SIDE_RIGHT,
+            C::REAR_CENTRE,
+            C::LFE1,
+        ],
+        8 => vec![
+            C::FRONT_LEFT,
+            C::FRONT_CENTRE,
+            C::FRONT_RIGHT,
+            C::SIDE_LEFT,
+            C::SIDE_RIGHT,
+            C::REAR_LEFT,
+            C::REAR_RIGHT,
+            C::LFE1,
+        ],
+        _ => return None,
+    };
+    Some(order)
+}
+
+/// Owned libopus multistream decoder state.
+struct MsDecoder(*mut ffi::OpusMSDecoder);
+
+// The state is only ever used through `&mut self`, so moving it to the
+// thread rodio decodes on, or sharing a reference that can't touch it, is
+// sound.
+unsafe impl Send for MsDecoder {}
+unsafe impl Sync for MsDecoder {}
+
+impl MsDecoder {
+    fn new(head: &Head) -> Result<Self> {
+        let mut error: c_int = 0;
+        let state = unsafe {
+            ffi::opus_multistream_decoder_create(
+                SAMPLE_RATE as i32,
+                head.channels as c_int,
+                head.streams as c_int,
+                head.coupled as c_int,
+                head.mapping.as_ptr(),
+                &mut error,
+            )
+        };
+        if state.is_null() || error != ffi::OPUS_OK as c_int {
+            return decode_error("opus: invalid stream configuration");
+        }
+        Ok(Self(state))
+    }
+
+    /// Decode `packet` into interleaved `pcm`, returning the frames decoded.
+    fn decode(&mut self, packet: &[u8], pcm: &mut [f32], channels: usize) -> Result<usize> {
+        let decoded = unsafe {
+            ffi::opus_multistream_decode_float(
+                self.0,
+                packet.as_ptr(),
+                packet.len() as i32,
+                pcm.as_mut_ptr(),
+                (pcm.len() / channels) as c_int,
+                0,
+            )
+        };
+        if decoded < 0 {
+            return decode_error("opus: malformed packet");
+        }
+        Ok(decoded as usize)
+    }
+
+    fn reset(&mut self) {
+        unsafe {
+            ffi::opus_multistream_decoder_ctl(self.0, ffi::OPUS_RESET_STATE as c_int);
+        }
+    }
+}
+
+impl Drop for MsDecoder {
+    fn drop(&mut self) {
+        unsafe { ffi::opus_multistream_decoder_destroy(self.0) }
+    }
+}
+
+/// Symphonia decoder for `CODEC_TYPE_OPUS`, backed by libopus.
+pub struct OpusDecoder {
+    params: CodecParameters,
+    decoder: MsDecoder,
+    head: Head,
+    /// Plane of each decoded channel.
+    planes: Vec<usize>,
+    /// Linear output gain from the header.
+    gain: f32,
+    /// Interleaved output of libopus.
+    pcm: Vec<f32>,
+    buf: AudioBuffer<f32>,
+    /// Warm-up frames still to drop.
+    skip: usize,
+}
+
+impl OpusDecoder {
+    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
+        let channels = self.head.channels as usize;
+        let frames = self.decoder.decode(&packet.data, &mut self.pcm, channels)?;
+
+        self.buf.clear();
+        self.buf.render_reserved(Some(frames));
+        for (i, &plane) in self.planes.iter().enumerate() {
+            let out = self.buf.chan_mut(plane);
+            for (sample, frame) in out.iter_mut().zip(self.pcm.chunks_exact(channels)) {
+                *sample = frame[i] * self.gain;
+            }
+        }
+
+        let skip = self.skip.min(frames);
+        self.skip -= skip;
+        self.buf.trim(
+            skip + packet.trim_start() as usize,
+            packet.trim_end() as usize,
+        );
+        Ok(())
+    }
+}
+
+impl Decoder for OpusDecoder {
+    fn try_new(params: &CodecParameters, _options: &DecoderOptions) -> Result<Self> {
+        let head = match &params.extra_data {
+            Some(data) => Head::parse(data)?,
+            None => {
+                let channels = params.channels.map_or(2, |c| c.count()) as u8;
+                Head::stereo(channels, params.delay.unwrap_or(0) as u16, 0)
+            }
+        };
+        let decoder = MsDecoder::new(&head)?;
+        let spec = SignalSpec::new(SAMPLE_RATE, head.layout());
+        Ok(Self {
+            params: params.clone(),
+            decoder,
+            planes: head.planes(),
+            gain: 10f32.powf(head.gain as f32 / 256.0 / 20.0),
+            pcm: vec![0.0; MAX_FRAMES * head.channels as usize],
+            buf: AudioBuffer::new(MAX_FRAMES as u64, spec),
+            skip: head.pre_skip as usize,
+            head,
+        })
+    }
+
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[CodecDescriptor {
+            codec: CODEC_TYPE_OPUS,
+            short_name: "opus",
+            long_name: "Opus",
+            inst_func: |params, options| Ok(Box::new(OpusDecoder::try_new(params, options)?)),
+        }]
+    }
+
+    fn reset(&mut self) {
+        self.decoder.reset();
+        self.skip = self.head.pre_skip as usize;
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        if let Err(err) = self.decode_inner(packet) {
+            self.buf.clear();
+            return Err(err);
+        }
+        Ok(self.buf.as_audio_buffer_ref())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        Default::default()
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(channels: u8, pre_skip: u16, family: u8, table: &[u8]) -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.extend([1, channels]);
+        head.extend(pre_skip.to_le_bytes());
+        head.extend(44_100u32.to_le_bytes());
+        head.extend(0i16.to_le_bytes());
+        head.push(family);
+        head.extend(table);
+        head
+    }
+
+    #[test]
+    fn parses_stereo_and_surround_headers() {
+        let stereo = Head::parse(&head(2, 312, 0, &[])).unwrap();
+        assert_eq!(stereo.pre_skip, 312);
+        assert_eq!((stereo.streams, stereo.coupled), (1, 1));
+        assert_eq!(stereo.planes(), [0, 1]);
+
+        // 5.1: four streams, two of them coupled (L/R and the rears).
+        let surround = Head::parse(&head(6, 312, 1, &[4, 2, 0, 4, 1, 2, 3, 5])).unwrap();
+        assert_eq!(surround.layout().count(), 6);
+        assert_eq!(surround.mapping, [0, 4, 1, 2, 3, 5]);
+        // Vorbis order L C R RL RR LFE into symphonia's L R C LFE RL RR.
+        assert_eq!(surround.planes(), [0, 2, 1, 4, 5, 3]);
+
+        assert!(Head::parse(&head(6, 312, 1, &[4, 2, 0])).is_err());
+        assert!(Head::parse(&head(3, 312, 0, &[])).is_err());
+        assert!(Head::parse(b"OggS").is_err());
+    }
+
+    /// 20 ms packets of a 1 kHz stereo sine, encoded by libopus.
+    fn encode_sine(packets: usize) -> Vec<Vec<u8>> {
+        const FRAME: usize = 960;
+        let mut error = 0;
+        let encoder =
+            unsafe { ffi::opus_encoder_create(48_000, 2, ffi::OPUS_APPLICATION_AUDIO, &mut error) };
+        assert_eq!(error, ffi::OPUS_OK);
+        let encoded = (0..packets)
+            .map(|packet| {
+                let pcm: Vec<f32> = (0..FRAME)
+                    .flat_map(|i| {
+                        let t = (packet * FRAME + i) as f32 / 48_000.0;
+                        let s = 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin();
+                        [s, s]
+                    })
+                    .collect();
+                let mut data = vec![0u8; 4000];
+                let len = unsafe {
+                    ffi::opus_encode_float(
+                        encoder,
+                        pcm.as_ptr(),
+                        FRAME as c_int,
+                        data.as_mut_ptr(),
+                        data.len() as i32,
+                    )
+                };
+                assert!(len > 0);
+                data.truncate(len as usize);
+                data
+            })
+            .collect();
+        unsafe { ffi::opus_encoder_destroy(encoder) };
+        encoded
+    }
+
+    #[test]
+    fn decodes_what_libopus_encoded() {
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_OPUS)
+            .with_extra_data(head(2, 312, 0, &[]).into_boxed_slice());
+        let mut decoder = codecs().make(&params, &DecoderOptions::default()).unwrap();
+
+        let mut frames = 0;
+        let mut tail = Vec::new();
+        for (i, data) in encode_sine(50).iter().enumerate() {
+            let packet = Packet::new_from_slice(0, 0, 960, data);
+            let decoded = decoder.decode(&packet).unwrap();
+            let spec = *decoded.spec();
+            assert_eq!(spec.rate, 48_000);
+            assert_eq!(spec.channels.count(), 2);
+            frames += decoded.frames();
+            if i == 49 {
+                let mut samples = symphonia::core::audio::SampleBuffer::<f32>::new(
+                    decoded.capacity() as u64,
+                    spec,
+                );
+                samples.copy_interleaved_ref(decoded);
+                tail = samples.samples().to_vec();
+            }
+        }
+
+        // The warm-up is dropped, and a steady tone comes out.
+        assert_eq!(frames, 50 * 960 - 312);
+        let rms = (tail.iter().map(|s| s * s).sum::<f32>() / tail.len() as f32).sqrt();
+        assert!((rms - 0.3536).abs() < 0.02, "rms {rms}");
+    }
+}
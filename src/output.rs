@@ -0,0 +1,401 @@
+//! Where the player's audio goes, selected with `--output`.
+//!
+//! `device` (the default) plays on a sound card through rodio. The headless
+//! outputs need no audio hardware: they mix every sink into one
+//! 16-bit stereo 44.1 kHz stream and write it, paced in real time, as a WAV
+//! file (`wav:<path>`), raw s16le on stdout (`stdout`) or raw s16le into a
+//! named pipe (`fifo:<path>`) for tools such as snapserver or ffmpeg.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Error};
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use rodio::Sink;
+
+use crate::device::{self, DeviceStream};
+use crate::dither::Dither;
+
+/// Format written by the headless outputs.
+pub const PCM_CHANNELS: u16 = 2;
+pub const PCM_SAMPLE_RATE: u32 = 44100;
+
+/// Frames written per chunk.
+const CHUNK_FRAMES: usize = 1024;
+
+/// How far the writer may run ahead of the wall clock. A little slack keeps
+/// the reader fed without the mixer racing through silence while idle.
+const LEAD: Duration = Duration::from_millis(200);
+
+/// How often the WAV header is rewritten with the current length, so the
+/// file stays playable even when the process is killed.
+const WAV_HEADER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An `--output` value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputSpec {
+    /// A sound card, by name (`None` = system default).
+    Device(Option<String>),
+    Wav(PathBuf),
+    Stdout,
+    Fifo(PathBuf),
+}
+
+impl OutputSpec {
+    /// Whether this output writes to the terminal, which a TUI also needs.
+    pub fn is_stdout(&self) -> bool {
+        matches!(self, OutputSpec::Stdout)
+    }
+}
+
+impl FromStr for OutputSpec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = |path: &str| {
+            if path.is_empty() {
+                Err(anyhow!("missing path in output '{}'", s))
+            } else {
+                Ok(PathBuf::from(path))
+            }
+        };
+        match s.split_once(':') {
+            None if s == "device" => Ok(OutputSpec::Device(None)),
+            None if s == "stdout" => Ok(OutputSpec::Stdout),
+            Some(("device", name)) => Ok(OutputSpec::Device(
+                Some(name.to_string()).filter(|name| !name.is_empty()),
+            )),
+            Some(("wav", p)) => Ok(OutputSpec::Wav(path(p)?)),
+            Some(("fifo", p)) => Ok(OutputSpec::Fifo(path(p)?)),
+            _ => Err(anyhow!(
+                "unsupported output '{}', expected device, wav:<path>, stdout or fifo:<path>",
+                s
+            )),
+        }
+    }
+}
+
+/// An open output. Like rodio's `OutputStream`, it must stay alive for as
+/// long as its sinks play, and it can't leave the thread that opened it.
+pub struct Output {
+    kind: Kind,
+}
+
+enum Kind {
+    Device {
+        stream: DeviceStream,
+        sample_rate: u32,
+        channels: u16,
+    },
+    Pcm {
+        mixer: Arc<DynamicMixerController<f32>>,
+        stop: Arc<AtomicBool>,
+    },
+}
+
+impl Output {
+    pub fn open(spec: &OutputSpec) -> Result<Self, Error> {
+        let target = match spec {
+            OutputSpec::Device(name) => {
+                let device = device::open(name.as_deref())?;
+                return Ok(Self {
+                    kind: Kind::Device {
+                        stream: device.stream,
+                        sample_rate: device.sample_rate,
+                        channels: device.channels,
+                    },
+                });
+            }
+            OutputSpec::Wav(path) => Target::Wav(WavWriter::create(path)?),
+            OutputSpec::Stdout => Target::Raw(Box::new(io::stdout())),
+            OutputSpec::Fifo(path) => {
+                if !path.exists() {
+                    return Err(anyhow!(
+                        "named pipe {} does not exist; create it with `mkfifo {}`",
+                        path.display(),
+                        path.display()
+                    ));
+                }
+                Target::Fifo(path.clone(), None)
+            }
+        };
+
+        let (mixer, source) = dynamic_mixer::mixer::<f32>(PCM_CHANNELS, PCM_SAMPLE_RATE);
+        let stop = Arc::new(AtomicBool::new(false));
+        thread::Builder::new()
+            .name("tunein-pcm-writer".into())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    if let Err(err) = write_pcm(source, target, &stop) {
+                        eprintln!("audio output stopped: {:?}", err);
+                    }
+                }
+            })
+            .context("failed to spawn PCM writer thread")?;
+
+        Ok(Self {
+            kind: Kind::Pcm { mixer, stop },
+        })
+    }
+
+    /// The rate this output plays at; anything else is converted by rodio.
+    pub fn sample_rate(&self) -> u32 {
+        match &self.kind {
+            Kind::Device { sample_rate, .. } => *sample_rate,
+            Kind::Pcm { .. } => PCM_SAMPLE_RATE,
+        }
+    }
+
+    /// How many channels this output plays; headless outputs are stereo.
+    pub fn channels(&self) -> u16 {
+        match &self.kind {
+            Kind::Device { channels, .. } => *channels,
+            Kind::Pcm { .. } => PCM_CHANNELS,
+        }
+    }
+
+    /// Create a sink playing on this output.
+    pub fn new_sink(&self) -> Result<Sink, Error> {
+        match &self.kind {
+            Kind::Device {
+                stream: DeviceStream::Rodio { handle, .. },
+                ..
+            } => Sink::try_new(handle).map_err(|e| anyhow!("failed to create audio sink: {}", e)),
+            Kind::Device {
+                stream: DeviceStream::Dithered { mixer, .. },
+                ..
+            }
+            | Kind::Pcm { mixer, .. } => {
+                let (sink, queue) = Sink::new_idle();
+                mixer.add(queue);
+                Ok(sink)
+            }
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if let Kind::Pcm { stop, .. } = &self.kind {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+enum Target {
+    Wav(WavWriter),
+    Raw(Box<dyn Write + Send>),
+    /// Opened by the writer thread, since opening blocks until a reader
+    /// connects; re-opened when the reader goes away.
+    Fifo(PathBuf, Option<File>),
+}
+
+impl Target {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        match self {
+            Target::Wav(wav) => wav.write(bytes),
+            Target::Raw(out) => Ok(out.write_all(bytes)?),
+            Target::Fifo(path, file) => {
+                if file.is_none() {
+                    *file = Some(
+                        File::options()
+                            .write(true)
+                            .open(&*path)
+                            .with_context(|| format!("failed to open {}", path.display()))?,
+                    );
+                }
+                let pipe = file.as_mut().expect("pipe opened above");
+                if let Err(err) = pipe.write_all(bytes) {
+                    if err.kind() != io::ErrorKind::BrokenPipe {
+                        return Err(err.into());
+                    }
+                    // The reader left; wait for the next one.
+                    *file = None;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        match self {
+            Target::Wav(wav) => wav.finalize(),
+            Target::Raw(out) => Ok(out.flush()?),
+            Target::Fifo(..) => Ok(()),
+        }
+    }
+}
+
+/// Pull the mix in real time and write it to `target` until `stop` is set.
+fn write_pcm(
+    mut source: DynamicMixer<f32>,
+    mut target: Target,
+    stop: &AtomicBool,
+) -> Result<(), Error> {
+    let samples = CHUNK_FRAMES * PCM_CHANNELS as usize;
+    let chunk = Duration::from_secs_f64(CHUNK_FRAMES as f64 / PCM_SAMPLE_RATE as f64);
+    let mut bytes = Vec::with_capacity(samples * 2);
+    let mut dither = Dither::new();
+    // Wall-clock time at which the audio written so far ends.
+    let mut clock = Instant::now();
+    let mut last_flush = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        bytes.clear();
+        for _ in 0..samples {
+            // An empty mixer yields nothing; play silence like a sound card.
+            let sample = source.next().unwrap_or(0.0);
+            bytes.extend_from_slice(&dither.quantize(sample).to_le_bytes());
+        }
+        target.write(&bytes)?;
+
+        if last_flush.elapsed() >= WAV_HEADER_INTERVAL {
+            target.flush()?;
+            last_flush = Instant::now();
+        }
+
+        // After a stall (e.g. waiting for a pipe reader) carry on from now
+        // instead of racing to catch up.
+        let now = Instant::now();
+        clock = clock.max(now) + chunk;
+        if let Some(ahead) = clock.checked_duration_since(now + LEAD) {
+            thread::sleep(ahead);
+        }
+    }
+
+    target.flush()
+}
+
+/// A 16-bit PCM WAV file whose header is patched with the data length on
+/// every [`WavWriter::finalize`].
+struct WavWriter {
+    file: BufWriter<File>,
+    data_len: u32,
+}
+
+impl WavWriter {
+    fn create(path: &Path) -> Result<Self, Error> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let mut wav = Self {
+            file: BufWriter::new(file),
+            data_len: 0,
+        };
+        wav.file.write_all(&wav_header(0))?;
+        Ok(wav)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.file.write_all(bytes)?;
+        // WAV sizes are 32-bit: past ~6.7 hours the header stops growing.
+        self.data_len = self.data_len.saturating_add(bytes.len() as u32);
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<(), Error> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&wav_header(self.data_len))?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// The 44-byte RIFF header for `data_len` bytes of PCM in the output format.
+pub fn wav_header(data_len: u32) -> [u8; 44] {
+    let block_align = PCM_CHANNELS * 2;
+    let byte_rate = PCM_SAMPLE_RATE * block_align as u32;
+
+    let mut header = [0u8; 44];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&data_len.saturating_add(36).to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes()); // PCM
+    header[22..24].copy_from_slice(&PCM_CHANNELS.to_le_bytes());
+    header[24..28].copy_from_slice(&PCM_SAMPLE_RATE.to_le_bytes());
+    header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    header[32..34].copy_from_slice(&block_align.to_le_bytes());
+    header[34..36].copy_from_slice(&16u16.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    #[test]
+    fn parses_output_specs() {
+        assert_eq!(
+            "device".parse::<OutputSpec>().unwrap(),
+            OutputSpec::Device(None)
+        );
+        assert_eq!(
+            "device:USB DAC".parse::<OutputSpec>().unwrap(),
+            OutputSpec::Device(Some("USB DAC".into()))
+        );
+        assert_eq!(
+            "wav:/tmp/out.wav".parse::<OutputSpec>().unwrap(),
+            OutputSpec::Wav("/tmp/out.wav".into())
+        );
+        assert_eq!("stdout".parse::<OutputSpec>().unwrap(), OutputSpec::Stdout);
+        assert_eq!(
+            "fifo:/tmp/snapfifo".parse::<OutputSpec>().unwrap(),
+            OutputSpec::Fifo("/tmp/snapfifo".into())
+        );
+        assert!("wav:".parse::<OutputSpec>().is_err());
+        assert!("speakers".parse::<OutputSpec>().is_err());
+    }
+
+    #[test]
+    fn missing_fifo_is_an_error() {
+        let path = std::env::temp_dir().join("tunein-output-test-missing-fifo");
+        assert!(Output::open(&OutputSpec::Fifo(path)).is_err());
+    }
+
+    /// Play a tone through a sink on a WAV output, with no audio hardware.
+    #[test]
+    fn wav_output_records_sink_playback() {
+        let path =
+            std::env::temp_dir().join(format!("tunein-output-test-{}.wav", std::process::id()));
+        let output = Output::open(&OutputSpec::Wav(path.clone())).unwrap();
+        let sink = output.new_sink().unwrap();
+        let tone: Vec<f32> = (0..PCM_SAMPLE_RATE / 10)
+            .flat_map(|i| {
+                let s = (i as f32 * 440.0 * std::f32::consts::TAU / PCM_SAMPLE_RATE as f32).sin();
+                [s * 0.5, s * 0.5]
+            })
+            .collect();
+        sink.append(SamplesBuffer::new(PCM_CHANNELS, PCM_SAMPLE_RATE, tone));
+        sink.sleep_until_end();
+        // Give the writer time to pass the tone before stopping it.
+        thread::sleep(LEAD + Duration::from_millis(100));
+        drop(sink);
+        drop(output);
+        thread::sleep(Duration::from_millis(100));
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        let data_len = u32::from_le_bytes(bytes[40..44].try_into().unwrap()) as usize;
+        assert_eq!(data_len, bytes.len() - 44);
+        let samples: Vec<i16> = bytes[44..]
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap();
+        assert!(peak > 14_000 && peak < 17_000, "peak {}", peak);
+    }
+}
@@ -1,152 +1,742 @@
+//! The player core shared by every front end: the `play` TUI, the
+//! interactive hub and the gRPC server all drive one [`Player`] through
+//! [`PlayerCommand`]s and listen to its [`PlayerEvent`]s.
+//!
+//! The player owns the output and a crossfading [`Mixer`] for its whole
+//! lifetime. Stations are connected (redirects, ICY metadata, decoding) and
+//! pre-buffered on a background thread while the current one keeps playing,
+//! so volume, pause and metadata behave the same wherever they are driven
+//! from. Every stream is read through a [`ReadAhead`] buffer, so network
+//! hiccups shorter than the buffer go unheard.
+//!
+//! While a station plays the player also tracks its [`PlaybackStats`],
+//! readable at any time through [`Player::stats`].
+
 use std::{
-    pin::Pin,
-    sync::{Arc, Mutex},
-    task::{Context, Poll},
+    fs::File,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use anyhow::Error;
-use futures_util::Future;
-use rodio::{OutputStream, OutputStreamHandle, Sink};
-use tokio::sync::mpsc;
-
-use crate::decoder::StreamDecoder;
+use anyhow::{Context as _, Error};
+use hyper::header::HeaderValue;
+use rodio::{Sink, Source};
+use tokio::sync::mpsc as async_mpsc;
 
-pub struct Player;
+use crate::decoder::{Frame, StreamDecoder};
+use crate::downmix::ChannelPolicy;
+use crate::equalizer::Equalizer;
+use crate::mixer::{Mixer, MixerHandle, Prebuffered, PREBUFFER};
+use crate::output::{Output, OutputSpec};
+use crate::readahead::{BufferOptions, ReadAhead};
+use crate::resample::Resampler;
+use crate::station_memory::StationMemory;
+use crate::stats::{CountingReader, PlaybackPhase, PlaybackStats, StreamCounters};
+use crate::types::Station;
 
-impl Player {
-    pub fn new(cmd_rx: Arc<Mutex<mpsc::UnboundedReceiver<PlayerCommand>>>) -> Self {
-        thread::spawn(move || {
-            let internal = PlayerInternal::new(cmd_rx);
-            futures::executor::block_on(internal);
-        });
-        Self {}
-    }
-}
+/// How often the [`PlaybackStats`] of a playing station are updated.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug)]
 pub enum PlayerCommand {
-    Play(String),
+    /// Connect to a station and crossfade into it. Unpauses. The station's
+    /// remembered volume and EQ preset, looked up by `provider` and id, are
+    /// restored.
+    Play {
+        station: Station,
+        provider: String,
+    },
+    /// Ring an alarm: play `url`, ramping the volume up from 0, and fall
+    /// back to looping a local sound file when the stream fails to start.
+    PlayAlarm(AlarmPlayback),
+    Pause,
+    Resume,
     PlayOrPause,
+    /// Volume as a ratio, `1.0` = 100%. Remembered for the current station
+    /// unless it is 0 (muted).
+    SetVolume(f32),
+    /// Move playback to another output, restarting the current station.
+    SetOutput(OutputSpec),
     Stop,
 }
 
-struct PlayerInternal {
-    sink: Arc<Mutex<Sink>>,
-    stream: OutputStream,
-    handle: OutputStreamHandle,
-    commands: Arc<Mutex<mpsc::UnboundedReceiver<PlayerCommand>>>,
+#[derive(Debug)]
+pub struct AlarmPlayback {
+    /// Resolved stream URL, `None` when the station could not be resolved.
+    pub url: Option<String>,
+    /// Target volume in percent.
+    pub volume: f32,
+    pub ramp: Duration,
+    pub fallback: Option<String>,
 }
 
-impl PlayerInternal {
-    fn new(cmd_rx: Arc<Mutex<mpsc::UnboundedReceiver<PlayerCommand>>>) -> Self {
-        let (stream, handle) = rodio::OutputStream::try_default().unwrap();
-        Self {
-            sink: Arc::new(Mutex::new(rodio::Sink::try_new(&handle).unwrap())),
-            stream,
-            handle,
-            commands: cmd_rx,
-        }
-    }
+/// Events emitted by the player.
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    /// Connecting to a station, following redirects.
+    Connecting(Station),
+    /// A station connected and is fading in.
+    Started(PlaybackState),
+    /// The output ran dry waiting for the stream; `total` counts the
+    /// current station's underruns so far.
+    Underrun {
+        total: u64,
+    },
+    /// A station failed to connect; whatever was playing carries on.
+    LoadFailed {
+        station: Station,
+        error: String,
+    },
+    Error(String),
+    Stopped,
+}
 
-    fn handle_play(&mut self, url: String) -> Result<(), Error> {
-        let (stream, handle) = rodio::OutputStream::try_default().unwrap();
-        self.stream = stream;
-        self.sink = Arc::new(Mutex::new(rodio::Sink::try_new(&handle).unwrap()));
-        self.handle = handle;
-        let sink = self.sink.clone();
+/// Snapshot of the current playback metadata.
+#[derive(Debug, Clone)]
+pub struct PlaybackState {
+    pub station: Station,
+    /// `icy-name`, or the station's own name when the stream has none.
+    pub stream_name: String,
+    pub now_playing: String,
+    pub genre: String,
+    pub description: String,
+    pub bitrate: String,
+    /// Decoded channel layout, e.g. `stereo` or `5.1 → stereo`.
+    pub channel_layout: String,
+    /// Volume ratio remembered for the station, which it started at.
+    /// `None` keeps the volume of whatever played before.
+    pub volume: Option<f32>,
+}
 
-        thread::spawn(move || {
-            let client = reqwest::blocking::Client::new();
+pub struct PlayerOptions {
+    pub output: OutputSpec,
+    /// Crossfade length when switching stations.
+    pub crossfade: Duration,
+    /// Read-ahead buffer between the network and the decoder.
+    pub buffer: BufferOptions,
+    /// Resample to the output's rate with [`Resampler`] rather than
+    /// rodio's linear interpolation.
+    pub resample: bool,
+    /// Initial volume ratio.
+    pub volume: f32,
+    /// Receives every mixed chunk, for a visualizer.
+    pub frames: Option<Sender<Frame>>,
+}
 
-            let response = client.get(url.clone()).send().unwrap();
+/// Cheap, cloneable handle to the player thread. The thread, and with it
+/// the output, goes away once every handle is dropped.
+#[derive(Clone)]
+pub struct Player {
+    commands: Sender<PlayerCommand>,
+    stats: Arc<Mutex<StatsTracker>>,
+    alarm_ringing: Arc<AtomicBool>,
+}
+
+/// Receiving end of the player's events.
+pub struct PlayerEvents {
+    rx: async_mpsc::UnboundedReceiver<PlayerEvent>,
+}
 
-            println!("headers: {:#?}", response.headers());
-            let location = response.headers().get("location");
+impl PlayerEvents {
+    pub async fn recv(&mut self) -> Option<PlayerEvent> {
+        self.rx.recv().await
+    }
+}
+
+impl Player {
+    /// Start the player thread. Fails when the output can't be opened.
+    pub fn new(options: PlayerOptions) -> Result<(Self, PlayerEvents), Error> {
+        let (cmd_tx, cmd_rx) = mpsc::channel::<PlayerCommand>();
+        let (event_tx, event_rx) = async_mpsc::unbounded_channel::<PlayerEvent>();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<(), Error>>();
+        let stats = Arc::new(Mutex::new(StatsTracker::default()));
+        let core_stats = stats.clone();
+        let alarm_ringing = Arc::new(AtomicBool::new(false));
+        let core_alarm_ringing = alarm_ringing.clone();
 
-            let response = match location {
-                Some(location) => {
-                    let response = client.get(location.to_str().unwrap()).send().unwrap();
-                    let location = response.headers().get("location");
-                    match location {
-                        Some(location) => client.get(location.to_str().unwrap()).send().unwrap(),
-                        None => response,
+        thread::Builder::new()
+            .name("tunein-player".into())
+            .spawn(move || {
+                // The output can't leave this thread, so report whether it
+                // opened back to the caller.
+                let mut core =
+                    match PlayerCore::new(options, event_tx, core_stats, core_alarm_ringing) {
+                        Ok(core) => {
+                            let _ = ready_tx.send(Ok(()));
+                            core
+                        }
+                        Err(err) => {
+                            let _ = ready_tx.send(Err(err));
+                            return;
+                        }
+                    };
+                let mut next_tick = Instant::now() + STATS_INTERVAL;
+                loop {
+                    let timeout = next_tick.saturating_duration_since(Instant::now());
+                    match cmd_rx.recv_timeout(timeout) {
+                        Ok(cmd) => {
+                            if let Err(err) = core.handle_command(cmd) {
+                                core.emit(PlayerEvent::Error(err.to_string()));
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    if Instant::now() >= next_tick {
+                        core.tick();
+                        next_tick = Instant::now() + STATS_INTERVAL;
                     }
                 }
-                None => response,
-            };
-            let content_type = response
-                .headers()
-                .get("content-type")
-                .and_then(|v| v.to_str().ok())
-                .map(String::from);
-            let decoder = StreamDecoder::new(response, content_type.as_deref(), None).unwrap();
-
-            {
-                let sink = sink.lock().unwrap();
-                sink.append(decoder);
-                sink.play();
-            }
+            })
+            .context("failed to spawn player thread")?;
 
-            loop {
-                let sink = sink.lock().unwrap();
+        ready_rx
+            .recv()
+            .context("player thread exited before opening the output")??;
 
-                if sink.empty() {
-                    break;
-                }
+        Ok((
+            Self {
+                commands: cmd_tx,
+                stats,
+                alarm_ringing,
+            },
+            PlayerEvents { rx: event_rx },
+        ))
+    }
+
+    pub fn send(&self, cmd: PlayerCommand) -> Result<(), Error> {
+        self.commands
+            .send(cmd)
+            .map_err(|_| Error::msg("player thread has stopped"))
+    }
 
-                drop(sink);
+    /// The current station's statistics, refreshed once a second.
+    pub fn stats(&self) -> PlaybackStats {
+        self.stats.lock().unwrap().stats.clone()
+    }
+
+    /// The station on air, if any.
+    pub fn station(&self) -> Option<Station> {
+        self.stats.lock().unwrap().station.clone()
+    }
+
+    /// Whether an alarm is on air, i.e. nothing was played or stopped
+    /// since the last [`PlayerCommand::PlayAlarm`].
+    pub fn alarm_ringing(&self) -> bool {
+        self.alarm_ringing.load(Ordering::SeqCst)
+    }
+}
+
+/// [`PlaybackStats`] plus what is needed to refresh them.
+#[derive(Default)]
+struct StatsTracker {
+    stats: PlaybackStats,
+    station: Option<Station>,
+    /// Counters of the stream on air, `None` until one has started.
+    counters: Option<Arc<StreamCounters>>,
+    /// Bytes received at the previous tick, and when, for the throughput.
+    last_bytes: u64,
+    last_tick: Option<Instant>,
+    /// The stream on air ran dry and is refilling its read-ahead buffer.
+    refilling: bool,
+}
 
-                std::thread::sleep(Duration::from_millis(10));
+impl StatsTracker {
+    /// Start tracking `station` from scratch.
+    fn reset(&mut self, phase: PlaybackPhase, station: Option<&Station>) {
+        *self = Self {
+            stats: PlaybackStats {
+                phase,
+                station: station.map(|station| station.name.clone()),
+                ..Default::default()
+            },
+            station: station.cloned(),
+            ..Default::default()
+        };
+    }
+}
+
+struct PlayerCore {
+    /// Plays the [`Mixer`] for the player's whole lifetime; stations are
+    /// crossfaded inside it.
+    sink: Arc<Sink>,
+    mixer: MixerHandle,
+    frames: Option<Sender<Frame>>,
+    crossfade: Duration,
+    buffer: BufferOptions,
+    resample: bool,
+    /// Bumped on every play, so a slow connection can't take over from a
+    /// station requested after it.
+    generation: Arc<AtomicU64>,
+    /// The station on air and its provider, replayed when the output
+    /// changes. Set by the connect thread once a new station takes over, so
+    /// a failed load leaves the previous one in place.
+    station: Arc<Mutex<Option<(Station, String)>>>,
+    /// Hands remembered volumes to the thread that writes them.
+    volumes: Sender<(String, String, f32)>,
+    /// A ringing alarm gets its own sink so it can ramp up from silence.
+    alarm_sink: Option<Arc<Mutex<Sink>>>,
+    /// Tells a looping alarm fallback to stop re-queueing its sound file.
+    alarm_stopped: Option<Arc<AtomicBool>>,
+    /// Shared with [`Player::alarm_ringing`].
+    alarm_ringing: Arc<AtomicBool>,
+    output: Output,
+    events: async_mpsc::UnboundedSender<PlayerEvent>,
+    stats: Arc<Mutex<StatsTracker>>,
+}
+
+impl PlayerCore {
+    fn new(
+        options: PlayerOptions,
+        events: async_mpsc::UnboundedSender<PlayerEvent>,
+        stats: Arc<Mutex<StatsTracker>>,
+        alarm_ringing: Arc<AtomicBool>,
+    ) -> Result<Self, Error> {
+        let output = Output::open(&options.output)?;
+        let sink = output.new_sink()?;
+        ChannelPolicy::global().set_output_channels(output.channels());
+        sink.set_volume(options.volume.max(0.0));
+        let (mixer, mixer_handle) = Mixer::new(options.frames.clone());
+        play_mixer(&sink, mixer, &output, options.resample);
+        Ok(Self {
+            sink: Arc::new(sink),
+            mixer: mixer_handle,
+            frames: options.frames,
+            crossfade: options.crossfade,
+            buffer: options.buffer,
+            resample: options.resample,
+            generation: Arc::new(AtomicU64::new(0)),
+            station: Arc::new(Mutex::new(None)),
+            volumes: spawn_volume_memory(events.clone()),
+            alarm_sink: None,
+            alarm_stopped: None,
+            alarm_ringing,
+            output,
+            events,
+            stats,
+        })
+    }
+
+    fn emit(&self, event: PlayerEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Refresh the stats from the stream's counters, marking read-ahead
+    /// refills in the phase, and report any new underruns.
+    fn tick(&self) {
+        let mut tracker = self.stats.lock().unwrap();
+        let Some(counters) = tracker.counters.clone() else {
+            return;
+        };
+        let bytes = counters.bytes_received();
+        let (sample_rate, layout) = counters.format();
+        let underruns = self.mixer.underruns();
+        let new_underruns = underruns > tracker.stats.underruns;
+        let elapsed = tracker
+            .last_tick
+            .map_or(STATS_INTERVAL, |last| last.elapsed())
+            .as_secs_f64();
+        let throughput = (bytes - tracker.last_bytes) as f64 * 8.0 / 1000.0 / elapsed;
+
+        tracker.last_bytes = bytes;
+        tracker.last_tick = Some(Instant::now());
+        tracker.stats.bytes_received = bytes;
+        tracker.stats.throughput_kbps = throughput.round() as u32;
+        tracker.stats.sample_rate = sample_rate;
+        tracker.stats.layout = layout;
+        tracker.stats.decode_errors = counters.decode_errors();
+        tracker.stats.underruns = underruns;
+        tracker.stats.buffer_fill = counters.buffer_fill();
+        // Only a playing stream is marked as refilling: while paused or
+        // switching stations the phase belongs to the user or the new
+        // connection.
+        let refill = match (tracker.stats.phase, counters.refilling()) {
+            (PlaybackPhase::Playing, true) => Some(counters.refill_progress()),
+            (PlaybackPhase::Buffering(_), true) if tracker.refilling => {
+                Some(counters.refill_progress())
             }
-        });
+            (PlaybackPhase::Buffering(_), false) if tracker.refilling => {
+                tracker.stats.phase = PlaybackPhase::Playing;
+                None
+            }
+            _ => None,
+        };
+        tracker.refilling = refill.is_some();
+        if let Some(percent) = refill {
+            tracker.stats.phase = PlaybackPhase::Buffering(percent);
+        }
+        drop(tracker);
+
+        if new_underruns {
+            self.emit(PlayerEvent::Underrun { total: underruns });
+        }
+    }
+
+    fn set_phase(&self, phase: PlaybackPhase) {
+        let mut tracker = self.stats.lock().unwrap();
+        if tracker.counters.is_some() {
+            tracker.stats.phase = phase;
+        }
+    }
 
+    fn handle_command(&mut self, cmd: PlayerCommand) -> Result<(), Error> {
+        match cmd {
+            PlayerCommand::Play { station, provider } => self.handle_play(station, provider),
+            PlayerCommand::PlayAlarm(alarm) => self.handle_play_alarm(alarm)?,
+            PlayerCommand::Pause => {
+                self.sink.pause();
+                self.set_phase(PlaybackPhase::Paused);
+            }
+            PlayerCommand::Resume => {
+                self.sink.play();
+                self.set_phase(PlaybackPhase::Playing);
+            }
+            PlayerCommand::PlayOrPause => match self.sink.is_paused() {
+                true => {
+                    self.sink.play();
+                    self.set_phase(PlaybackPhase::Playing);
+                }
+                false => {
+                    self.sink.pause();
+                    self.set_phase(PlaybackPhase::Paused);
+                }
+            },
+            PlayerCommand::SetVolume(volume) => {
+                let volume = volume.max(0.0);
+                self.sink.set_volume(volume);
+                if let Some((station, provider)) = self.station.lock().unwrap().as_ref() {
+                    if volume > 0.0 {
+                        let _ = self
+                            .volumes
+                            .send((provider.clone(), station.id.clone(), volume));
+                    }
+                }
+            }
+            PlayerCommand::SetOutput(output) => self.handle_set_output(&output)?,
+            PlayerCommand::Stop => {
+                self.stop_alarm();
+                self.generation.fetch_add(1, Ordering::SeqCst);
+                *self.station.lock().unwrap() = None;
+                self.mixer.stop();
+                self.stats.lock().unwrap().reset(PlaybackPhase::Idle, None);
+                self.emit(PlayerEvent::Stopped);
+            }
+        }
         Ok(())
     }
 
-    fn handle_play_or_pause(&self) -> Result<(), Error> {
-        let sink = self.sink.lock().unwrap();
-        match sink.is_paused() {
-            true => sink.play(),
-            false => sink.pause(),
-        };
+    /// Connect to `station` and pre-buffer it while the current station
+    /// keeps playing, then crossfade into it. Its remembered volume and EQ
+    /// preset apply once it takes over.
+    fn handle_play(&mut self, station: Station, provider: String) {
+        self.stop_alarm();
+        self.sink.play();
+        let volume = StationMemory::load()
+            .ok()
+            .and_then(|memory| memory.get(&provider, &station.id)?.volume);
+        let load = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let generation = self.generation.clone();
+        let sink = self.sink.clone();
+        let mixer = self.mixer.clone();
+        let crossfade = self.crossfade;
+        let buffer = self.buffer;
+        let events = self.events.clone();
+        let stats = self.stats.clone();
+        let on_air = self.station.clone();
+        // The stats keep describing the station on air until the new one
+        // takes over; only the phase follows the connection.
+        {
+            let mut tracker = stats.lock().unwrap();
+            tracker.stats.phase = PlaybackPhase::Connecting;
+            tracker.refilling = false;
+        }
+        self.emit(PlayerEvent::Connecting(station.clone()));
+
+        thread::spawn(move || {
+            let current = || generation.load(Ordering::SeqCst) == load;
+            let counters = Arc::new(StreamCounters::default());
+            let buffering = |percent| {
+                if current() {
+                    stats.lock().unwrap().stats.phase = PlaybackPhase::Buffering(percent);
+                }
+            };
+            let event = match connect(&station, &provider, buffer, &counters, buffering) {
+                Ok((mut state, codec, decoder)) => {
+                    if !current() {
+                        return;
+                    }
+                    mixer.crossfade_to(Box::new(decoder), crossfade);
+                    if let Some(volume) = volume {
+                        sink.set_volume(volume);
+                    }
+                    Equalizer::global().use_station(&provider, &station.id);
+                    *on_air.lock().unwrap() = Some((station.clone(), provider.clone()));
+                    let mut tracker = stats.lock().unwrap();
+                    tracker.reset(PlaybackPhase::Playing, Some(&station));
+                    tracker.stats.codec = codec;
+                    tracker.counters = Some(counters);
+                    state.volume = volume;
+                    PlayerEvent::Started(state)
+                }
+                Err(err) => {
+                    if current() {
+                        let mut tracker = stats.lock().unwrap();
+                        tracker.stats.phase = match tracker.counters {
+                            Some(_) => PlaybackPhase::Playing,
+                            None => PlaybackPhase::Idle,
+                        };
+                    }
+                    PlayerEvent::LoadFailed {
+                        station,
+                        error: format!("{:#}", err),
+                    }
+                }
+            };
+            let _ = events.send(event);
+        });
+    }
+
+    /// Open `output` with a fresh sink and mixer, then restart the current
+    /// station on it. The old output keeps playing when the new one fails.
+    fn handle_set_output(&mut self, output: &OutputSpec) -> Result<(), Error> {
+        let output = Output::open(output)?;
+        let sink = output.new_sink()?;
+        ChannelPolicy::global().set_output_channels(output.channels());
+        let (mixer, mixer_handle) = Mixer::new(self.frames.clone());
+        sink.set_volume(self.sink.volume());
+        play_mixer(&sink, mixer, &output, self.resample);
+
+        self.stop_alarm();
+        self.mixer.stop();
+        self.sink.stop();
+        self.sink = Arc::new(sink);
+        self.mixer = mixer_handle;
+        self.output = output;
+
+        let station = self.station.lock().unwrap().clone();
+        if let Some((station, provider)) = station {
+            self.handle_play(station, provider);
+        }
         Ok(())
     }
 
-    fn handle_stop(&self) -> Result<(), Error> {
-        let sink = self.sink.lock().unwrap();
-        sink.stop();
+    fn handle_play_alarm(&mut self, alarm: AlarmPlayback) -> Result<(), Error> {
+        self.stop_alarm();
+        *self.station.lock().unwrap() = None;
+        self.mixer.stop();
+        self.stats.lock().unwrap().reset(PlaybackPhase::Idle, None);
+        let sink = Arc::new(Mutex::new(self.output.new_sink()?));
+        self.alarm_sink = Some(sink.clone());
+        let stopped = Arc::new(AtomicBool::new(false));
+        self.alarm_stopped = Some(stopped.clone());
+        self.alarm_ringing.store(true, Ordering::SeqCst);
+        let events = self.events.clone();
+        let buffer = self.buffer;
+
+        thread::spawn(move || {
+            sink.lock()
+                .unwrap()
+                .set_volume(alarm.volume.max(0.0) / 100.0);
+
+            let stream = alarm
+                .url
+                .ok_or_else(|| Error::msg("alarm station could not be resolved"))
+                .and_then(|url| {
+                    open_stream(&url, buffer, Arc::default(), |_| {}).map(|(_, decoder)| decoder)
+                });
+            match stream {
+                Ok(decoder) => {
+                    let sink = sink.lock().unwrap();
+                    if alarm.ramp.is_zero() {
+                        sink.append(decoder);
+                    } else {
+                        sink.append(decoder.fade_in(alarm.ramp));
+                    }
+                    sink.play();
+                }
+                Err(err) => {
+                    let _ = events.send(PlayerEvent::Error(format!(
+                        "alarm stream failed: {:#}",
+                        err
+                    )));
+                    let Some(path) = alarm.fallback else {
+                        return;
+                    };
+                    // Loop the sound file until the alarm is stopped or
+                    // snoozed; only the first pass ramps up.
+                    let mut first = true;
+                    while !stopped.load(Ordering::Relaxed) {
+                        if sink.lock().unwrap().empty() {
+                            match open_file(&path) {
+                                Ok(decoder) => {
+                                    let sink = sink.lock().unwrap();
+                                    if first && !alarm.ramp.is_zero() {
+                                        sink.append(decoder.fade_in(alarm.ramp));
+                                    } else {
+                                        sink.append(decoder);
+                                    }
+                                    sink.play();
+                                    first = false;
+                                }
+                                Err(err) => {
+                                    let _ = events.send(PlayerEvent::Error(format!(
+                                        "alarm fallback failed: {:#}",
+                                        err
+                                    )));
+                                    return;
+                                }
+                            }
+                        }
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                }
+            }
+        });
+
         Ok(())
     }
 
-    pub fn handle_command(&mut self, cmd: PlayerCommand) -> Result<(), Error> {
-        match cmd {
-            PlayerCommand::Play(url) => self.handle_play(url),
-            PlayerCommand::PlayOrPause => self.handle_play_or_pause(),
-            PlayerCommand::Stop => self.handle_stop(),
+    /// Silence a ringing alarm, if any, and stop re-queueing its fallback
+    /// sound.
+    fn stop_alarm(&mut self) {
+        self.alarm_ringing.store(false, Ordering::SeqCst);
+        if let Some(stopped) = self.alarm_stopped.take() {
+            stopped.store(true, Ordering::Relaxed);
+        }
+        if let Some(sink) = self.alarm_sink.take() {
+            sink.lock().unwrap().stop();
         }
     }
 }
 
-impl Future for PlayerInternal {
-    type Output = ();
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {
-            // Process commands that have been sent to the player
-            let cmd = match self.commands.lock().unwrap().poll_recv(cx) {
-                Poll::Ready(None) => return Poll::Ready(()), // client has disconnected - shut down.
-                Poll::Ready(Some(cmd)) => Some(cmd),
-                _ => None,
-            };
+/// Play `mixer` on `sink`, through the high-quality resampler when asked.
+fn play_mixer(sink: &Sink, mixer: Mixer, output: &Output, resample: bool) {
+    if resample {
+        sink.append(Resampler::new(mixer, output.sample_rate()));
+    } else {
+        sink.append(mixer);
+    }
+}
 
-            if let Some(cmd) = cmd {
-                if let Err(e) = self.handle_command(cmd) {
-                    println!("{:?}", e);
-                }
+/// Remember station volumes from a thread of their own, so stepping the
+/// volume never waits on the disk. Only the last volume of each station
+/// queued while a write was under way gets written.
+fn spawn_volume_memory(
+    events: async_mpsc::UnboundedSender<PlayerEvent>,
+) -> Sender<(String, String, f32)> {
+    let (tx, rx) = mpsc::channel::<(String, String, f32)>();
+    thread::spawn(move || {
+        while let Ok(first) = rx.recv() {
+            let mut latest = vec![first];
+            for (provider, id, volume) in rx.try_iter() {
+                latest.retain(|(p, i, _)| *p != provider || *i != id);
+                latest.push((provider, id, volume));
+            }
+            let remembered = StationMemory::load().and_then(|mut memory| {
+                latest
+                    .iter()
+                    .try_for_each(|(provider, id, volume)| memory.set_volume(provider, id, *volume))
+            });
+            if let Err(err) = remembered {
+                let _ = events.send(PlayerEvent::Error(format!(
+                    "failed to remember the volume: {:#}",
+                    err
+                )));
             }
+        }
+    });
+    tx
+}
+
+/// Open `station`'s stream, read its ICY metadata and pre-buffer the
+/// decoded audio so it is ready to be faded in, reporting the read-ahead
+/// buffer's fill to `buffering`. Returns the codec name alongside. The
+/// stream's loudness is remembered for `provider`'s station.
+fn connect(
+    station: &Station,
+    provider: &str,
+    buffer: BufferOptions,
+    counters: &Arc<StreamCounters>,
+    buffering: impl FnMut(u8),
+) -> Result<(PlaybackState, String, Prebuffered<StreamDecoder>), Error> {
+    let (headers, decoder) = open_stream(&station.stream_url, buffer, counters.clone(), buffering)?;
+    let state = PlaybackState {
+        station: station.clone(),
+        stream_name: header_to_string(headers.get("icy-name"))
+            .filter(|name| name != "Unknown")
+            .unwrap_or_else(|| station.name.clone()),
+        now_playing: station.playing.clone().unwrap_or_default(),
+        genre: header_to_string(headers.get("icy-genre")).unwrap_or_default(),
+        description: header_to_string(headers.get("icy-description")).unwrap_or_default(),
+        bitrate: header_to_string(headers.get("icy-br")).unwrap_or_default(),
+        channel_layout: decoder.layout().to_string(),
+        volume: None,
+    };
+    let codec = decoder.codec().to_string();
+    let decoder = decoder.without_eq().with_loudness(provider, &station.id);
+    Ok((state, codec, Prebuffered::new(decoder, PREBUFFER)))
+}
+
+/// Connect to `url`, following redirects, fill the read-ahead buffer up to
+/// its prebuffer threshold, reporting progress to `buffering`, and start
+/// decoding. What comes in is counted to `counters`. Returns the final
+/// response's headers alongside the decoder.
+fn open_stream(
+    url: &str,
+    buffer: BufferOptions,
+    counters: Arc<StreamCounters>,
+    buffering: impl FnMut(u8),
+) -> Result<(reqwest::header::HeaderMap, StreamDecoder), Error> {
+    let client = reqwest::blocking::Client::new();
+    let response = client
+        .get(url)
+        .send()
+        .with_context(|| format!("failed to open stream {}", url))?;
+    let response = follow_redirects(client, response)?;
+    let headers = response.headers().clone();
+    let content_type = header_to_string(headers.get("content-type"));
+    let response = CountingReader::new(response, counters.clone());
+    let reader = ReadAhead::spawn(response, buffer, counters.clone())?;
+    reader.prebuffer(buffering);
+    let decoder = StreamDecoder::new(reader, content_type.as_deref(), None)
+        .with_context(|| format!("failed to decode stream {}", url))?
+        .with_counters(counters);
+    Ok((headers, decoder))
+}
 
-            thread::sleep(Duration::from_millis(500));
+/// Decode a local sound file, guessing its format from the extension.
+fn open_file(path: &str) -> Result<StreamDecoder, Error> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path))?;
+    let mime = mime_guess::from_path(path).first().map(|m| m.to_string());
+    StreamDecoder::new(file, mime.as_deref(), None)
+        .with_context(|| format!("failed to decode {}", path))
+}
+
+fn follow_redirects(
+    client: reqwest::blocking::Client,
+    response: reqwest::blocking::Response,
+) -> Result<reqwest::blocking::Response, Error> {
+    let mut current = response;
+    for _ in 0..3 {
+        if let Some(location) = current.headers().get("location") {
+            let url = location
+                .to_str()
+                .map_err(|_| Error::msg("invalid redirect location header"))?;
+            current = client.get(url).send()?;
+        } else {
+            return Ok(current);
         }
     }
+    Ok(current)
+}
+
+fn header_to_string(value: Option<&HeaderValue>) -> Option<String> {
+    value
+        .and_then(|header| header.to_str().ok())
+        .map(|s| s.to_string())
 }
@@ -0,0 +1,314 @@
+//! Read-ahead (jitter) buffer between a network stream and the decoder.
+//!
+//! A background thread keeps pulling the HTTP body into a bounded buffer
+//! while the decoder reads from the other end, so a short network hiccup
+//! drains the buffer instead of stalling the audio. Playback only starts
+//! once `prebuffer` bytes have arrived, and when the buffer does run dry
+//! the reader waits for it to refill to the same threshold before carrying
+//! on, rather than stuttering packet by packet.
+
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use anyhow::{Context as _, Error};
+
+use crate::stats::StreamCounters;
+
+/// How much is read from the network in one go.
+const CHUNK: usize = 8 * 1024;
+
+/// Sizes of the read-ahead buffer, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferOptions {
+    /// Most bytes held ahead of the decoder.
+    pub size: usize,
+    /// Bytes buffered before playback starts, and again after the buffer
+    /// ran dry.
+    pub prebuffer: usize,
+}
+
+impl BufferOptions {
+    pub fn from_kib(size: usize, prebuffer: usize) -> Self {
+        let size = size.max(1) * 1024;
+        Self {
+            size,
+            prebuffer: (prebuffer * 1024).min(size),
+        }
+    }
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self::from_kib(256, 32)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    data: VecDeque<u8>,
+    /// The network side is done: end of stream or an error.
+    eof: bool,
+    error: Option<io::Error>,
+    /// The buffer ran dry and the reader waits for it to refill.
+    refilling: bool,
+    /// The reader is gone; the fill thread should stop.
+    closed: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+    options: BufferOptions,
+    counters: Arc<StreamCounters>,
+}
+
+impl Shared {
+    fn publish(&self, state: &State) {
+        self.counters
+            .set_buffered(state.data.len(), state.refilling);
+        self.changed.notify_all();
+    }
+}
+
+/// Reading end of the read-ahead buffer. Dropping it stops the fill thread
+/// after its current network read.
+pub struct ReadAhead {
+    shared: Arc<Shared>,
+}
+
+impl ReadAhead {
+    /// Start filling a buffer from `inner` on a new thread, reporting its
+    /// fill level to `counters`.
+    pub fn spawn<R>(
+        inner: R,
+        options: BufferOptions,
+        counters: Arc<StreamCounters>,
+    ) -> Result<Self, Error>
+    where
+        R: Read + Send + 'static,
+    {
+        counters.set_buffer_limits(options.size, options.prebuffer);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                data: VecDeque::with_capacity(options.size),
+                refilling: true,
+                ..Default::default()
+            }),
+            changed: Condvar::new(),
+            options,
+            counters,
+        });
+        let filler = shared.clone();
+        thread::Builder::new()
+            .name("tunein-readahead".into())
+            .spawn(move || fill(inner, &filler))
+            .context("failed to spawn read-ahead thread")?;
+        Ok(Self { shared })
+    }
+
+    /// Block until the prebuffer threshold is reached or the stream ended,
+    /// calling `progress` with the filled percentage along the way.
+    pub fn prebuffer(&self, mut progress: impl FnMut(u8)) {
+        let threshold = self.shared.options.prebuffer;
+        let mut state = self.shared.state.lock().unwrap();
+        let mut reported = None;
+        loop {
+            let percent = match threshold {
+                0 => 100,
+                _ => (state.data.len().min(threshold) * 100 / threshold) as u8,
+            };
+            if reported != Some(percent) {
+                progress(percent);
+                reported = Some(percent);
+            }
+            if percent == 100 || state.eof {
+                return;
+            }
+            state = self.shared.changed.wait(state).unwrap();
+        }
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let shared = &self.shared;
+        let mut state = shared.state.lock().unwrap();
+        loop {
+            if state.refilling {
+                if state.data.len() >= shared.options.prebuffer || state.eof {
+                    state.refilling = false;
+                    shared.publish(&state);
+                } else {
+                    state = shared.changed.wait(state).unwrap();
+                    continue;
+                }
+            }
+            if !state.data.is_empty() {
+                let len = buf.len().min(state.data.len());
+                for (out, byte) in buf.iter_mut().zip(state.data.drain(..len)) {
+                    *out = byte;
+                }
+                shared.publish(&state);
+                return Ok(len);
+            }
+            if state.eof {
+                return match state.error.take() {
+                    Some(err) => Err(err),
+                    None => Ok(0),
+                };
+            }
+            state.refilling = true;
+            shared.publish(&state);
+        }
+    }
+}
+
+impl Drop for ReadAhead {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Body of the fill thread: copy `inner` into the buffer until it ends or
+/// the reader goes away.
+fn fill(mut inner: impl Read, shared: &Shared) {
+    let mut chunk = vec![0u8; CHUNK];
+    loop {
+        let read = match inner.read(&mut chunk) {
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                let mut state = shared.state.lock().unwrap();
+                state.eof = true;
+                state.error = Some(err);
+                shared.publish(&state);
+                return;
+            }
+        };
+
+        let mut state = shared.state.lock().unwrap();
+        if read == 0 {
+            state.eof = true;
+            shared.publish(&state);
+            return;
+        }
+        // Hand over as much as fits, so a reader waiting for the prebuffer
+        // threshold always gets there.
+        let mut pending = &chunk[..read];
+        while !pending.is_empty() {
+            while !state.closed && state.data.len() >= shared.options.size {
+                state = shared.changed.wait(state).unwrap();
+            }
+            if state.closed {
+                return;
+            }
+            let len = pending.len().min(shared.options.size - state.data.len());
+            state.data.extend(&pending[..len]);
+            pending = &pending[len..];
+            shared.publish(&state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// A network body fed chunk by chunk from a channel.
+    struct Feed(mpsc::Receiver<Vec<u8>>, Vec<u8>);
+
+    impl Read for Feed {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.1.is_empty() {
+                match self.0.recv() {
+                    Ok(chunk) => self.1 = chunk,
+                    Err(_) => return Ok(0),
+                }
+            }
+            let len = buf.len().min(self.1.len());
+            buf[..len].copy_from_slice(&self.1[..len]);
+            self.1.drain(..len);
+            Ok(len)
+        }
+    }
+
+    fn options(size: usize, prebuffer: usize) -> BufferOptions {
+        BufferOptions { size, prebuffer }
+    }
+
+    #[test]
+    fn passes_the_stream_through_unchanged() {
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        let counters = Arc::new(StreamCounters::default());
+        let mut reader =
+            ReadAhead::spawn(io::Cursor::new(data.clone()), options(4096, 1024), counters).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn prebuffer_waits_for_the_threshold() {
+        let (tx, rx) = mpsc::channel();
+        let counters = Arc::new(StreamCounters::default());
+        let reader =
+            ReadAhead::spawn(Feed(rx, Vec::new()), options(4000, 1000), counters.clone()).unwrap();
+
+        let feeder = thread::spawn(move || {
+            for _ in 0..4 {
+                thread::sleep(Duration::from_millis(10));
+                tx.send(vec![0u8; 250]).unwrap();
+            }
+            tx
+        });
+        let mut reported = Vec::new();
+        reader.prebuffer(|percent| reported.push(percent));
+        assert_eq!(reported.first(), Some(&0));
+        assert_eq!(reported.last(), Some(&100));
+        assert!(reported.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(counters.buffer_fill(), 25);
+        drop(feeder.join().unwrap());
+    }
+
+    #[test]
+    fn refills_to_the_threshold_after_running_dry() {
+        let (tx, rx) = mpsc::channel();
+        let counters = Arc::new(StreamCounters::default());
+        let mut reader =
+            ReadAhead::spawn(Feed(rx, Vec::new()), options(4096, 100), counters.clone()).unwrap();
+        tx.send(vec![1u8; 100]).unwrap();
+        let mut buf = [0u8; 200];
+        assert_eq!(reader.read(&mut buf).unwrap(), 100);
+
+        // Dry: a few bytes trickling in are held back until the threshold.
+        let feeder = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx.send(vec![2u8; 10]).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            assert!(counters.refilling());
+            tx.send(vec![3u8; 90]).unwrap();
+        });
+        assert_eq!(reader.read(&mut buf).unwrap(), 100);
+        assert_eq!(buf[0], 2);
+        assert_eq!(buf[99], 3);
+        feeder.join().unwrap();
+    }
+
+    #[test]
+    fn never_holds_more_than_its_size() {
+        let counters = Arc::new(StreamCounters::default());
+        let reader =
+            ReadAhead::spawn(io::repeat(0), options(16 * 1024, 0), counters.clone()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(reader.shared.state.lock().unwrap().data.len() <= 16 * 1024);
+        assert!(counters.buffer_fill() > 0);
+    }
+}
@@ -0,0 +1,261 @@
+//! Re-serving a station to someone else: the `/listen` LAN relay and the
+//! Icecast source client both read one [`Upstream`], with its ICY metadata
+//! split off, and hand its chunks on untouched or decoded, equalized and
+//! re-encoded as WAV.
+
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context as _, Error};
+use rodio::source::UniformSourceIterator;
+
+use crate::decoder::StreamDecoder;
+use crate::dither::Dither;
+use crate::equalizer::Equalizer;
+use crate::output::{wav_header, PCM_CHANNELS, PCM_SAMPLE_RATE};
+use crate::resample::Resampler;
+
+/// Bytes read from the upstream per chunk.
+const CHUNK_BYTES: usize = 4096;
+
+/// ICY headers worth passing on to listeners.
+const ICY_HEADERS: &[&str] = &[
+    "icy-name",
+    "icy-genre",
+    "icy-description",
+    "icy-br",
+    "icy-url",
+];
+
+/// A connected station, read chunk by chunk.
+pub struct Upstream {
+    pub chunks: Box<dyn Iterator<Item = Vec<u8>> + Send>,
+    /// Content type of the chunks: the station's own, or `audio/wav` when
+    /// equalized.
+    pub content_type: String,
+    /// The station's ICY headers (`icy-name`, `icy-genre`, ...).
+    pub headers: Vec<(String, String)>,
+    /// Latest `StreamTitle` seen on the stream.
+    pub title: Arc<Mutex<String>>,
+    /// Sent before the chunks to anyone joining: the WAV header of an
+    /// equalized stream.
+    pub preamble: Option<Vec<u8>>,
+}
+
+impl Upstream {
+    /// Connect to `url`. With an `eq` the stream is decoded, run through
+    /// that equalizer and re-encoded as endless 16-bit stereo 44.1 kHz WAV.
+    pub fn open(url: &str, eq: Option<Arc<Equalizer>>) -> Result<Self, Error> {
+        let response = reqwest::blocking::Client::new()
+            .get(url)
+            .header("Icy-MetaData", "1")
+            .send()
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("failed to open stream {}", url))?;
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let metaint = header("icy-metaint").and_then(|value| value.trim().parse().ok());
+        let content_type = header("content-type").unwrap_or_else(|| "audio/mpeg".to_string());
+        let headers = ICY_HEADERS
+            .iter()
+            .filter_map(|name| header(name).map(|value| (name.to_string(), value)))
+            .collect();
+
+        let title = Arc::new(Mutex::new(String::new()));
+        let mut reader = IcyReader::new(response, metaint, title.clone());
+
+        let Some(eq) = eq else {
+            let chunks = std::iter::from_fn(move || {
+                let mut buf = vec![0u8; CHUNK_BYTES];
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => None,
+                    Ok(read) => {
+                        buf.truncate(read);
+                        Some(buf)
+                    }
+                }
+            });
+            return Ok(Self {
+                chunks: Box::new(chunks),
+                content_type,
+                headers,
+                title,
+                preamble: None,
+            });
+        };
+
+        let decoder = StreamDecoder::new(reader, Some(&content_type), None)
+            .with_context(|| format!("failed to decode stream {}", url))?
+            .with_equalizer(eq);
+        let mut pcm = UniformSourceIterator::<_, f32>::new(
+            Resampler::new(decoder, PCM_SAMPLE_RATE),
+            PCM_CHANNELS,
+            PCM_SAMPLE_RATE,
+        );
+        let mut dither = Dither::new();
+        let chunks = std::iter::from_fn(move || {
+            let chunk: Vec<u8> = pcm
+                .by_ref()
+                .take(CHUNK_BYTES / 2)
+                .flat_map(|sample| dither.quantize(sample).to_le_bytes())
+                .collect();
+            (!chunk.is_empty()).then_some(chunk)
+        });
+        Ok(Self {
+            chunks: Box::new(chunks),
+            content_type: "audio/wav".to_string(),
+            headers,
+            title,
+            preamble: Some(wav_header(u32::MAX).to_vec()),
+        })
+    }
+}
+
+/// Strips ICY metadata blocks out of a stream, keeping the latest
+/// `StreamTitle` in `title`.
+pub struct IcyReader<R> {
+    inner: R,
+    metaint: Option<usize>,
+    until_metadata: usize,
+    title: Arc<Mutex<String>>,
+}
+
+impl<R: Read> IcyReader<R> {
+    pub fn new(inner: R, metaint: Option<usize>, title: Arc<Mutex<String>>) -> Self {
+        Self {
+            inner,
+            metaint,
+            until_metadata: metaint.unwrap_or(0),
+            title,
+        }
+    }
+
+    fn read_metadata(&mut self) -> io::Result<()> {
+        let mut len = [0u8; 1];
+        self.inner.read_exact(&mut len)?;
+        let mut block = vec![0u8; len[0] as usize * 16];
+        self.inner.read_exact(&mut block)?;
+        let text = String::from_utf8_lossy(&block);
+        if let Some(title) = stream_title(&text) {
+            *self.title.lock().unwrap() = title.to_string();
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for IcyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(metaint) = self.metaint else {
+            return self.inner.read(buf);
+        };
+        if self.until_metadata == 0 {
+            self.read_metadata()?;
+            self.until_metadata = metaint;
+        }
+        let len = buf.len().min(self.until_metadata);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.until_metadata -= read;
+        Ok(read)
+    }
+}
+
+/// The `StreamTitle` of an ICY metadata block.
+pub fn stream_title(metadata: &str) -> Option<&str> {
+    let start = metadata.find("StreamTitle='")? + "StreamTitle='".len();
+    let len = metadata[start..].find("';")?;
+    Some(&metadata[start..start + len])
+}
+
+/// Interleaves ICY metadata blocks into a listener's audio every `metaint`
+/// bytes. The title is only sent again after it changes.
+pub struct IcyInserter {
+    metaint: usize,
+    until_metadata: usize,
+    sent_title: Option<String>,
+}
+
+impl IcyInserter {
+    pub fn new(metaint: usize) -> Self {
+        Self {
+            metaint,
+            until_metadata: metaint,
+            sent_title: None,
+        }
+    }
+
+    pub fn insert(&mut self, mut audio: &[u8], title: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(audio.len() + 1);
+        while !audio.is_empty() {
+            let len = audio.len().min(self.until_metadata);
+            out.extend_from_slice(&audio[..len]);
+            audio = &audio[len..];
+            self.until_metadata -= len;
+            if self.until_metadata == 0 {
+                self.write_metadata(&mut out, title);
+                self.until_metadata = self.metaint;
+            }
+        }
+        out
+    }
+
+    fn write_metadata(&mut self, out: &mut Vec<u8>, title: &str) {
+        if self.sent_title.as_deref() == Some(title) {
+            out.push(0);
+            return;
+        }
+        let mut text = format!("StreamTitle='{}';", title).into_bytes();
+        text.truncate(255 * 16);
+        let blocks = text.len().div_ceil(16);
+        text.resize(blocks * 16, 0);
+        out.push(blocks as u8);
+        out.extend_from_slice(&text);
+        self.sent_title = Some(title.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserted_metadata_strips_back_to_the_same_audio() {
+        let audio: Vec<u8> = (0..50_000).map(|i| (i % 251) as u8).collect();
+        let mut inserter = IcyInserter::new(1000);
+        let mut relayed = Vec::new();
+        for (i, chunk) in audio.chunks(777).enumerate() {
+            let title = if i < 30 { "First Song" } else { "Second Song" };
+            relayed.extend(inserter.insert(chunk, title));
+        }
+        assert!(relayed.len() > audio.len());
+
+        let title = Arc::new(Mutex::new(String::new()));
+        let mut reader = IcyReader::new(&relayed[..], Some(1000), title.clone());
+        let mut stripped = Vec::new();
+        reader.read_to_end(&mut stripped).unwrap();
+        assert_eq!(stripped, audio);
+        assert_eq!(*title.lock().unwrap(), "Second Song");
+    }
+
+    #[test]
+    fn unchanged_titles_are_sent_as_empty_blocks() {
+        let mut inserter = IcyInserter::new(4);
+        // `StreamTitle='';` fits one 16-byte block.
+        assert_eq!(inserter.insert(&[1, 2, 3, 4], "")[4], 1);
+        assert_eq!(inserter.insert(&[5, 6, 7, 8], ""), vec![5, 6, 7, 8, 0]);
+    }
+
+    #[test]
+    fn parses_stream_title() {
+        assert_eq!(
+            stream_title("StreamTitle='Artist - Song';StreamUrl='';\0\0"),
+            Some("Artist - Song")
+        );
+        assert_eq!(stream_title("StreamUrl='x';"), None);
+    }
+}
@@ -0,0 +1,338 @@
+//! High-quality sample-rate conversion to a fixed rate.
+//!
+//! rodio converts between rates by linear interpolation, which aliases and
+//! dulls the top octave. A [`Resampler`] uses a windowed-sinc filter
+//! instead: 32 Blackman-windowed taps, interpolated between 256 phases, with
+//! the cutoff lowered to the output's Nyquist frequency when downsampling.
+//! Streams already at the target rate pass through untouched.
+
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use rodio::Source;
+
+/// Zero crossings of the sinc kernel on each side of a sample.
+const HALF_TAPS: usize = 16;
+const TAPS: usize = HALF_TAPS * 2;
+
+/// Fractional positions the kernel is tabulated at.
+const PHASES: usize = 256;
+
+/// Samples read from a source that does not report frame lengths.
+const CHUNK_FRAMES: usize = 1024;
+
+/// Resamples `S`, which may change format at its frame boundaries, to a
+/// fixed rate. The channel count is left alone.
+pub struct Resampler<S> {
+    inner: S,
+    rate: u32,
+    in_rate: u32,
+    channels: u16,
+    /// Interleaved input not yet fully used, starting `HALF_TAPS - 1`
+    /// frames before the next output position.
+    input: Vec<f32>,
+    /// Where the next output frame falls, in input frames into `input`.
+    position: f64,
+    /// Input frames per output frame.
+    step: f64,
+    /// `PHASES + 1` rows of `TAPS` weights.
+    kernel: Vec<f32>,
+    output: Vec<f32>,
+    offset: usize,
+}
+
+impl<S> Resampler<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(inner: S, rate: u32) -> Self {
+        Self {
+            inner,
+            rate,
+            in_rate: 0,
+            channels: 0,
+            input: Vec::new(),
+            position: 0.0,
+            step: 1.0,
+            kernel: Vec::new(),
+            output: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Start over for a new input format, dropping the little history left
+    /// of the previous one.
+    fn reset(&mut self, channels: u16, in_rate: u32) {
+        let rate_changed = in_rate != self.in_rate;
+        self.channels = channels;
+        self.in_rate = in_rate;
+        self.step = in_rate as f64 / self.rate as f64;
+        self.input.clear();
+        self.position = 0.0;
+        if self.passthrough() {
+            return;
+        }
+        if rate_changed {
+            self.kernel = kernel((self.rate as f64 / in_rate as f64).min(1.0));
+        }
+        // Silence before the first sample, so output starts right away.
+        self.input.resize((HALF_TAPS - 1) * channels as usize, 0.0);
+        self.position = (HALF_TAPS - 1) as f64;
+    }
+
+    fn passthrough(&self) -> bool {
+        self.in_rate == self.rate
+    }
+
+    /// Append the next chunk of the inner source to `input`. Returns false
+    /// at the end of the stream.
+    fn pull(&mut self) -> bool {
+        // Reading the first sample starts the inner source's next frame, so
+        // its format is only known afterwards.
+        let Some(first) = self.inner.next() else {
+            return false;
+        };
+        let channels = self.inner.channels().max(1);
+        let in_rate = self.inner.sample_rate().max(1);
+        if channels != self.channels || in_rate != self.in_rate {
+            self.reset(channels, in_rate);
+        }
+        let len = match self.inner.current_frame_len() {
+            Some(len) => len,
+            None => CHUNK_FRAMES * channels as usize - 1,
+        };
+        self.input.push(first);
+        self.input.extend(self.inner.by_ref().take(len));
+        true
+    }
+
+    /// Turn as much of `input` into output as the kernel's reach allows.
+    fn convert(&mut self) {
+        if self.passthrough() {
+            std::mem::swap(&mut self.output, &mut self.input);
+            self.input.clear();
+            return;
+        }
+
+        let channels = self.channels as usize;
+        let frames = self.input.len() / channels;
+        while self.position as usize + HALF_TAPS < frames {
+            let base = self.position as usize;
+            let phase = (self.position - base as f64) * PHASES as f64;
+            let row = phase as usize;
+            let mix = (phase - row as f64) as f32;
+            let near = &self.kernel[row * TAPS..(row + 1) * TAPS];
+            let far = &self.kernel[(row + 1) * TAPS..(row + 2) * TAPS];
+            let start = base + 1 - HALF_TAPS;
+            for channel in 0..channels {
+                let mut acc = 0.0;
+                for (tap, (near, far)) in near.iter().zip(far).enumerate() {
+                    let weight = near + (far - near) * mix;
+                    acc += weight * self.input[(start + tap) * channels + channel];
+                }
+                self.output.push(acc);
+            }
+            self.position += self.step;
+        }
+
+        let used = (self.position as usize + 1)
+            .saturating_sub(HALF_TAPS)
+            .min(frames);
+        self.input.drain(..used * channels);
+        self.position -= used as f64;
+    }
+
+    /// Produce the next chunk of output. Returns false at the end of the
+    /// stream.
+    fn fill(&mut self) -> bool {
+        self.output.clear();
+        self.offset = 0;
+        loop {
+            if !self.input.is_empty() {
+                self.convert();
+                if !self.output.is_empty() {
+                    return true;
+                }
+            }
+            if !self.pull() {
+                return false;
+            }
+        }
+    }
+}
+
+/// Blackman-windowed sinc with its cutoff at `cutoff` × Nyquist, tabulated
+/// for every phase. Each row is normalised to unity gain.
+fn kernel(cutoff: f64) -> Vec<f32> {
+    let mut table = Vec::with_capacity((PHASES + 1) * TAPS);
+    for phase in 0..=PHASES {
+        let frac = phase as f64 / PHASES as f64;
+        let row: Vec<f64> = (0..TAPS)
+            .map(|tap| {
+                let distance = frac + (HALF_TAPS - 1) as f64 - tap as f64;
+                sinc(cutoff * distance) * blackman(distance / HALF_TAPS as f64)
+            })
+            .collect();
+        let sum: f64 = row.iter().sum();
+        table.extend(row.iter().map(|weight| (weight / sum) as f32));
+    }
+    table
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman window over `-1..=1`, zero outside.
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+}
+
+impl<S> Source for Resampler<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.output.len() - self.offset)
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels.max(1)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl<S> Iterator for Resampler<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.offset >= self.output.len() && !self.fill() {
+            return None;
+        }
+        let sample = self.output[self.offset];
+        self.offset += 1;
+        Some(sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rodio::buffer::SamplesBuffer;
+
+    fn sine(freq: f64, rate: u32, frames: usize) -> SamplesBuffer<f32> {
+        let samples = (0..frames)
+            .flat_map(|i| {
+                let s = (0.5 * (2.0 * PI * freq * i as f64 / rate as f64).sin()) as f32;
+                [s, s]
+            })
+            .collect::<Vec<_>>();
+        SamplesBuffer::new(2, rate, samples)
+    }
+
+    fn rms(samples: &[f32]) -> f64 {
+        (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn same_rate_passes_through_untouched() {
+        let input: Vec<f32> = sine(1000.0, 48000, 5000).collect();
+        let output: Vec<f32> = Resampler::new(sine(1000.0, 48000, 5000), 48000).collect();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn upsampling_keeps_the_tone() {
+        let resampler = Resampler::new(sine(1000.0, 44100, 44100), 48000);
+        assert_eq!(resampler.sample_rate(), 48000);
+        let output: Vec<f32> = resampler.collect();
+        let frames = output.len() / 2;
+        assert!((frames as i64 - 48000).abs() < 64, "{} frames", frames);
+
+        // Steady state: a 0.5 sine has an RMS of 0.354, and crosses zero
+        // twice per cycle.
+        let middle = &output[24000..72000];
+        assert!((rms(middle) - 0.3536).abs() < 0.002, "rms {}", rms(middle));
+        let left: Vec<f32> = middle.iter().step_by(2).copied().collect();
+        let crossings = left
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        assert!(
+            (crossings as i64 - 1000).abs() <= 2,
+            "{} crossings",
+            crossings
+        );
+    }
+
+    #[test]
+    fn downsampling_removes_what_would_alias() {
+        // 18 kHz is above the 11 kHz Nyquist frequency of 22.05 kHz.
+        let output: Vec<f32> = Resampler::new(sine(18000.0, 48000, 48000), 22050).collect();
+        let middle = &output[4096..output.len() - 4096];
+        assert!(rms(middle) < 0.01, "rms {}", rms(middle));
+    }
+
+    /// Plays `parts` back to back, reporting each one as a frame.
+    struct Parts(Vec<(u16, u32, Vec<f32>)>);
+
+    impl Iterator for Parts {
+        type Item = f32;
+        fn next(&mut self) -> Option<f32> {
+            while self.0.first()?.2.is_empty() {
+                self.0.remove(0);
+            }
+            Some(self.0[0].2.remove(0))
+        }
+    }
+
+    impl Source for Parts {
+        fn current_frame_len(&self) -> Option<usize> {
+            Some(self.0.first().map_or(0, |part| part.2.len()))
+        }
+        fn channels(&self) -> u16 {
+            self.0.first().map_or(1, |part| part.0)
+        }
+        fn sample_rate(&self) -> u32 {
+            self.0.first().map_or(44100, |part| part.1)
+        }
+        fn total_duration(&self) -> Option<Duration> {
+            None
+        }
+    }
+
+    #[test]
+    fn follows_format_changes() {
+        let parts = Parts(vec![
+            (2, 44100, sine(1000.0, 44100, 4410).collect()),
+            (1, 22050, vec![0.25; 2205]),
+        ]);
+        let mut resampler = Resampler::new(parts, 44100);
+        let stereo: Vec<f32> = resampler.by_ref().take(4410 * 2).collect();
+        assert_eq!(stereo.len(), 4410 * 2);
+        let mono: Vec<f32> = resampler.collect();
+        assert!(
+            (mono.len() as i64 - 4410).abs() < 64,
+            "{} samples",
+            mono.len()
+        );
+        assert!((mono[2000] - 0.25).abs() < 0.001);
+    }
+}
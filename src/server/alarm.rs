@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use chrono::{Local, Utc};
+use tunein_cli::api::{
+    objects::v1alpha1::Alarm as AlarmObject,
+    tunein::v1alpha1::{
+        alarm_service_server::AlarmService, ListAlarmsRequest, ListAlarmsResponse,
+        SnoozeAlarmRequest, SnoozeAlarmResponse,
+    },
+};
+use tunein_cli::provider::{radiobrowser::Radiobrowser, tunein::Tunein, Provider};
+
+use crate::alarm::{Alarm, AlarmStore, DEFAULT_SNOOZE_MINUTES};
+use crate::player::{AlarmPlayback, Player, PlayerCommand};
+
+#[derive(Default)]
+pub struct Alarms;
+
+#[tonic::async_trait]
+impl AlarmService for Alarms {
+    async fn list_alarms(
+        &self,
+        _request: tonic::Request<ListAlarmsRequest>,
+    ) -> Result<tonic::Response<ListAlarmsResponse>, tonic::Status> {
+        let store = AlarmStore::load().map_err(|e| tonic::Status::internal(e.to_string()))?;
+        Ok(tonic::Response::new(ListAlarmsResponse {
+            alarms: store.all().iter().cloned().map(AlarmObject::from).collect(),
+        }))
+    }
+
+    async fn snooze_alarm(
+        &self,
+        request: tonic::Request<SnoozeAlarmRequest>,
+    ) -> Result<tonic::Response<SnoozeAlarmResponse>, tonic::Status> {
+        let req = request.into_inner();
+        let mut store = AlarmStore::load().map_err(|e| tonic::Status::internal(e.to_string()))?;
+        if store.get(req.id).is_none() {
+            return Err(tonic::Status::not_found(format!(
+                "no alarm with id {}",
+                req.id
+            )));
+        }
+        let alarm = store
+            .snooze(
+                req.id,
+                req.minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES),
+                Utc::now().timestamp(),
+            )
+            .map_err(|e| tonic::Status::failed_precondition(e.to_string()))?;
+        Ok(tonic::Response::new(SnoozeAlarmResponse {
+            alarm: Some(alarm.into()),
+        }))
+    }
+}
+
+impl From<Alarm> for AlarmObject {
+    fn from(alarm: Alarm) -> Self {
+        Self {
+            id: alarm.id,
+            time: alarm.time,
+            days: alarm.days,
+            station: alarm.station,
+            provider: alarm.provider,
+            volume: alarm.volume,
+            ramp_secs: alarm.ramp_secs,
+            fallback: alarm.fallback,
+            enabled: alarm.enabled,
+            snoozed_until: alarm.snoozed_until,
+        }
+    }
+}
+
+/// Check the alarm store once a second and ring whatever is due. The store
+/// is re-read whenever the file changes, so alarms added or snoozed from the
+/// CLI, gRPC or GraphQL take effect without restarting the daemon.
+pub async fn run_scheduler(player: Player) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    let mut last = Local::now().naive_local();
+    let mut last_ts = Utc::now().timestamp();
+    // The alarm currently ringing, so a snooze can silence it.
+    let mut ringing: Option<u32> = None;
+    let mut store: Option<AlarmStore> = None;
+    let mut modified = None;
+
+    loop {
+        interval.tick().await;
+        let now = Local::now().naive_local();
+        let now_ts = Utc::now().timestamp();
+
+        let changed = AlarmStore::modified();
+        if store.is_none() || changed != modified {
+            match AlarmStore::load() {
+                Ok(loaded) => {
+                    store = Some(loaded);
+                    modified = changed;
+                }
+                Err(err) => {
+                    eprintln!("failed to load alarms: {:?}", err);
+                    continue;
+                }
+            }
+        }
+        let Some(store) = &mut store else {
+            continue;
+        };
+
+        if let Some(id) = ringing {
+            // Something else was played, or playback stopped, since.
+            if !player.alarm_ringing() {
+                ringing = None;
+            } else if store.get(id).is_none_or(|alarm| alarm.is_snoozed(now_ts)) {
+                let _ = player.send(PlayerCommand::Stop);
+                ringing = None;
+            }
+        }
+
+        let due: Vec<Alarm> = store
+            .all()
+            .iter()
+            .filter(|alarm| alarm.is_due(last, now, last_ts, now_ts))
+            .cloned()
+            .collect();
+        for alarm in due {
+            eprintln!("alarm {} ringing: {}", alarm.id, alarm.station);
+            let url = resolve_stream_url(&alarm).await;
+            let _ = player.send(PlayerCommand::PlayAlarm(AlarmPlayback {
+                url,
+                volume: alarm.volume,
+                ramp: alarm.ramp(),
+                fallback: alarm.fallback,
+            }));
+            ringing = Some(alarm.id);
+        }
+
+        // Tell the CLI and the APIs which alarm can be snoozed.
+        if let Err(err) = store.mark_ringing(ringing) {
+            eprintln!("failed to save alarms: {:?}", err);
+        }
+
+        last = now;
+        last_ts = now_ts;
+    }
+}
+
+async fn resolve_stream_url(alarm: &Alarm) -> Option<String> {
+    let client: Box<dyn Provider + Send + Sync> = match alarm.provider.as_str() {
+        "radiobrowser" => Box::new(Radiobrowser::new().await),
+        _ => Box::new(Tunein::new()),
+    };
+    match client.get_station(alarm.station.clone()).await {
+        Ok(Some(station)) if !station.stream_url.is_empty() => Some(station.stream_url),
+        Ok(_) => None,
+        Err(err) => {
+            eprintln!(
+                "failed to resolve alarm station {}: {:?}",
+                alarm.station, err
+            );
+            None
+        }
+    }
+}
@@ -1,21 +1,37 @@
 use std::net::SocketAddr;
+use std::time::Duration;
 
+use actix_web::{web, App, HttpServer};
 use anyhow::Error;
 use owo_colors::OwoColorize;
 use tonic::transport::Server;
 use tunein_cli::api::tunein::v1alpha1::{
-    browse_service_server::BrowseServiceServer, playback_service_server::PlaybackServiceServer,
+    alarm_service_server::AlarmServiceServer, browse_service_server::BrowseServiceServer,
+    playback_service_server::PlaybackServiceServer,
 };
 use tunein_cli::api::tunein::FILE_DESCRIPTOR_SET;
 
-use self::{browse::Browse, playback::Playback};
+use crate::output::OutputSpec;
+use crate::player::{Player, PlayerEvent, PlayerOptions};
+use crate::readahead::BufferOptions;
 
+use self::{alarm::Alarms, browse::Browse, playback::Playback, relay::Relay};
+
+pub mod alarm;
 pub mod browse;
 pub mod playback;
+pub mod relay;
 
-pub async fn exec(port: u16) -> Result<(), Error> {
+pub async fn exec(
+    port: u16,
+    relay_port: u16,
+    crossfade: Duration,
+    buffer: BufferOptions,
+    resample: bool,
+    output: OutputSpec,
+) -> Result<(), Error> {
     let addr: SocketAddr = format!("0.0.0.0:{}", port).parse().unwrap();
-    println!(
+    eprintln!(
         "{}",
         r#"
         ______              ____       _______   ____
@@ -26,8 +42,47 @@ pub async fn exec(port: u16) -> Result<(), Error> {
     "#
         .bright_green()
     );
-    println!("Listening on {}", addr.cyan());
-    Server::builder()
+    eprintln!("Listening on {}", addr.cyan());
+    eprintln!(
+        "Relaying streams at {}",
+        format!("http://0.0.0.0:{}/listen/<station>", relay_port).cyan()
+    );
+
+    let (player, mut events) = Player::new(PlayerOptions {
+        output,
+        crossfade,
+        buffer,
+        resample,
+        volume: 1.0,
+        frames: None,
+    })?;
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            match event {
+                PlayerEvent::Connecting(station) => eprintln!("connecting to {}", station.name),
+                PlayerEvent::Started(state) => eprintln!("playing {}", state.stream_name),
+                PlayerEvent::Underrun { total } => eprintln!("buffer underrun ({} so far)", total),
+                PlayerEvent::LoadFailed { station, error } => {
+                    eprintln!("failed to play {}: {}", station.name, error)
+                }
+                PlayerEvent::Error(err) => eprintln!("{}", err),
+                PlayerEvent::Stopped => {}
+            }
+        }
+    });
+    tokio::spawn(alarm::run_scheduler(player.clone()));
+
+    let relay = web::Data::new(Relay::new(player.clone()));
+    let relay_server = HttpServer::new(move || {
+        App::new()
+            .app_data(relay.clone())
+            .route("/listen", web::get().to(relay::listen))
+            .route("/listen/{station}", web::get().to(relay::listen))
+    })
+    .bind(("0.0.0.0", relay_port))?
+    .run();
+
+    let grpc_server = Server::builder()
         .accept_http1(true)
         .add_service(
             tonic_reflection::server::Builder::configure()
@@ -38,9 +93,13 @@ pub async fn exec(port: u16) -> Result<(), Error> {
             Browse::default(),
         )))
         .add_service(tonic_web::enable(PlaybackServiceServer::new(
-            Playback::default(),
+            Playback::new(player),
         )))
-        .serve(addr)
-        .await?;
+        .add_service(tonic_web::enable(AlarmServiceServer::new(Alarms)))
+        .serve(addr);
+
+    tokio::try_join!(async { grpc_server.await.map_err(Error::from) }, async {
+        relay_server.await.map_err(Error::from)
+    },)?;
     Ok(())
 }
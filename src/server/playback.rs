@@ -1,27 +1,39 @@
-use std::sync::{Arc, Mutex};
-
+use crate::dynamics::{Dynamics, DynamicsParams};
 use crate::player::{Player, PlayerCommand};
-use tokio::sync::mpsc;
+use crate::stats::{PlaybackPhase, PlaybackStats};
+use crate::types::Station;
 use tunein_cli::provider::{tunein::Tunein, Provider};
 use tunein_cli::{
     api::tunein::v1alpha1::{
-        playback_service_server::PlaybackService, PlayOrPauseRequest, PlayOrPauseResponse,
-        PlayRequest, PlayResponse, StopRequest, StopResponse,
+        self, playback_service_server::PlaybackService, GetDynamicsRequest, GetDynamicsResponse,
+        GetPlaybackStatsRequest, GetPlaybackStatsResponse, PlayOrPauseRequest, PlayOrPauseResponse,
+        PlayRequest, PlayResponse, SetDynamicsRequest, SetDynamicsResponse, StopRequest,
+        StopResponse,
     },
     provider::radiobrowser::Radiobrowser,
 };
 
+/// The providers here come from the library, whose `Station` the binary's
+/// player doesn't take.
+fn to_station(station: tunein_cli::types::Station) -> Station {
+    Station {
+        id: station.id,
+        name: station.name,
+        codec: station.codec,
+        bitrate: station.bitrate,
+        stream_url: station.stream_url,
+        playing: station.playing,
+    }
+}
+
 pub struct Playback {
     player: Player,
-    cmd_tx: mpsc::UnboundedSender<PlayerCommand>,
 }
 
-impl Default for Playback {
-    fn default() -> Self {
-        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel::<PlayerCommand>();
-        let cmd_rx = Arc::new(Mutex::new(cmd_rx));
-        let player = Player::new(cmd_rx);
-        Self { player, cmd_tx }
+impl Playback {
+    /// The player is shared with the alarm scheduler.
+    pub fn new(player: Player) -> Self {
+        Self { player }
     }
 }
 
@@ -54,10 +66,13 @@ impl PlaybackService for Playback {
         }
 
         let station = station.unwrap();
-        let stream_url = station.stream_url.clone();
-        println!("{}", stream_url);
 
-        self.cmd_tx.send(PlayerCommand::Play(stream_url)).unwrap();
+        self.player
+            .send(PlayerCommand::Play {
+                station: to_station(station),
+                provider: provider.unwrap_or("tunein").to_string(),
+            })
+            .map_err(|e| tonic::Status::internal(e.to_string()))?;
         Ok(tonic::Response::new(PlayResponse {}))
     }
 
@@ -65,7 +80,9 @@ impl PlaybackService for Playback {
         &self,
         _request: tonic::Request<StopRequest>,
     ) -> Result<tonic::Response<StopResponse>, tonic::Status> {
-        self.cmd_tx.send(PlayerCommand::Stop).unwrap();
+        self.player
+            .send(PlayerCommand::Stop)
+            .map_err(|e| tonic::Status::internal(e.to_string()))?;
         Ok(tonic::Response::new(StopResponse {}))
     }
 
@@ -73,7 +90,86 @@ impl PlaybackService for Playback {
         &self,
         _request: tonic::Request<PlayOrPauseRequest>,
     ) -> Result<tonic::Response<PlayOrPauseResponse>, tonic::Status> {
-        self.cmd_tx.send(PlayerCommand::PlayOrPause).unwrap();
+        self.player
+            .send(PlayerCommand::PlayOrPause)
+            .map_err(|e| tonic::Status::internal(e.to_string()))?;
         Ok(tonic::Response::new(PlayOrPauseResponse {}))
     }
+
+    async fn get_playback_stats(
+        &self,
+        _request: tonic::Request<GetPlaybackStatsRequest>,
+    ) -> Result<tonic::Response<GetPlaybackStatsResponse>, tonic::Status> {
+        Ok(tonic::Response::new(GetPlaybackStatsResponse {
+            stats: Some(self.player.stats().into()),
+        }))
+    }
+
+    async fn get_dynamics(
+        &self,
+        _request: tonic::Request<GetDynamicsRequest>,
+    ) -> Result<tonic::Response<GetDynamicsResponse>, tonic::Status> {
+        Ok(tonic::Response::new(GetDynamicsResponse {
+            dynamics: Some(current_dynamics()),
+        }))
+    }
+
+    async fn set_dynamics(
+        &self,
+        request: tonic::Request<SetDynamicsRequest>,
+    ) -> Result<tonic::Response<SetDynamicsResponse>, tonic::Status> {
+        let req = request.into_inner();
+        let dynamics = Dynamics::global();
+        let params = dynamics.params();
+        dynamics.set(
+            req.night_mode.unwrap_or_else(|| dynamics.is_enabled()),
+            DynamicsParams {
+                threshold_db: req.threshold_db.unwrap_or(params.threshold_db),
+                ratio: req.ratio.unwrap_or(params.ratio),
+                attack_ms: req.attack_ms.unwrap_or(params.attack_ms),
+                release_ms: req.release_ms.unwrap_or(params.release_ms),
+                ceiling_db: req.ceiling_db.unwrap_or(params.ceiling_db),
+            },
+        );
+        Ok(tonic::Response::new(SetDynamicsResponse {
+            dynamics: Some(current_dynamics()),
+        }))
+    }
+}
+
+/// Night mode as the player currently applies it (values clamped).
+fn current_dynamics() -> v1alpha1::Dynamics {
+    let dynamics = Dynamics::global();
+    let params = dynamics.params();
+    v1alpha1::Dynamics {
+        night_mode: dynamics.is_enabled(),
+        threshold_db: params.threshold_db,
+        ratio: params.ratio,
+        attack_ms: params.attack_ms,
+        release_ms: params.release_ms,
+        ceiling_db: params.ceiling_db,
+    }
+}
+
+impl From<PlaybackStats> for v1alpha1::PlaybackStats {
+    fn from(stats: PlaybackStats) -> Self {
+        let (phase, buffering_percent) = match stats.phase {
+            PlaybackPhase::Buffering(percent) => ("buffering".to_string(), Some(percent as u32)),
+            phase => (phase.to_string(), None),
+        };
+        Self {
+            phase,
+            buffering_percent,
+            station: stats.station,
+            codec: stats.codec,
+            sample_rate: stats.sample_rate,
+            channels: stats.layout.channels as u32,
+            bytes_received: stats.bytes_received,
+            throughput_kbps: stats.throughput_kbps,
+            underruns: stats.underruns,
+            decode_errors: stats.decode_errors,
+            buffer_fill_percent: stats.buffer_fill as u32,
+            channel_layout: stats.layout.to_string(),
+        }
+    }
 }
@@ -0,0 +1,303 @@
+//! `/listen/<station>` HTTP relay: re-serves stations to other devices on
+//! the LAN (phones, smart speakers, VLC) as Icecast-style streams.
+//!
+//! Every distinct upstream gets one connection, read on its own thread and
+//! broadcast to all of its listeners; it is closed once the last listener
+//! leaves. ICY metadata is stripped from the upstream and re-inserted for
+//! listeners that send `Icy-MetaData: 1`. With `?eq=true` the stream is
+//! decoded, run through the equalizer and re-encoded as 16-bit WAV; with
+//! `?preset=<name>` it gets an equalizer of its own running that preset,
+//! whatever the player's EQ is doing.
+//! The upstream side lives in [`crate::rebroadcast`].
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use actix_web::{
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    web::{self, Bytes},
+    HttpRequest, HttpResponse,
+};
+use anyhow::{anyhow, Context as _, Error};
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tunein_cli::provider::{radiobrowser::Radiobrowser, tunein::Tunein, Provider};
+
+use crate::eq_presets::PresetStore;
+use crate::equalizer::{EqPreset, Equalizer};
+use crate::player::Player;
+use crate::rebroadcast::{IcyInserter, Upstream};
+
+/// Bytes of audio between two metadata blocks sent to listeners.
+const METAINT: usize = 16_000;
+
+/// Chunks a listener may fall behind before it starts skipping audio.
+const LISTENER_BACKLOG: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ChannelKey {
+    url: String,
+    eq: bool,
+    /// Lowercased name of the channel's own EQ preset.
+    preset: Option<String>,
+}
+
+/// One upstream connection shared by all of its listeners.
+struct Channel {
+    tx: broadcast::Sender<Bytes>,
+    /// Latest `StreamTitle` seen on the upstream.
+    title: Arc<Mutex<String>>,
+    content_type: String,
+    headers: Vec<(String, String)>,
+    /// Sent to every listener before the live audio: the WAV header of an
+    /// equalized channel.
+    preamble: Option<Bytes>,
+}
+
+type Channels = Arc<Mutex<HashMap<ChannelKey, Arc<Channel>>>>;
+
+pub struct Relay {
+    player: Player,
+    channels: Channels,
+    /// Held while a channel's upstream connects, so listeners joining
+    /// meanwhile wait for that connection instead of opening their own.
+    connecting: Mutex<HashMap<ChannelKey, Arc<Mutex<()>>>>,
+}
+
+#[derive(Deserialize)]
+pub struct ListenQuery {
+    provider: Option<String>,
+    /// Apply the equalizer, re-encoding the stream as WAV.
+    #[serde(default)]
+    eq: bool,
+    /// Equalize with this built-in or saved preset instead, independently
+    /// of the player's EQ.
+    preset: Option<String>,
+}
+
+impl Relay {
+    /// `player` provides the station on air for a bare `/listen`.
+    pub fn new(player: Player) -> Self {
+        Self {
+            player,
+            channels: Arc::default(),
+            connecting: Mutex::default(),
+        }
+    }
+
+    /// Join the channel for `key`, connecting to the upstream if nobody is
+    /// listening to it yet. Blocks on the network; listeners arriving while
+    /// it connects wait for that connection.
+    fn subscribe(
+        &self,
+        key: ChannelKey,
+        eq: Option<Arc<Equalizer>>,
+    ) -> Result<(Arc<Channel>, broadcast::Receiver<Bytes>), Error> {
+        if let Some(joined) = self.join(&key) {
+            return Ok(joined);
+        }
+        let gate = self
+            .connecting
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let opened = {
+            let _connecting = gate.lock().unwrap();
+            match self.join(&key) {
+                Some(joined) => Ok(joined),
+                None => open_channel(&key, eq, self.channels.clone()).map(|(channel, rx)| {
+                    self.channels
+                        .lock()
+                        .unwrap()
+                        .insert(key.clone(), channel.clone());
+                    (channel, rx)
+                }),
+            }
+        };
+        // The last one through lets go of the gate.
+        let mut connecting = self.connecting.lock().unwrap();
+        if Arc::strong_count(&gate) == 2 {
+            connecting.remove(&key);
+        }
+        opened
+    }
+
+    /// Subscribe to the open channel for `key`, if there is one.
+    fn join(&self, key: &ChannelKey) -> Option<(Arc<Channel>, broadcast::Receiver<Bytes>)> {
+        let channels = self.channels.lock().unwrap();
+        let channel = channels.get(key)?;
+        Some((channel.clone(), channel.tx.subscribe()))
+    }
+}
+
+/// `GET /listen` (the station on air) and `GET /listen/{station}` (a
+/// station id or name, resolved with `?provider=`).
+pub async fn listen(
+    relay: web::Data<Relay>,
+    station: Option<web::Path<String>>,
+    query: web::Query<ListenQuery>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let url = match station {
+        Some(station) => resolve_stream_url(&station, query.provider.as_deref()).await,
+        None => relay
+            .player
+            .station()
+            .map(|station| station.stream_url)
+            .ok_or_else(|| anyhow!("nothing is playing; use /listen/<station>")),
+    };
+    let url = match url {
+        Ok(url) => url,
+        Err(err) => return HttpResponse::NotFound().body(err.to_string()),
+    };
+
+    let eq = match &query.preset {
+        Some(name) => match preset_equalizer(name) {
+            Ok(eq) => Some(eq),
+            Err(err) => return HttpResponse::NotFound().body(err.to_string()),
+        },
+        None => query.eq.then(Equalizer::shared),
+    };
+    let key = ChannelKey {
+        url,
+        eq: query.eq && query.preset.is_none(),
+        preset: query.preset.as_ref().map(|name| name.to_lowercase()),
+    };
+    let joined = {
+        let relay = relay.clone();
+        web::block(move || relay.subscribe(key, eq)).await
+    };
+    let (channel, rx) = match joined {
+        Ok(Ok(joined)) => joined,
+        Ok(Err(err)) => return HttpResponse::BadGateway().body(format!("{:#}", err)),
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+
+    let wants_metadata = req
+        .headers()
+        .get("icy-metadata")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim() == "1");
+
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header((CONTENT_TYPE, channel.content_type.as_str()))
+        .insert_header((CACHE_CONTROL, "no-cache, no-store"));
+    for (name, value) in &channel.headers {
+        response.insert_header((name.as_str(), value.as_str()));
+    }
+    if wants_metadata {
+        response.insert_header(("icy-metaint", METAINT.to_string()));
+    }
+
+    let live = stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(chunk) => return Some((chunk, rx)),
+                // A slow listener skips what it missed rather than
+                // holding everyone else back.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    let mut inserter = wants_metadata.then(|| IcyInserter::new(METAINT));
+    let title = channel.title.clone();
+    let body = stream::iter(channel.preamble.clone())
+        .chain(live)
+        .map(move |chunk| {
+            let chunk = match &mut inserter {
+                Some(inserter) => Bytes::from(inserter.insert(&chunk, &title.lock().unwrap())),
+                None => chunk,
+            };
+            Ok::<_, io::Error>(chunk)
+        });
+    response.streaming(body)
+}
+
+/// An equalizer of its own running the built-in or saved preset `name`.
+fn preset_equalizer(name: &str) -> Result<Arc<Equalizer>, Error> {
+    let preset = PresetStore::load()?
+        .find(name)
+        .ok_or_else(|| anyhow!("no EQ preset named \"{}\"", name))?;
+    Ok(Arc::new(Equalizer::with_preset(&EqPreset {
+        enabled: true,
+        bands: preset.bands,
+        bass: 0,
+        treble: 0,
+    })))
+}
+
+async fn resolve_stream_url(station: &str, provider: Option<&str>) -> Result<String, Error> {
+    let client: Box<dyn Provider + Send + Sync> = match provider {
+        Some("tunein") | None => Box::new(Tunein::new()),
+        Some("radiobrowser") => Box::new(Radiobrowser::new().await),
+        Some(other) => return Err(anyhow!("unsupported provider '{}'", other)),
+    };
+    let station = client
+        .get_station(station.to_string())
+        .await?
+        .ok_or_else(|| anyhow!("no station found for \"{}\"", station))?;
+    Ok(station.stream_url)
+}
+
+/// Connect to the upstream for `key`, equalized with `eq` if given, and
+/// start broadcasting it. The first listener is subscribed before the
+/// reader starts, so the channel can't close before anyone joined.
+fn open_channel(
+    key: &ChannelKey,
+    eq: Option<Arc<Equalizer>>,
+    channels: Channels,
+) -> Result<(Arc<Channel>, broadcast::Receiver<Bytes>), Error> {
+    let upstream = Upstream::open(&key.url, eq)?;
+    let (tx, rx) = broadcast::channel(LISTENER_BACKLOG);
+    let channel = Arc::new(Channel {
+        tx,
+        title: upstream.title,
+        content_type: upstream.content_type,
+        headers: upstream.headers,
+        preamble: upstream.preamble.map(Bytes::from),
+    });
+
+    let chunks = upstream.chunks;
+    let broadcaster = channel.clone();
+    let key = key.clone();
+    thread::Builder::new()
+        .name("tunein-relay".into())
+        .spawn(move || {
+            for chunk in chunks {
+                // Sending only fails once every listener is gone; check
+                // again under the lock so nobody joins a closing channel.
+                if broadcaster.tx.send(Bytes::from(chunk)).is_err() {
+                    let mut channels = channels.lock().unwrap();
+                    if broadcaster.tx.receiver_count() == 0 {
+                        remove_channel(&mut channels, &key, &broadcaster);
+                        return;
+                    }
+                }
+            }
+            remove_channel(&mut channels.lock().unwrap(), &key, &broadcaster);
+        })
+        .context("failed to spawn relay thread")?;
+
+    Ok((channel, rx))
+}
+
+/// Forget `channel`, unless it has already been replaced by a newer one.
+fn remove_channel(
+    channels: &mut HashMap<ChannelKey, Arc<Channel>>,
+    key: &ChannelKey,
+    channel: &Arc<Channel>,
+) {
+    if channels
+        .get(key)
+        .is_some_and(|current| Arc::ptr_eq(current, channel))
+    {
+        channels.remove(key);
+    }
+}
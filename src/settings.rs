@@ -1,11 +1,15 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Error};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
-/// Number of equalizer bands (matches `rockbox_dsp::EQ_NUM_BANDS`).
+use crate::downmix::Multichannel;
+use crate::dynamics::DynamicsParams;
+
+/// Number of equalizer bands (matches Rockbox's `EQ_NUM_BANDS`).
 pub const EQ_BANDS: usize = 10;
 
 /// One EQ band, in the exact on-disk format Rockbox's `[[eq_band_settings]]`
@@ -43,6 +47,88 @@ pub struct Settings {
     /// Treble shelf cutoff in Hz. `0` = Rockbox default 3500 Hz.
     #[serde(default)]
     pub treble_cutoff: i32,
+    /// Left/right balance in percent (matches Rockbox `balance`): −100 =
+    /// left only, +100 = right only.
+    #[serde(default)]
+    pub balance: i32,
+    /// Stereo width in percent (matches Rockbox `stereo_width`): 0 = mono,
+    /// 100 = unchanged, up to 200.
+    #[serde(default = "default_stereo_width")]
+    pub stereo_width: i32,
+    /// Fold stereo down to mono, for one-earbud listening.
+    #[serde(default)]
+    pub mono: bool,
+    /// Crossfade length when switching stations, in milliseconds. `0`
+    /// switches immediately.
+    #[serde(default = "default_crossfade_ms")]
+    pub crossfade_ms: u64,
+    /// Size of the read-ahead buffer between the network and the decoder,
+    /// in KiB.
+    #[serde(default = "default_buffer_kb")]
+    pub buffer_kb: usize,
+    /// KiB buffered before a station starts playing, and again after the
+    /// read-ahead buffer ran dry. Capped at `buffer_kb`.
+    #[serde(default = "default_prebuffer_kb")]
+    pub prebuffer_kb: usize,
+    /// Resample to the output's native rate with a windowed-sinc filter
+    /// instead of rodio's linear interpolation.
+    #[serde(default)]
+    pub resample: bool,
+    /// `downmix` folds 5.1/7.1 streams to stereo ahead of the equalizer;
+    /// `passthrough` keeps every channel when the output device has enough.
+    #[serde(default)]
+    pub multichannel: Multichannel,
+    /// Normalise every station toward `loudness_target`.
+    #[serde(default)]
+    pub loudness_enabled: bool,
+    /// Loudness normalisation target in LUFS.
+    #[serde(default = "default_loudness_target")]
+    pub loudness_target: f32,
+    /// Night mode: compress and limit the signal after the equalizer.
+    #[serde(default)]
+    pub night_mode: bool,
+    /// Compressor threshold in dBFS.
+    #[serde(default = "default_compressor_threshold")]
+    pub compressor_threshold: f32,
+    /// Compressor ratio, e.g. `4.0` = 4:1.
+    #[serde(default = "default_compressor_ratio")]
+    pub compressor_ratio: f32,
+    /// Compressor attack in milliseconds.
+    #[serde(default = "default_compressor_attack_ms")]
+    pub compressor_attack_ms: f32,
+    /// Compressor release in milliseconds.
+    #[serde(default = "default_compressor_release_ms")]
+    pub compressor_release_ms: f32,
+    /// Brick-wall limiter ceiling in dBFS.
+    #[serde(default = "default_limiter_ceiling")]
+    pub limiter_ceiling: f32,
+    /// Name of the audio output device to play on. Unset = system default.
+    #[serde(default)]
+    pub output_device: Option<String>,
+    /// Vertical scale of the `play` scope, `1.0` = full scale fills it.
+    #[serde(default = "default_scope_scale")]
+    pub scope_scale: f32,
+    /// Draw the scope as dots instead of lines (`s` in `play`).
+    #[serde(default)]
+    pub scope_scatter: bool,
+    /// Draw the reference lines (`r` in `play`).
+    #[serde(default)]
+    pub scope_references: bool,
+    /// Draw the header line and the axis labels (`h` in `play`).
+    #[serde(default)]
+    pub scope_show_ui: bool,
+    /// Draw lines with braille dots, finer than whole-cell dots.
+    #[serde(default = "default_scope_braille")]
+    pub scope_braille: bool,
+    /// Samples per channel the scope shows, and the spectrum size.
+    #[serde(default = "default_scope_samples")]
+    pub scope_samples: u32,
+    /// Sample rate the spectrum modes assume until the stream reports its own.
+    #[serde(default = "default_scope_sample_rate")]
+    pub scope_sample_rate: u32,
+    /// Channel colours, by name (`red`, `lightblue`, …), index or `#rrggbb`.
+    #[serde(default = "default_scope_palette")]
+    pub scope_palette: Vec<String>,
 }
 
 impl Default for Settings {
@@ -54,10 +140,98 @@ impl Default for Settings {
             treble: 0,
             bass_cutoff: 0,
             treble_cutoff: 0,
+            balance: 0,
+            stereo_width: default_stereo_width(),
+            mono: false,
+            crossfade_ms: default_crossfade_ms(),
+            buffer_kb: default_buffer_kb(),
+            prebuffer_kb: default_prebuffer_kb(),
+            resample: false,
+            multichannel: Multichannel::Downmix,
+            loudness_enabled: false,
+            loudness_target: default_loudness_target(),
+            night_mode: false,
+            compressor_threshold: default_compressor_threshold(),
+            compressor_ratio: default_compressor_ratio(),
+            compressor_attack_ms: default_compressor_attack_ms(),
+            compressor_release_ms: default_compressor_release_ms(),
+            limiter_ceiling: default_limiter_ceiling(),
+            output_device: None,
+            scope_scale: default_scope_scale(),
+            scope_scatter: false,
+            scope_references: false,
+            scope_show_ui: false,
+            scope_braille: default_scope_braille(),
+            scope_samples: default_scope_samples(),
+            scope_sample_rate: default_scope_sample_rate(),
+            scope_palette: default_scope_palette(),
         }
     }
 }
 
+fn default_stereo_width() -> i32 {
+    100
+}
+
+fn default_crossfade_ms() -> u64 {
+    2000
+}
+
+fn default_buffer_kb() -> usize {
+    256
+}
+
+fn default_prebuffer_kb() -> usize {
+    32
+}
+
+fn default_loudness_target() -> f32 {
+    -18.0
+}
+
+fn default_compressor_threshold() -> f32 {
+    DynamicsParams::NIGHT_MODE.threshold_db
+}
+
+fn default_compressor_ratio() -> f32 {
+    DynamicsParams::NIGHT_MODE.ratio
+}
+
+fn default_compressor_attack_ms() -> f32 {
+    DynamicsParams::NIGHT_MODE.attack_ms
+}
+
+fn default_compressor_release_ms() -> f32 {
+    DynamicsParams::NIGHT_MODE.release_ms
+}
+
+fn default_limiter_ceiling() -> f32 {
+    DynamicsParams::NIGHT_MODE.ceiling_db
+}
+
+fn default_scope_scale() -> f32 {
+    1.0
+}
+
+fn default_scope_braille() -> bool {
+    true
+}
+
+fn default_scope_samples() -> u32 {
+    1152
+}
+
+fn default_scope_sample_rate() -> u32 {
+    44100
+}
+
+fn default_scope_palette() -> Vec<String> {
+    ["red", "yellow", "green", "magenta"]
+        .iter()
+        .map(|color| color.to_string())
+        .collect()
+}
+
 /// The ISO-octave 10-band flat preset used when a fresh config has no
 /// `[[eq_band_settings]]` section: standard ISO center frequencies,
 /// Q 0.7 across the board, every gain at 0 dB, so the DSP output is
@@ -106,6 +280,10 @@ impl Settings {
         fs::write(&path, serialized).context("failed to write settings file")
     }
 
+    pub fn crossfade(&self) -> Duration {
+        Duration::from_millis(self.crossfade_ms)
+    }
+
     /// Guarantee the EQ always has the full 10 bands so the DSP has
     /// something to pass through and the TUI can render 10 sliders
     /// regardless of prior file state.
@@ -139,6 +317,21 @@ mod tests {
         assert_eq!(back.eq_band_settings, settings.eq_band_settings);
         assert_eq!(back.bass, 6);
         assert_eq!(back.treble, -3);
+        assert_eq!(back.crossfade_ms, 2000);
+        assert_eq!((back.buffer_kb, back.prebuffer_kb), (256, 32));
+    }
+
+    #[test]
+    fn scope_options_default_when_missing() {
+        let settings: Settings = toml::from_str("scope_scatter = true\n").unwrap();
+        assert!(settings.scope_scatter);
+        assert!(!settings.scope_show_ui);
+        assert!(settings.scope_braille);
+        assert_eq!(settings.scope_samples, 1152);
+        assert_eq!(
+            settings.scope_palette,
+            ["red", "yellow", "green", "magenta"]
+        );
     }
 
     #[test]
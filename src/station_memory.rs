@@ -0,0 +1,173 @@
+//! Per-station playback settings: the last volume a station was played at
+//! and, when the user saved one, its own equalizer preset. Entries are keyed
+//! by provider and station id, like [`crate::favorites::FavoriteStation`].
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::equalizer::EqPreset;
+
+/// What is remembered about one station.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StationSettings {
+    pub id: String,
+    pub provider: String,
+    /// Volume ratio, `1.0` = 100%.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eq: Option<EqPreset>,
+}
+
+/// File-backed store of [`StationSettings`].
+pub struct StationMemory {
+    path: PathBuf,
+    stations: Vec<StationSettings>,
+}
+
+impl StationMemory {
+    /// Load the store from disk, falling back to an empty one when the file
+    /// does not exist or is corrupted.
+    pub fn load() -> Result<Self, Error> {
+        let path = memory_path()?;
+        ensure_parent(&path)?;
+
+        let stations = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<Vec<StationSettings>>(&content) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    eprintln!(
+                        "warning: station settings file corrupted ({}), starting fresh",
+                        err
+                    );
+                    Vec::new()
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                return Err(Error::from(err).context("failed to read station settings file"))
+            }
+        };
+
+        Ok(Self { path, stations })
+    }
+
+    pub fn get(&self, provider: &str, id: &str) -> Option<&StationSettings> {
+        self.stations
+            .iter()
+            .find(|station| station.id == id && station.provider == provider)
+    }
+
+    /// Remember `volume` for a station.
+    pub fn set_volume(&mut self, provider: &str, id: &str, volume: f32) -> Result<(), Error> {
+        let entry = self.entry(provider, id);
+        if entry.volume == Some(volume) {
+            return Ok(());
+        }
+        entry.volume = Some(volume);
+        self.save()
+    }
+
+    /// Save an equalizer preset for a station, or forget it with `None`.
+    pub fn set_eq(&mut self, provider: &str, id: &str, eq: Option<EqPreset>) -> Result<(), Error> {
+        self.entry(provider, id).eq = eq;
+        self.stations
+            .retain(|station| station.volume.is_some() || station.eq.is_some());
+        self.save()
+    }
+
+    fn entry(&mut self, provider: &str, id: &str) -> &mut StationSettings {
+        let index = match self
+            .stations
+            .iter()
+            .position(|station| station.id == id && station.provider == provider)
+        {
+            Some(index) => index,
+            None => {
+                self.stations.push(StationSettings {
+                    id: id.to_string(),
+                    provider: provider.to_string(),
+                    volume: None,
+                    eq: None,
+                });
+                self.stations.len() - 1
+            }
+        };
+        &mut self.stations[index]
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let serialized = serde_json::to_string_pretty(&self.stations)
+            .context("failed to serialize station settings")?;
+        fs::write(&self.path, serialized).context("failed to write station settings file")
+    }
+}
+
+fn memory_path() -> Result<PathBuf, Error> {
+    let dirs = ProjectDirs::from("io", "tunein-cli", "tunein-cli")
+        .ok_or_else(|| Error::msg("unable to determine configuration directory"))?;
+    Ok(dirs.config_dir().join("stations.json"))
+}
+
+fn ensure_parent(path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("failed to create configuration directory")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_in(dir: &Path) -> StationMemory {
+        StationMemory {
+            path: dir.join("stations.json"),
+            stations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn keys_entries_by_provider_and_id() {
+        let dir = std::env::temp_dir().join(format!("tunein-stations-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut memory = memory_in(&dir);
+
+        memory.set_volume("tunein", "s1", 0.4).unwrap();
+        memory.set_volume("radiobrowser", "s1", 0.8).unwrap();
+        memory.set_volume("tunein", "s1", 0.5).unwrap();
+
+        let content = fs::read_to_string(dir.join("stations.json")).unwrap();
+        let stations: Vec<StationSettings> = serde_json::from_str(&content).unwrap();
+        assert_eq!(stations.len(), 2);
+        assert_eq!(memory.get("tunein", "s1").unwrap().volume, Some(0.5));
+        assert_eq!(memory.get("radiobrowser", "s1").unwrap().volume, Some(0.8));
+        assert!(memory.get("tunein", "s2").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn forgetting_the_last_setting_drops_the_entry() {
+        let dir = std::env::temp_dir().join(format!("tunein-stations-eq-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut memory = memory_in(&dir);
+
+        let preset = EqPreset {
+            enabled: true,
+            bands: crate::settings::default_eq_band_settings(),
+            bass: 3,
+            treble: 0,
+        };
+        memory.set_eq("tunein", "s1", Some(preset.clone())).unwrap();
+        assert_eq!(memory.get("tunein", "s1").unwrap().eq, Some(preset));
+        memory.set_eq("tunein", "s1", None).unwrap();
+        assert!(memory.get("tunein", "s1").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Stream statistics: what the network, the decoder and the output are
+//! doing, so a stutter can be pinned on the connection or on the station.
+//!
+//! The connection, the read-ahead buffer and the decoder bump lock-free
+//! [`StreamCounters`]; the player folds them, together with the mixer's
+//! underrun count, into a [`PlaybackStats`] snapshot about once a second.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::downmix::ChannelLayout;
+
+/// Counters for one connected stream.
+#[derive(Debug, Default)]
+pub struct StreamCounters {
+    bytes_received: AtomicU64,
+    decode_errors: AtomicU64,
+    sample_rate: AtomicU32,
+    /// Decoded channel count and speaker mask, and the channels played.
+    channels: AtomicU16,
+    channel_mask: AtomicU32,
+    output_channels: AtomicU16,
+    /// Read-ahead buffer: bytes held, its size and prebuffer threshold.
+    buffered: AtomicUsize,
+    buffer_size: AtomicUsize,
+    prebuffer: AtomicUsize,
+    refilling: AtomicBool,
+}
+
+impl StreamCounters {
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    /// Sample rate and channel layout of the last decoded packet.
+    pub fn format(&self) -> (u32, ChannelLayout) {
+        (
+            self.sample_rate.load(Ordering::Relaxed),
+            ChannelLayout {
+                mask: self.channel_mask.load(Ordering::Relaxed),
+                channels: self.channels.load(Ordering::Relaxed),
+                output: self.output_channels.load(Ordering::Relaxed),
+            },
+        )
+    }
+
+    /// Read-ahead buffer fill, in percent of its size.
+    pub fn buffer_fill(&self) -> u8 {
+        percent(
+            self.buffered.load(Ordering::Relaxed),
+            self.buffer_size.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Whether the read-ahead buffer ran dry and playback waits for it to
+    /// refill.
+    pub fn refilling(&self) -> bool {
+        self.refilling.load(Ordering::Relaxed)
+    }
+
+    /// Progress of a refill towards the prebuffer threshold, in percent.
+    pub fn refill_progress(&self) -> u8 {
+        percent(
+            self.buffered.load(Ordering::Relaxed),
+            self.prebuffer.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn add_decode_errors(&self, count: u64) {
+        self.decode_errors.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn set_format(&self, sample_rate: u32, layout: ChannelLayout) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.channels.store(layout.channels, Ordering::Relaxed);
+        self.channel_mask.store(layout.mask, Ordering::Relaxed);
+        self.output_channels.store(layout.output, Ordering::Relaxed);
+    }
+
+    pub fn set_buffer_limits(&self, size: usize, prebuffer: usize) {
+        self.buffer_size.store(size, Ordering::Relaxed);
+        self.prebuffer.store(prebuffer, Ordering::Relaxed);
+    }
+
+    pub fn set_buffered(&self, bytes: usize, refilling: bool) {
+        self.buffered.store(bytes, Ordering::Relaxed);
+        self.refilling.store(refilling, Ordering::Relaxed);
+    }
+}
+
+/// `part` of `whole` in percent, capped at 100; an empty whole counts as
+/// full.
+fn percent(part: usize, whole: usize) -> u8 {
+    match whole {
+        0 => 100,
+        _ => (part.min(whole) * 100 / whole) as u8,
+    }
+}
+
+/// Counts every byte read from the network into [`StreamCounters`].
+pub struct CountingReader<R> {
+    inner: R,
+    counters: Arc<StreamCounters>,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R, counters: Arc<StreamCounters>) -> Self {
+        Self { inner, counters }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.counters
+            .bytes_received
+            .fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// Where the player is with the current station.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaybackPhase {
+    #[default]
+    Idle,
+    Connecting,
+    /// Pre-buffering the connected stream, in percent.
+    Buffering(u8),
+    Playing,
+    Paused,
+}
+
+impl fmt::Display for PlaybackPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaybackPhase::Idle => write!(f, "idle"),
+            PlaybackPhase::Connecting => write!(f, "connecting"),
+            PlaybackPhase::Buffering(percent) => write!(f, "buffering {}%", percent),
+            PlaybackPhase::Playing => write!(f, "playing"),
+            PlaybackPhase::Paused => write!(f, "paused"),
+        }
+    }
+}
+
+/// Snapshot of the current station's stream statistics.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaybackStats {
+    pub phase: PlaybackPhase,
+    /// Name of the station being played or connected to.
+    pub station: Option<String>,
+    /// Short name of the decoded codec, e.g. `mp3` or `aac`.
+    pub codec: String,
+    /// Decoded sample rate in Hz.
+    pub sample_rate: u32,
+    /// Decoded channel layout, and whether it is downmixed.
+    pub layout: ChannelLayout,
+    pub bytes_received: u64,
+    /// Network throughput over the last second, in kbit/s.
+    pub throughput_kbps: u32,
+    /// Read-ahead buffer fill, in percent.
+    pub buffer_fill: u8,
+    /// Times the output ran dry waiting for the stream.
+    pub underruns: u64,
+    /// Malformed packets skipped by the decoder.
+    pub decode_errors: u64,
+}
+
+impl PlaybackStats {
+    /// `(label, value)` rows for the stats panels.
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let format = match (self.codec.is_empty(), self.sample_rate) {
+            (true, _) | (_, 0) => "-".to_string(),
+            (false, rate) => format!(
+                "{} • {:.1} kHz • {}",
+                self.codec,
+                rate as f32 / 1000.0,
+                self.layout
+            ),
+        };
+        vec![
+            ("State", self.phase.to_string()),
+            (
+                "Station",
+                self.station.clone().unwrap_or_else(|| "-".into()),
+            ),
+            ("Format", format),
+            ("Received", format_bytes(self.bytes_received)),
+            ("Throughput", format!("{} kbit/s", self.throughput_kbps)),
+            ("Buffer", format!("{}%", self.buffer_fill)),
+            ("Underruns", self.underruns.to_string()),
+            ("Decode errors", self.decode_errors.to_string()),
+        ]
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const KIB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes < KIB {
+        format!("{} B", bytes)
+    } else if bytes < KIB * KIB {
+        format!("{:.1} KiB", bytes / KIB)
+    } else {
+        format!("{:.1} MiB", bytes / (KIB * KIB))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counting_reader_counts_every_byte() {
+        let counters = Arc::new(StreamCounters::default());
+        let mut reader = CountingReader::new(&[7u8; 10_000][..], counters.clone());
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out.len(), 10_000);
+        assert_eq!(counters.bytes_received(), 10_000);
+    }
+
+    #[test]
+    fn rows_describe_the_stream() {
+        let stats = PlaybackStats {
+            phase: PlaybackPhase::Buffering(40),
+            codec: "aac".into(),
+            sample_rate: 44100,
+            layout: ChannelLayout::new(0, 6, 2),
+            bytes_received: 3 * 1024 * 1024,
+            ..Default::default()
+        };
+        let rows = stats.rows();
+        assert_eq!(rows[0].1, "buffering 40%");
+        assert_eq!(rows[2].1, "aac • 44.1 kHz • 5.1 → stereo");
+        assert_eq!(rows[3].1, "3.0 MiB");
+    }
+}
@@ -0,0 +1,81 @@
+//! `i` stats panel: a small box in the top-right corner showing the
+//! current stream's [`PlaybackStats`]. Shared by the `play` TUI and
+//! interactive mode. Unlike the other popups it does not capture the
+//! keyboard, so playback can be driven while watching it.
+
+use crate::stats::PlaybackStats;
+use crate::theme;
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+#[derive(Default)]
+pub struct StatsPanel {
+    pub visible: bool,
+}
+
+impl StatsPanel {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Draw `stats` in the top-right corner of `frame`.
+    pub fn render(&self, frame: &mut Frame, stats: &PlaybackStats) {
+        if !self.visible {
+            return;
+        }
+
+        let rows = stats.rows();
+        let label_width = rows.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+        let value_width = rows
+            .iter()
+            .map(|(_, v)| v.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let outer = frame.size();
+        let width = ((label_width + value_width + 5) as u16)
+            .max(30)
+            .min(outer.width);
+        let height = (rows.len() as u16 + 2).min(outer.height);
+        let area = Rect {
+            x: outer.x + outer.width - width,
+            y: outer.y,
+            width,
+            height,
+        };
+        frame.render_widget(Clear, area);
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(" Stream Stats ")
+            .title_alignment(Alignment::Center)
+            .border_style(Style::default().fg(theme::PRIMARY));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let lines: Vec<Line> = rows
+            .into_iter()
+            .map(|(label, value)| {
+                let highlight = match label {
+                    "Underruns" | "Decode errors" if value != "0" => theme::ERROR,
+                    _ => Color::Reset,
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!(" {:<label_width$}  ", label),
+                        Style::default()
+                            .fg(theme::ACCENT)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(value, Style::default().fg(highlight)),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+}
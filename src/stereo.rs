@@ -0,0 +1,205 @@
+//! Stereo image controls: left/right balance, a stereo-width control for
+//! narrow AM-style streams and a mono fold-down for one-earbud listening.
+//!
+//! The settings live in the global [`Stereo`], seeded from the settings
+//! file and edited from the equalizer popup. Each stream owns a
+//! [`StereoProcessor`], run right after the equalizer wherever the
+//! equalizer runs, so the vectorscope shows the result.
+
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::OnceLock;
+
+use crate::settings::Settings;
+
+/// Balance range in percent: −100 = left only, +100 = right only.
+pub const BALANCE_RANGE: (i32, i32) = (-100, 100);
+/// Stereo width range in percent: 0 = mono, 100 = unchanged, 200 = twice
+/// the side signal.
+pub const WIDTH_RANGE: (i32, i32) = (0, 200);
+
+/// Process-wide balance, width and mono switch, seeded from the settings
+/// file.
+pub struct Stereo {
+    balance: AtomicI32,
+    width: AtomicI32,
+    mono: AtomicBool,
+}
+
+static GLOBAL: OnceLock<Stereo> = OnceLock::new();
+
+impl Stereo {
+    pub fn global() -> &'static Stereo {
+        GLOBAL.get_or_init(|| {
+            let settings = Settings::load();
+            Stereo {
+                balance: AtomicI32::new(settings.balance.clamp(BALANCE_RANGE.0, BALANCE_RANGE.1)),
+                width: AtomicI32::new(settings.stereo_width.clamp(WIDTH_RANGE.0, WIDTH_RANGE.1)),
+                mono: AtomicBool::new(settings.mono),
+            }
+        })
+    }
+
+    /// Balance in percent, negative toward the left.
+    pub fn balance(&self) -> i32 {
+        self.balance.load(Ordering::Relaxed)
+    }
+
+    /// Stereo width in percent, `100` = unchanged.
+    pub fn width(&self) -> i32 {
+        self.width.load(Ordering::Relaxed)
+    }
+
+    pub fn is_mono(&self) -> bool {
+        self.mono.load(Ordering::Relaxed)
+    }
+
+    /// Whether anything would change the signal.
+    pub fn is_active(&self) -> bool {
+        self.balance() != 0 || self.width() != 100 || self.is_mono()
+    }
+
+    pub fn adjust_balance(&self, delta: i32) {
+        let balance = (self.balance() + delta).clamp(BALANCE_RANGE.0, BALANCE_RANGE.1);
+        self.balance.store(balance, Ordering::Relaxed);
+    }
+
+    pub fn adjust_width(&self, delta: i32) {
+        let width = (self.width() + delta).clamp(WIDTH_RANGE.0, WIDTH_RANGE.1);
+        self.width.store(width, Ordering::Relaxed);
+    }
+
+    pub fn set_mono(&self, mono: bool) {
+        self.mono.store(mono, Ordering::Relaxed);
+    }
+
+    /// Short description for status lines, e.g. `mono · R20`; empty when
+    /// the image is untouched.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.is_mono() {
+            parts.push("mono".to_string());
+        } else if self.width() != 100 {
+            parts.push(format!("width {}%", self.width()));
+        }
+        if self.balance() != 0 {
+            parts.push(fmt_balance(self.balance()));
+        }
+        parts.join(" · ")
+    }
+
+    pub fn save(&self) {
+        let mut settings = Settings::load();
+        settings.balance = self.balance();
+        settings.stereo_width = self.width();
+        settings.mono = self.is_mono();
+        if let Err(err) = settings.save() {
+            eprintln!("warning: failed to save settings: {}", err);
+        }
+    }
+
+    /// The 2×2 matrix `[[ll, lr], [rl, rr]]` the current settings apply.
+    fn matrix(&self) -> [[f32; 2]; 2] {
+        let width = if self.is_mono() {
+            0.0
+        } else {
+            self.width() as f32 / 100.0
+        };
+        let balance = self.balance() as f32 / 100.0;
+        stereo_matrix(balance, width)
+    }
+}
+
+/// `L' = gl·(mid + w·side)`, `R' = gr·(mid − w·side)`, with the balance
+/// only ever attenuating the opposite side so centred material keeps its
+/// level.
+fn stereo_matrix(balance: f32, width: f32) -> [[f32; 2]; 2] {
+    let left = (1.0 - balance).min(1.0);
+    let right = (1.0 + balance).min(1.0);
+    let (direct, cross) = ((1.0 + width) / 2.0, (1.0 - width) / 2.0);
+    [
+        [left * direct, left * cross],
+        [right * cross, right * direct],
+    ]
+}
+
+/// Balance as `C`, `L40` or `R20`.
+pub fn fmt_balance(balance: i32) -> String {
+    match balance {
+        0 => "C".to_string(),
+        b if b < 0 => format!("L{}", -b),
+        b => format!("R{}", b),
+    }
+}
+
+/// Per-stream stereo processor. Changes are ramped over one chunk so
+/// moving a slider doesn't click.
+pub struct StereoProcessor {
+    matrix: [[f32; 2]; 2],
+}
+
+impl StereoProcessor {
+    pub fn new() -> Self {
+        Self {
+            matrix: stereo_matrix(0.0, 1.0),
+        }
+    }
+
+    /// Apply balance, width and mono to interleaved stereo samples in
+    /// place. Anything but two channels passes through untouched.
+    pub fn process(&mut self, buffer: &mut [f32], channels: u16) {
+        if channels != 2 {
+            return;
+        }
+        let stereo = Stereo::global();
+        let target = stereo.matrix();
+        if !stereo.is_active() && self.matrix == target {
+            return;
+        }
+        self.apply(buffer, target);
+    }
+
+    fn apply(&mut self, buffer: &mut [f32], target: [[f32; 2]; 2]) {
+        let from = self.matrix;
+        let frames = (buffer.len() / 2).max(1) as f32;
+        for (i, frame) in buffer.chunks_exact_mut(2).enumerate() {
+            let t = (i + 1) as f32 / frames;
+            let m =
+                |row: usize, col: usize| from[row][col] + (target[row][col] - from[row][col]) * t;
+            let (l, r) = (frame[0], frame[1]);
+            frame[0] = m(0, 0) * l + m(0, 1) * r;
+            frame[1] = m(1, 0) * l + m(1, 1) * r;
+        }
+        self.matrix = target;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processed(balance: f32, width: f32, frame: [f32; 2]) -> [f32; 2] {
+        let mut processor = StereoProcessor {
+            matrix: stereo_matrix(balance, width),
+        };
+        let mut buffer = frame.to_vec();
+        processor.apply(&mut buffer, stereo_matrix(balance, width));
+        [buffer[0], buffer[1]]
+    }
+
+    #[test]
+    fn width_scales_the_side_signal() {
+        // Unchanged at 100%.
+        assert_eq!(processed(0.0, 1.0, [0.8, 0.2]), [0.8, 0.2]);
+        // Mono: both sides get the mid.
+        assert_eq!(processed(0.0, 0.0, [0.8, 0.2]), [0.5, 0.5]);
+        // Wider: twice the difference, same mid.
+        let [l, r] = processed(0.0, 2.0, [0.8, 0.2]);
+        assert!((l - 1.1).abs() < 1e-6 && (r + 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn balance_only_attenuates_the_opposite_side() {
+        assert_eq!(processed(0.5, 1.0, [0.8, 0.8]), [0.4, 0.8]);
+        assert_eq!(processed(-1.0, 1.0, [0.8, 0.8]), [0.8, 0.0]);
+    }
+}
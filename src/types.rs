@@ -5,7 +5,7 @@ use tunein::types::{SearchResult, StationLinkDetails};
 
 use crate::extract::extract_stream_url;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Station {
     pub id: String,
     pub name: String,
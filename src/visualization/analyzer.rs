@@ -0,0 +1,362 @@
+//! Classic bar spectrum analyzer: the spectrum summed into octave,
+//! third-octave or evenly log-spaced bands, drawn as bars of block
+//! characters. Bars jump up at once and fall back under gravity; a peak cap
+//! above each bar lingers briefly before falling too.
+//!
+//! It scales down to whatever cells it gets: bands are merged when there
+//! are more of them than columns, which lets it sit next to the station
+//! info when the terminal is too short for a visualization below it.
+
+use std::collections::VecDeque;
+
+use crossterm::event::{Event, KeyCode};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Axis, Paragraph},
+    Frame,
+};
+
+use crate::input::Matrix;
+
+use super::{
+    spectroscope::{hann_window, magnitude},
+    DataSet, Dimension, DisplayMode, GraphConfig,
+};
+
+use rustfft::{num_complex::Complex, FftPlanner};
+
+/// Samples per channel the spectrum is taken over, longer than a frame so
+/// the low third-octave bands still span a few FFT bins.
+const FFT_LEN: usize = 4096;
+/// Noise bandwidth of the Hann window in bins: a tone's power is spread
+/// over this many.
+const HANN_ENBW: f64 = 1.5;
+/// Bottom of the bar scale, in dBFS.
+const FLOOR_DB: f64 = -72.0;
+/// Gravity pulling bars and caps down, in full heights per second².
+const GRAVITY: f64 = 6.0;
+/// How long a peak cap stays before it starts falling.
+const CAP_HOLD_SECS: f64 = 0.4;
+/// Band count range and step of [`Spacing::Log`].
+const BANDS: (usize, usize) = (4, 96);
+const BANDS_STEP: usize = 4;
+/// Fewest rows for frequency labels under the bars.
+const LABEL_MIN_ROWS: u16 = 4;
+/// Eighth blocks, from one eighth to full.
+const EIGHTHS: [&str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+/// How the 20 Hz – 20 kHz range is split into bands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spacing {
+    /// Ten octave bands, 31.5 Hz to 16 kHz.
+    Octave,
+    /// Thirty-one third-octave bands, 20 Hz to 20 kHz.
+    ThirdOctave,
+    /// The given number of evenly log-spaced bands.
+    Log(usize),
+}
+
+impl Spacing {
+    /// `(low, high)` edges of every band, in Hz.
+    fn bands(self) -> Vec<(f64, f64)> {
+        let around = |centre: f64, per_octave: f64| {
+            let half = 2f64.powf(0.5 / per_octave);
+            (centre / half, centre * half)
+        };
+        match self {
+            Spacing::Octave => (-5..=4)
+                .map(|k| around(1000.0 * 2f64.powi(k), 1.0))
+                .collect(),
+            Spacing::ThirdOctave => (-17..=13)
+                .map(|k| around(1000.0 * 2f64.powf(k as f64 / 3.0), 3.0))
+                .collect(),
+            Spacing::Log(count) => {
+                let (low, high) = (20f64.ln(), 20000f64.ln());
+                let step = (high - low) / count as f64;
+                (0..count)
+                    .map(|band| {
+                        let edge = |n: usize| (low + n as f64 * step).exp();
+                        (edge(band), edge(band + 1))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Spacing::Octave => "octave".into(),
+            Spacing::ThirdOctave => "1/3 octave".into(),
+            Spacing::Log(count) => format!("{} bands", count),
+        }
+    }
+}
+
+/// One bar: its height and its cap, both as fractions of the full scale.
+#[derive(Debug, Default, Clone)]
+struct Bar {
+    level: f64,
+    speed: f64,
+    cap: f64,
+    cap_speed: f64,
+    cap_secs: f64,
+}
+
+impl Bar {
+    /// Jump up to `target` or fall toward it under gravity.
+    fn update(&mut self, target: f64, secs: f64) {
+        if target >= self.level {
+            self.level = target;
+            self.speed = 0.0;
+        } else {
+            self.speed += GRAVITY * secs;
+            self.level = (self.level - self.speed * secs).max(target);
+        }
+
+        if self.level >= self.cap {
+            self.cap = self.level;
+            self.cap_speed = 0.0;
+            self.cap_secs = 0.0;
+        } else {
+            self.cap_secs += secs;
+            if self.cap_secs > CAP_HOLD_SECS {
+                self.cap_speed += GRAVITY * secs;
+                self.cap = (self.cap - self.cap_speed * secs).max(self.level);
+            }
+        }
+    }
+}
+
+pub struct Analyzer {
+    pub spacing: Spacing,
+    /// Band count [`Spacing::Log`] uses.
+    pub band_count: usize,
+    bars: Vec<Bar>,
+    /// The last [`FFT_LEN`] samples of every channel.
+    window: Vec<VecDeque<f64>>,
+    /// Sample rate of the last frame, for the band edges.
+    sampling_rate: u32,
+}
+
+impl Analyzer {
+    fn set_spacing(&mut self, spacing: Spacing) {
+        self.spacing = spacing;
+        self.bars.clear();
+    }
+
+    /// Slide the new samples into the window and take the energy of every
+    /// band in dBFS, all channels together.
+    fn levels_db(&mut self, data: &Matrix<f64>) -> Vec<f64> {
+        if self.window.len() != data.len() {
+            self.window = vec![VecDeque::from(vec![0.0; FFT_LEN]); data.len()];
+        }
+        let len = FFT_LEN;
+        let fft = FftPlanner::<f64>::new().plan_fft_forward(len);
+        let mut power = vec![0.0; len / 2 + 1];
+        for (window, chan) in self.window.iter_mut().zip(data) {
+            window.extend(chan);
+            window.drain(..window.len() - len);
+            let chunk: Vec<f64> = window.iter().copied().collect();
+            let mut tmp: Vec<Complex<f64>> = hann_window(&chunk)
+                .into_iter()
+                .map(|x| Complex { re: x, im: 0.0 })
+                .collect();
+            fft.process(tmp.as_mut_slice());
+            for (bin, x) in power.iter_mut().zip(&tmp) {
+                // A full-scale sine reads 0 dBFS through the Hann window.
+                let level = magnitude(*x) * 4.0 / len as f64;
+                *bin += level * level / data.len() as f64;
+            }
+        }
+
+        let resolution = self.sampling_rate as f64 / len as f64;
+        let last = power.len() - 1;
+        self.spacing
+            .bands()
+            .iter()
+            .map(|&(low, high)| {
+                let (first, end) = ((low / resolution).ceil(), (high / resolution).floor());
+                let energy = if first <= end {
+                    power[(first as usize).min(last)..=(end as usize).min(last)]
+                        .iter()
+                        .sum::<f64>()
+                        / HANN_ENBW
+                } else {
+                    // Narrower than a bin: the bin it falls in.
+                    power[((low * high).sqrt() / resolution).round().min(last as f64) as usize]
+                };
+                10.0 * energy.max(f64::MIN_POSITIVE).log10()
+            })
+            .collect()
+    }
+
+    /// The bars merged down to at most `columns`, keeping the loudest of
+    /// each group, with the centre frequency of its first band.
+    fn fit(&self, columns: usize) -> Vec<(f64, Bar)> {
+        let bands = self.spacing.bands();
+        let groups = self.bars.len().min(columns.max(1));
+        (0..groups)
+            .filter_map(|group| {
+                let start = group * self.bars.len() / groups;
+                let end = (group + 1) * self.bars.len() / groups;
+                let bars = &self.bars[start..end];
+                let loudest = bars
+                    .iter()
+                    .max_by(|a, b| a.level.total_cmp(&b.level))?
+                    .clone();
+                let cap = bars.iter().map(|bar| bar.cap).fold(0.0, f64::max);
+                let (low, high) = bands[start];
+                Some(((low * high).sqrt(), Bar { cap, ..loudest }))
+            })
+            .collect()
+    }
+}
+
+fn level_color(fraction: f64) -> Color {
+    if fraction > 0.85 {
+        Color::Red
+    } else if fraction > 0.6 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+/// Short frequency label to two significant figures: `63`, `160`, `1k`,
+/// `16k`.
+fn fmt_hz(hz: f64) -> String {
+    let magnitude = 10f64.powf(hz.log10().floor() - 1.0).max(1.0);
+    let hz = (hz / magnitude).round() * magnitude;
+    if hz >= 1000.0 {
+        format!("{}k", (hz / 1000.0).round())
+    } else {
+        format!("{}", hz)
+    }
+}
+
+impl DisplayMode for Analyzer {
+    fn from_args(opts: &crate::cfg::SourceOptions) -> Self {
+        Analyzer {
+            spacing: Spacing::ThirdOctave,
+            band_count: 32,
+            bars: Vec::new(),
+            window: Vec::new(),
+            sampling_rate: opts.sample_rate,
+        }
+    }
+
+    fn mode_str(&self) -> &'static str {
+        "analyzer"
+    }
+
+    fn header(&self, _: &GraphConfig) -> String {
+        format!("live  {}  {}…0 dBFS", self.spacing.describe(), FLOOR_DB)
+    }
+
+    fn axis(&self, _: &GraphConfig, _: Dimension) -> Axis<'_> {
+        Axis::default()
+    }
+
+    fn process(&mut self, cfg: &GraphConfig, data: &Matrix<f64>) -> Vec<DataSet> {
+        if cfg.pause || data.is_empty() || cfg.sampling_rate == 0 {
+            return Vec::new();
+        }
+        self.sampling_rate = cfg.sampling_rate;
+        let levels = self.levels_db(data);
+        if self.bars.len() != levels.len() {
+            self.bars = vec![Bar::default(); levels.len()];
+        }
+        let secs = data[0].len() as f64 / cfg.sampling_rate as f64;
+        for (bar, db) in self.bars.iter_mut().zip(levels) {
+            bar.update(((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0), secs);
+        }
+        Vec::new()
+    }
+
+    fn handle(&mut self, event: Event) {
+        if let Event::Key(key) = event {
+            match key.code {
+                KeyCode::Char('o') => self.set_spacing(match self.spacing {
+                    Spacing::Octave => Spacing::ThirdOctave,
+                    Spacing::ThirdOctave => Spacing::Log(self.band_count),
+                    Spacing::Log(_) => Spacing::Octave,
+                }),
+                KeyCode::PageUp | KeyCode::PageDown => {
+                    self.band_count = match key.code {
+                        KeyCode::PageUp => self.band_count + BANDS_STEP,
+                        _ => self.band_count.saturating_sub(BANDS_STEP),
+                    }
+                    .clamp(BANDS.0, BANDS.1);
+                    self.set_spacing(Spacing::Log(self.band_count));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn render(&self, cfg: &GraphConfig, frame: &mut Frame, area: Rect) -> bool {
+        if area.width == 0 || area.height == 0 {
+            return true;
+        }
+        let labels = area.height >= LABEL_MIN_ROWS;
+        let rows = area.height - labels as u16;
+        let width = area.width as usize;
+        // A gap between bars when there is room for one.
+        let gap = (self.bars.len() * 2 <= width) as usize;
+        let bars = self.fit(if gap == 1 { width.div_ceil(2) } else { width });
+        if bars.is_empty() {
+            return true;
+        }
+        let bar_width = ((width + gap) / bars.len()).saturating_sub(gap).max(1);
+        let eighths = rows as f64 * 8.0;
+
+        let mut lines = Vec::new();
+        for row in (0..rows).rev() {
+            let mut spans = Vec::new();
+            for (_, bar) in &bars {
+                // Eighths of this row the bar and its cap fill.
+                let filled = (bar.level * eighths).round() as i64 - row as i64 * 8;
+                let cap = (bar.cap * eighths).round() as i64 - row as i64 * 8;
+                let color = level_color((row as f64 + 0.5) / rows as f64);
+                let cell = if filled >= 8 {
+                    Span::styled(EIGHTHS[7], Style::default().fg(color))
+                } else if filled > 0 {
+                    Span::styled(EIGHTHS[filled as usize - 1], Style::default().fg(color))
+                } else if (1..=8).contains(&cap) && bar.cap > bar.level + 1.0 / eighths {
+                    Span::styled("▔", Style::default().fg(cfg.labels_color))
+                } else {
+                    Span::raw(" ")
+                };
+                for _ in 0..bar_width {
+                    spans.push(cell.clone());
+                }
+                if gap == 1 {
+                    spans.push(Span::raw(" "));
+                }
+            }
+            lines.push(Line::from(spans));
+        }
+
+        if labels {
+            // Label a band wherever the previous label has room to end.
+            let mut scale = String::new();
+            for (index, (hz, _)) in bars.iter().enumerate() {
+                let at = index * (bar_width + gap);
+                let label = fmt_hz(*hz);
+                if at >= scale.chars().count() + (index > 0) as usize && at + label.len() <= width {
+                    scale.push_str(&" ".repeat(at - scale.chars().count()));
+                    scale.push_str(&label);
+                }
+            }
+            lines.push(Line::from(Span::styled(
+                scale,
+                Style::default().fg(cfg.axis_color),
+            )));
+        }
+
+        frame.render_widget(Paragraph::new(lines), area);
+        true
+    }
+}
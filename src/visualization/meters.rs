@@ -0,0 +1,327 @@
+//! Level meters: per-channel peak and RMS bars with peak hold, momentary
+//! and short-term loudness, and a phase-correlation meter.
+//!
+//! Drawn as text rows rather than a chart, see [`DisplayMode::render`].
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Axis, Paragraph},
+    Frame,
+};
+
+use crate::{input::Matrix, loudness::LoudnessMeter};
+
+use super::{DataSet, Dimension, DisplayMode, GraphConfig};
+
+/// Bottom of the bar scale, in dBFS (and LUFS).
+const FLOOR_DB: f64 = -60.0;
+/// How fast the peak bar falls back, in dB per second.
+const PEAK_FALL_DB_PER_SEC: f64 = 20.0;
+/// How long the peak hold marker stays before it follows the peak again.
+const HOLD_SECS: f64 = 2.0;
+/// Integration time of the RMS and correlation meters.
+const INTEGRATION_SECS: f64 = 0.3;
+/// Bar colours above these levels.
+const WARN_DB: f64 = -18.0;
+const CLIP_DB: f64 = -6.0;
+/// Width of the channel labels and of the readouts right of the bars.
+const LABEL_WIDTH: u16 = 4;
+const READOUT_WIDTH: u16 = 34;
+
+#[derive(Default)]
+struct ChannelMeter {
+    peak_db: f64,
+    /// Mean square of the samples, integrated over [`INTEGRATION_SECS`].
+    mean_square: f64,
+    hold_db: f64,
+    hold_secs: f64,
+}
+
+impl ChannelMeter {
+    fn new() -> Self {
+        Self {
+            peak_db: FLOOR_DB,
+            hold_db: FLOOR_DB,
+            ..Self::default()
+        }
+    }
+
+    fn process(&mut self, samples: &[f64], sample_rate: f64) {
+        let secs = samples.len() as f64 / sample_rate;
+        let coeff = 1.0 - (-1.0 / (INTEGRATION_SECS * sample_rate)).exp();
+        let mut peak = 0.0f64;
+        for sample in samples {
+            peak = peak.max(sample.abs());
+            self.mean_square += (sample * sample - self.mean_square) * coeff;
+        }
+
+        self.peak_db = to_db(peak).max(self.peak_db - PEAK_FALL_DB_PER_SEC * secs);
+        self.hold_secs += secs;
+        if self.peak_db >= self.hold_db || self.hold_secs > HOLD_SECS {
+            self.hold_db = self.peak_db;
+            self.hold_secs = 0.0;
+        }
+    }
+
+    fn rms_db(&self) -> f64 {
+        to_db(self.mean_square.sqrt())
+    }
+}
+
+/// Correlation between two channels: +1 for mono, 0 for unrelated
+/// channels, −1 for one channel the inverse of the other.
+#[derive(Default)]
+struct Correlation {
+    lr: f64,
+    ll: f64,
+    rr: f64,
+}
+
+impl Correlation {
+    fn process(&mut self, left: &[f64], right: &[f64], sample_rate: f64) {
+        let coeff = 1.0 - (-1.0 / (INTEGRATION_SECS * sample_rate)).exp();
+        for (l, r) in left.iter().zip(right) {
+            self.lr += (l * r - self.lr) * coeff;
+            self.ll += (l * l - self.ll) * coeff;
+            self.rr += (r * r - self.rr) * coeff;
+        }
+    }
+
+    /// `None` while either channel is silent.
+    fn value(&self) -> Option<f64> {
+        let power = (self.ll * self.rr).sqrt();
+        (power > 1e-10).then(|| (self.lr / power).clamp(-1.0, 1.0))
+    }
+}
+
+pub struct Meters {
+    channels: Vec<ChannelMeter>,
+    loudness: LoudnessMeter,
+    correlation: Correlation,
+}
+
+fn to_db(level: f64) -> f64 {
+    (20.0 * level.max(f64::MIN_POSITIVE).log10()).max(FLOOR_DB)
+}
+
+/// Readout with one decimal, or a dash when there is nothing to show.
+fn fmt_level(level: Option<f64>) -> String {
+    match level {
+        Some(level) if level > FLOOR_DB => format!("{:6.1}", level),
+        _ => format!("{:>6}", "-"),
+    }
+}
+
+fn level_color(db: f64) -> Color {
+    if db >= CLIP_DB {
+        Color::Red
+    } else if db >= WARN_DB {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}
+
+/// Cell of a `width` wide bar that `db` falls in.
+fn bar_cell(db: f64, width: usize) -> usize {
+    let position = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+    ((position * width as f64).round() as usize).min(width)
+}
+
+/// A level bar: solid up to `solid_db`, shaded up to `shaded_db` and a
+/// marker at `marker_db`, coloured by the level of each cell.
+fn level_bar(
+    width: usize,
+    solid_db: f64,
+    shaded_db: f64,
+    marker_db: Option<f64>,
+) -> Vec<Span<'static>> {
+    let solid = bar_cell(solid_db, width);
+    let shaded = bar_cell(shaded_db, width).max(solid);
+    let marker = marker_db
+        .filter(|db| *db > FLOOR_DB)
+        .map(|db| bar_cell(db, width).saturating_sub(1));
+    (0..width)
+        .map(|cell| {
+            let cell_db = FLOOR_DB - FLOOR_DB * (cell as f64 + 1.0) / width as f64;
+            let color = level_color(cell_db);
+            if Some(cell) == marker && cell >= solid {
+                Span::styled("│", Style::default().fg(color))
+            } else if cell < solid {
+                Span::styled("█", Style::default().fg(color))
+            } else if cell < shaded {
+                Span::styled("▒", Style::default().fg(color))
+            } else {
+                Span::styled("·", Style::default().fg(Color::DarkGray))
+            }
+        })
+        .collect()
+}
+
+/// dB labels under a `width` wide bar.
+fn scale_line(width: usize) -> String {
+    let mut scale = vec![' '; width + 4];
+    for db in (FLOOR_DB as i32..=0).step_by(12) {
+        let label = db.to_string();
+        let end = bar_cell(db as f64, width);
+        let start = end.saturating_sub(label.len());
+        for (slot, ch) in scale[start..].iter_mut().zip(label.chars()) {
+            *slot = ch;
+        }
+    }
+    scale.into_iter().collect()
+}
+
+/// `−1`, `0` and `+1` under the correlation bar.
+fn correlation_scale(width: usize) -> String {
+    let mut scale = vec![' '; width.max(3)];
+    let centre = width / 2;
+    for (at, label) in [(0, "-1"), (centre, "0"), (width.saturating_sub(2), "+1")] {
+        for (slot, ch) in scale[at..].iter_mut().zip(label.chars()) {
+            *slot = ch;
+        }
+    }
+    scale.into_iter().collect()
+}
+
+/// Correlation bar from −1 to +1 with a marker at `value`.
+fn correlation_bar(width: usize, value: Option<f64>) -> Vec<Span<'static>> {
+    let centre = width / 2;
+    let marker = value.map(|value| {
+        let cell = ((value + 1.0) / 2.0 * (width.max(1) - 1) as f64).round() as usize;
+        (
+            cell,
+            if value < 0.0 {
+                Color::Red
+            } else {
+                Color::Green
+            },
+        )
+    });
+    (0..width)
+        .map(|cell| match marker {
+            Some((at, color)) if at == cell => Span::styled("●", Style::default().fg(color)),
+            _ if cell == centre => Span::styled("┼", Style::default().fg(Color::DarkGray)),
+            _ => Span::styled("─", Style::default().fg(Color::DarkGray)),
+        })
+        .collect()
+}
+
+impl DisplayMode for Meters {
+    fn from_args(_opts: &crate::cfg::SourceOptions) -> Self {
+        Meters {
+            channels: Vec::new(),
+            loudness: LoudnessMeter::new(),
+            correlation: Correlation::default(),
+        }
+    }
+
+    fn mode_str(&self) -> &'static str {
+        "meters"
+    }
+
+    fn channel_name(&self, index: usize) -> String {
+        match (self.channels.len(), index) {
+            (1, _) => "L+R".into(),
+            (_, 0) => "L".into(),
+            (_, 1) => "R".into(),
+            _ => format!("{}", index),
+        }
+    }
+
+    fn header(&self, _: &GraphConfig) -> String {
+        format!("live  {}…0 dBFS", FLOOR_DB)
+    }
+
+    fn axis(&self, _: &GraphConfig, _: Dimension) -> Axis<'_> {
+        Axis::default()
+    }
+
+    fn process(&mut self, cfg: &GraphConfig, data: &Matrix<f64>) -> Vec<DataSet> {
+        if cfg.pause || data.is_empty() || cfg.sampling_rate == 0 {
+            return Vec::new();
+        }
+        if self.channels.len() != data.len() {
+            self.channels = data.iter().map(|_| ChannelMeter::new()).collect();
+            self.correlation = Correlation::default();
+        }
+        let rate = cfg.sampling_rate as f64;
+        for (meter, samples) in self.channels.iter_mut().zip(data) {
+            meter.process(samples, rate);
+        }
+        self.loudness.process(data, cfg.sampling_rate);
+        if let [left, right] = data.as_slice() {
+            self.correlation.process(left, right, rate);
+        }
+        Vec::new()
+    }
+
+    fn render(&self, cfg: &GraphConfig, frame: &mut Frame, area: Rect) -> bool {
+        let width = area.width.saturating_sub(LABEL_WIDTH + READOUT_WIDTH) as usize;
+        let label = |text: String| {
+            Span::styled(
+                format!("{:<1$}", text, LABEL_WIDTH as usize),
+                Style::default().fg(cfg.labels_color),
+            )
+        };
+        let readout = |text: String| Span::raw(format!("  {}", text));
+
+        let mut lines = Vec::new();
+        for (index, meter) in self.channels.iter().enumerate() {
+            let mut line = vec![label(self.channel_name(index))];
+            line.extend(level_bar(
+                width,
+                meter.rms_db(),
+                meter.peak_db,
+                Some(meter.hold_db),
+            ));
+            line.push(readout(format!(
+                "pk {} rms {} hold {}",
+                fmt_level(Some(meter.peak_db)),
+                fmt_level(Some(meter.rms_db())),
+                fmt_level(Some(meter.hold_db)),
+            )));
+            lines.push(Line::from(line));
+        }
+        lines.push(Line::from(vec![
+            label(String::new()),
+            Span::styled(scale_line(width), Style::default().fg(cfg.axis_color)),
+        ]));
+        lines.push(Line::default());
+
+        for (name, lufs, title) in [
+            ("M", self.loudness.momentary_lufs(), "LUFS momentary"),
+            ("S", self.loudness.short_term_lufs(), "LUFS short-term"),
+        ] {
+            let level = lufs.unwrap_or(FLOOR_DB);
+            let mut line = vec![label(name.into())];
+            line.extend(level_bar(width, level, level, None));
+            line.push(readout(format!("{} {}", fmt_level(lufs), title)));
+            lines.push(Line::from(line));
+        }
+        lines.push(Line::default());
+
+        let mut line = vec![label("φ".into())];
+        line.extend(correlation_bar(width, self.correlation.value()));
+        line.push(readout(
+            match (self.channels.len(), self.correlation.value()) {
+                (1, _) => "mono".to_string(),
+                (_, Some(value)) => format!("{:+6.2} correlation", value),
+                (_, None) => format!("{:>6} correlation", "-"),
+            },
+        ));
+        lines.push(Line::from(line));
+        lines.push(Line::from(vec![
+            label(String::new()),
+            Span::styled(
+                correlation_scale(width),
+                Style::default().fg(cfg.axis_color),
+            ),
+        ]));
+
+        frame.render_widget(Paragraph::new(lines), area);
+        true
+    }
+}
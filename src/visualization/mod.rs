@@ -1,12 +1,18 @@
+pub mod analyzer;
+pub mod meters;
 pub mod oscilloscope;
+pub mod spectrogram;
 pub mod spectroscope;
+pub mod tuner;
 pub mod vectorscope;
 
 use crossterm::event::Event;
 use ratatui::{
+    layout::Rect,
     style::{Color, Style},
     symbols::Marker,
     widgets::{Axis, Dataset, GraphType},
+    Frame,
 };
 
 use crate::input::Matrix;
@@ -20,7 +26,7 @@ pub enum Dimension {
 pub struct GraphConfig {
     pub pause: bool,
     pub samples: u32,
-    #[allow(dead_code)]
+    /// Sample rate of the frames being shown.
     pub sampling_rate: u32,
     pub scale: f64,
     pub width: u32,
@@ -63,6 +69,11 @@ pub trait DisplayMode {
         vec![]
     }
     fn handle(&mut self, _event: Event) {}
+    /// Draw the mode into `area` itself instead of charting its datasets.
+    /// Returns `false` (the default) to get the chart.
+    fn render(&self, _cfg: &GraphConfig, _frame: &mut Frame, _area: Rect) -> bool {
+        false
+    }
 }
 
 pub struct DataSet {
@@ -103,4 +114,8 @@ impl DataSet {
             color,
         }
     }
+
+    pub fn data(&self) -> &[(f64, f64)] {
+        &self.data
+    }
 }
@@ -0,0 +1,221 @@
+//! Scrolling spectrogram ("waterfall"): one spectrum per frame, newest at
+//! the top, with the level of each frequency shown as colour.
+//!
+//! The chart is drawn from one block-marker dataset per colour step, quietest
+//! first, so where several history rows or frequency columns share a
+//! terminal cell the loudest one wins.
+
+use std::collections::VecDeque;
+
+use crossterm::event::{Event, KeyCode};
+use ratatui::{
+    style::{Color, Style},
+    symbols::Marker,
+    text::Span,
+    widgets::{Axis, GraphType},
+};
+
+use crate::{app::update_value_f, input::Matrix};
+
+use super::{
+    spectroscope::{hann_window, magnitude},
+    DataSet, Dimension, DisplayMode, GraphConfig,
+};
+
+use rustfft::{num_complex::Complex, FftPlanner};
+
+/// Spectra kept on screen, one per frame.
+const HISTORY: usize = 128;
+/// Log-spaced frequency columns between [`LOWEST_HZ`] and Nyquist.
+const COLUMNS: usize = 256;
+const LOWEST_HZ: f64 = 20.0;
+/// Dynamic range bounds and step, in dB.
+const RANGE_DB: (f64, f64) = (20.0, 150.0);
+const RANGE_STEP_DB: f64 = 10.0;
+
+/// Colour ramp from just above the floor to 0 dBFS.
+const RAMP: [Color; 8] = [
+    Color::Rgb(20, 11, 52),
+    Color::Rgb(58, 9, 99),
+    Color::Rgb(101, 21, 110),
+    Color::Rgb(159, 42, 99),
+    Color::Rgb(212, 72, 66),
+    Color::Rgb(245, 125, 21),
+    Color::Rgb(250, 193, 39),
+    Color::Rgb(252, 255, 164),
+];
+
+pub struct Spectrogram {
+    pub sampling_rate: u32,
+    pub buffer_size: u32,
+    /// How far below 0 dBFS the colour ramp reaches.
+    pub range_db: f64,
+    /// Spectra in dBFS, one level per column, newest last.
+    pub history: VecDeque<Vec<f64>>,
+}
+
+impl Spectrogram {
+    /// Centre frequency of every column, in Hz.
+    fn column_hz(&self) -> Vec<f64> {
+        let lowest = LOWEST_HZ.ln();
+        let highest = (self.sampling_rate as f64 / 2.0).max(LOWEST_HZ * 2.0).ln();
+        let step = (highest - lowest) / COLUMNS as f64;
+        (0..COLUMNS)
+            .map(|column| (lowest + (column as f64 + 0.5) * step).exp())
+            .collect()
+    }
+
+    /// Power spectrum of all channels together, reduced to the columns: the
+    /// loudest bin within each column, or the nearest bin where a column is
+    /// narrower than one bin.
+    fn columns_db(&self, data: &Matrix<f64>) -> Vec<f64> {
+        let len = self.buffer_size.max(2) as usize;
+        let fft = FftPlanner::<f64>::new().plan_fft_forward(len);
+        let mut power = vec![0.0; len / 2 + 1];
+        for chan in data {
+            let mut chunk = chan.clone();
+            chunk.resize(len, 0.0);
+            let mut tmp: Vec<Complex<f64>> = hann_window(&chunk)
+                .into_iter()
+                .map(|x| Complex { re: x, im: 0.0 })
+                .collect();
+            fft.process(tmp.as_mut_slice());
+            for (bin, x) in power.iter_mut().zip(&tmp) {
+                // A full-scale sine reads 0 dBFS through the Hann window.
+                let level = magnitude(*x) * 4.0 / len as f64;
+                *bin += level * level / data.len().max(1) as f64;
+            }
+        }
+
+        let resolution = self.sampling_rate as f64 / len as f64;
+        let centres = self.column_hz();
+        let ratio = (centres[1] / centres[0]).sqrt();
+        centres
+            .iter()
+            .map(|&hz| {
+                let low = ((hz / ratio) / resolution).ceil() as usize;
+                let high = ((hz * ratio) / resolution).floor() as usize;
+                let peak = if low <= high {
+                    power[low.min(power.len() - 1)..=high.min(power.len() - 1)]
+                        .iter()
+                        .copied()
+                        .fold(0.0, f64::max)
+                } else {
+                    power[((hz / resolution).round() as usize).min(power.len() - 1)]
+                };
+                10.0 * peak.max(f64::MIN_POSITIVE).log10()
+            })
+            .collect()
+    }
+
+    /// Colour step for a level, `None` at or below the floor.
+    fn step(&self, db: f64) -> Option<usize> {
+        let position = (db + self.range_db) / self.range_db;
+        let step = (position * RAMP.len() as f64).ceil();
+        (step >= 1.0).then(|| (step as usize).min(RAMP.len()) - 1)
+    }
+}
+
+impl DisplayMode for Spectrogram {
+    fn from_args(opts: &crate::cfg::SourceOptions) -> Self {
+        Spectrogram {
+            sampling_rate: opts.sample_rate,
+            buffer_size: opts.buffer,
+            range_db: 90.0,
+            history: VecDeque::with_capacity(HISTORY),
+        }
+    }
+
+    fn mode_str(&self) -> &'static str {
+        "waterfall"
+    }
+
+    fn header(&self, _: &GraphConfig) -> String {
+        format!(
+            "live  {:.0}dB range  {:.1}s",
+            self.range_db,
+            (HISTORY as u32 * self.buffer_size) as f64 / self.sampling_rate as f64
+        )
+    }
+
+    fn axis(&self, cfg: &GraphConfig, dimension: Dimension) -> Axis<'_> {
+        let (name, bounds) = match dimension {
+            Dimension::X => (
+                "frequency -",
+                [
+                    LOWEST_HZ.ln(),
+                    ((cfg.samples as f64 / cfg.width as f64) * 20000.0).ln(),
+                ],
+            ),
+            Dimension::Y => ("| time", [0.0, HISTORY as f64 - 1.0]),
+        };
+        let mut a = Axis::default();
+        if cfg.show_ui {
+            a = a.title(Span::styled(name, Style::default().fg(cfg.labels_color)));
+        }
+        a.style(Style::default().fg(cfg.axis_color)).bounds(bounds)
+    }
+
+    fn process(&mut self, cfg: &GraphConfig, data: &Matrix<f64>) -> Vec<DataSet> {
+        if !cfg.pause && !data.is_empty() {
+            let columns = self.columns_db(data);
+            self.history.push_back(columns);
+            while self.history.len() > HISTORY {
+                self.history.pop_front();
+            }
+        }
+
+        let x: Vec<f64> = self.column_hz().iter().map(|hz| hz.ln()).collect();
+        let mut points = vec![Vec::new(); RAMP.len()];
+        for (age, spectrum) in self.history.iter().rev().enumerate() {
+            let y = (HISTORY - 1 - age) as f64;
+            for (column, &db) in spectrum.iter().enumerate() {
+                if let Some(step) = self.step(db) {
+                    points[step].push((x[column], y));
+                }
+            }
+        }
+
+        points
+            .into_iter()
+            .zip(RAMP)
+            .map(|(data, color)| DataSet::new(None, data, Marker::Block, GraphType::Scatter, color))
+            .collect()
+    }
+
+    fn handle(&mut self, event: Event) {
+        if let Event::Key(key) = event {
+            match key.code {
+                KeyCode::PageUp => update_value_f(
+                    &mut self.range_db,
+                    RANGE_STEP_DB,
+                    1.,
+                    RANGE_DB.0..RANGE_DB.1,
+                ),
+                KeyCode::PageDown => update_value_f(
+                    &mut self.range_db,
+                    -RANGE_STEP_DB,
+                    1.,
+                    RANGE_DB.0..RANGE_DB.1,
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    fn references(&self, cfg: &GraphConfig) -> Vec<DataSet> {
+        // Decade lines, visible wherever the spectrum is below the floor.
+        [100.0f64, 1000.0, 10000.0]
+            .iter()
+            .map(|hz| {
+                DataSet::new(
+                    None,
+                    vec![(hz.ln(), 0.0), (hz.ln(), HISTORY as f64 - 1.0)],
+                    cfg.marker_type,
+                    GraphType::Line,
+                    cfg.axis_color,
+                )
+            })
+            .collect()
+    }
+}
@@ -23,7 +23,7 @@ pub struct Spectroscope {
     pub log_y: bool,
 }
 
-fn magnitude(c: Complex<f64>) -> f64 {
+pub fn magnitude(c: Complex<f64>) -> f64 {
     let squared = (c.re * c.re) + (c.im * c.im);
     squared.sqrt()
 }
@@ -41,6 +41,30 @@ pub fn hann_window(samples: &[f64]) -> Vec<f64> {
     windowed_samples
 }
 
+impl Spectroscope {
+    /// Turn the channel datasets [`DisplayMode::process`] returned into one
+    /// spectrum of `(Hz, dB)` points, averaged over the channels.
+    pub fn levels_db(&self, datasets: &[DataSet]) -> Vec<(f64, f64)> {
+        let Some(first) = datasets.first() else {
+            return Vec::new();
+        };
+        (0..first.data().len())
+            .map(|bin| {
+                let level = datasets
+                    .iter()
+                    .filter_map(|dataset| dataset.data().get(bin))
+                    .map(|&(_, level)| match self.log_y {
+                        true => 20.0 * level / std::f64::consts::LN_10,
+                        false => 20.0 * level.max(f64::MIN_POSITIVE).log10(),
+                    })
+                    .sum::<f64>()
+                    / datasets.len() as f64;
+                (first.data()[bin].0.exp(), level)
+            })
+            .collect()
+    }
+}
+
 impl DisplayMode for Spectroscope {
     fn from_args(opts: &crate::cfg::SourceOptions) -> Self {
         Spectroscope {
@@ -137,9 +161,8 @@ impl DisplayMode for Spectroscope {
             //  - https://cdn-cms.tunein.com/service/Audio/georestricted.enUS.mp3
             //  - https://cdn-cms.tunein.com/service/Audio/notcompatible.enUS.mp3
             // (darkwave radomir [s340893] does this in the UK if you connect via VPN with tunein/pull/1 applied)
-            if chunk.len() < sample_len as usize {
-                chunk.resize(sample_len as usize, 0.0);
-            }
+            // and a bigger one when `--tune` shrank the buffer, which rustfft won't take either
+            chunk.resize(sample_len as usize, 0.0);
             if self.window {
                 chunk = hann_window(chunk.as_slice());
             }
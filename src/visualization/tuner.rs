@@ -0,0 +1,256 @@
+//! Tuner: finds the dominant pitch of the stream and shows the nearest
+//! note, its frequency and how many cents sharp or flat it is, using the
+//! note table in [`crate::music`].
+//!
+//! The pitch is the strongest spectral peak between [`LOWEST_HZ`] and
+//! [`HIGHEST_HZ`], refined between FFT bins by parabolic interpolation.
+//! On a full mix that is usually the bass line or the lead voice.
+
+use std::collections::VecDeque;
+
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Axis, Paragraph},
+    Frame,
+};
+
+use crate::{input::Matrix, music::Note};
+
+use super::{
+    spectroscope::{hann_window, magnitude},
+    DataSet, Dimension, DisplayMode, GraphConfig,
+};
+
+use rustfft::{num_complex::Complex, FftPlanner};
+
+/// Samples per channel the spectrum is taken over: about 5 Hz bins at
+/// 44.1 kHz, fine enough to tell E1 from F1.
+const FFT_LEN: usize = 8192;
+const LOWEST_HZ: f64 = 30.0;
+const HIGHEST_HZ: f64 = 4000.0;
+/// A peak quieter than this isn't a pitch, in dBFS.
+const SILENCE_DB: f64 = -60.0;
+/// How far a peak must stand above the average of the searched range, in
+/// dB, to count as a pitch rather than noise.
+const PROMINENCE_DB: f64 = 15.0;
+/// How long a reading stays up once the pitch is lost.
+const HOLD_SECS: f64 = 0.5;
+/// Smoothing of the reading while it stays on one note, per frame.
+const SMOOTHING: f64 = 0.3;
+/// Cents either side of the needle's centre.
+const CENTS_RANGE: f64 = 50.0;
+/// Widest the needle gets, in cells.
+const NEEDLE_WIDTH: usize = 61;
+
+pub struct Tuner {
+    /// The note `--tune` set the oscilloscope to, shown in the header.
+    target: Option<Note>,
+    /// The last [`FFT_LEN`] samples, channels mixed together.
+    window: VecDeque<f64>,
+    /// Smoothed pitch in Hz, `None` when there is no clear pitch.
+    pitch: Option<f64>,
+    /// Time since the pitch was last found.
+    lost_secs: f64,
+}
+
+impl Tuner {
+    /// The dominant pitch of the window in Hz, if one stands out.
+    fn detect(&self, sample_rate: f64) -> Option<f64> {
+        let fft = FftPlanner::<f64>::new().plan_fft_forward(FFT_LEN);
+        let chunk: Vec<f64> = self.window.iter().copied().collect();
+        let mut tmp: Vec<Complex<f64>> = hann_window(&chunk)
+            .into_iter()
+            .map(|x| Complex { re: x, im: 0.0 })
+            .collect();
+        fft.process(tmp.as_mut_slice());
+
+        let resolution = sample_rate / FFT_LEN as f64;
+        let first = ((LOWEST_HZ / resolution).floor() as usize).max(1);
+        let last = ((HIGHEST_HZ / resolution).ceil() as usize).min(FFT_LEN / 2 - 1);
+        if first >= last {
+            return None;
+        }
+        // A full-scale sine reads 1.0 through the Hann window.
+        let levels: Vec<f64> = tmp[first - 1..=last + 1]
+            .iter()
+            .map(|x| magnitude(*x) * 4.0 / FFT_LEN as f64)
+            .collect();
+        let searched = &levels[1..levels.len() - 1];
+        let (peak, level) = searched
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))?;
+        let average = searched.iter().map(|x| x * x).sum::<f64>() / searched.len() as f64;
+        let level_db = 20.0 * level.max(f64::MIN_POSITIVE).log10();
+        let prominence_db = 10.0 * (level * level / average.max(f64::MIN_POSITIVE)).log10();
+        if level_db < SILENCE_DB || prominence_db < PROMINENCE_DB {
+            return None;
+        }
+
+        // Fit a parabola through the peak and its neighbours (in dB) to
+        // find the true frequency between the bins.
+        let db = |bin: usize| 20.0 * levels[bin].max(f64::MIN_POSITIVE).log10();
+        let (a, b, c) = (db(peak), db(peak + 1), db(peak + 2));
+        let curvature = a - 2.0 * b + c;
+        let offset = if curvature < 0.0 {
+            (0.5 * (a - c) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        Some((first + peak) as f64 * resolution + offset * resolution)
+    }
+}
+
+fn cents_color(cents: f64) -> Color {
+    match cents.abs() {
+        c if c <= 5.0 => Color::Green,
+        c if c <= 15.0 => Color::Yellow,
+        _ => Color::Red,
+    }
+}
+
+/// The needle: a line from −50 to +50 cents with a mark at `cents`.
+fn needle(width: usize, cents: Option<f64>) -> Vec<Span<'static>> {
+    let centre = width / 2;
+    let quarter = width / 4;
+    let mark = cents.map(|cents| {
+        let position = (cents / CENTS_RANGE).clamp(-1.0, 1.0);
+        let cell = (centre as f64 + position * centre as f64).round() as usize;
+        (cell.min(width - 1), cents_color(cents))
+    });
+    (0..width)
+        .map(|cell| match mark {
+            Some((at, color)) if at == cell => Span::styled("▲", Style::default().fg(color)),
+            _ if cell == centre => Span::styled("┼", Style::default().fg(Color::DarkGray)),
+            _ if cell + quarter == centre || cell == centre + quarter => {
+                Span::styled("┴", Style::default().fg(Color::DarkGray))
+            }
+            _ => Span::styled("─", Style::default().fg(Color::DarkGray)),
+        })
+        .collect()
+}
+
+/// `-50`, `-25`, `0`, `+25` and `+50` under the needle's ticks, as wide as
+/// the needle so both stay lined up when centred.
+fn needle_scale(width: usize) -> String {
+    let centre = width / 2;
+    let quarter = width / 4;
+    let mut scale = vec![' '; width];
+    for (at, label) in [
+        (0, "-50"),
+        (centre - quarter, "-25"),
+        (centre, "0"),
+        (centre + quarter, "+25"),
+        (width - 1, "+50"),
+    ] {
+        let start = at
+            .saturating_sub(label.len() / 2)
+            .min(width.saturating_sub(label.len()));
+        for (slot, ch) in scale[start..].iter_mut().zip(label.chars()) {
+            *slot = ch;
+        }
+    }
+    scale.into_iter().collect()
+}
+
+impl DisplayMode for Tuner {
+    fn from_args(opts: &crate::cfg::SourceOptions) -> Self {
+        Tuner {
+            target: opts.tune.as_ref().and_then(|note| note.parse().ok()),
+            window: VecDeque::from(vec![0.0; FFT_LEN]),
+            pitch: None,
+            lost_secs: 0.0,
+        }
+    }
+
+    fn mode_str(&self) -> &'static str {
+        "tuner"
+    }
+
+    fn header(&self, _: &GraphConfig) -> String {
+        match &self.target {
+            Some(note) => format!("live  scope tuned to {} ({:.2} Hz)", note, note.freq()),
+            None => "live".into(),
+        }
+    }
+
+    fn axis(&self, _: &GraphConfig, _: Dimension) -> Axis<'_> {
+        Axis::default()
+    }
+
+    fn process(&mut self, cfg: &GraphConfig, data: &Matrix<f64>) -> Vec<DataSet> {
+        if cfg.pause || data.is_empty() || cfg.sampling_rate == 0 {
+            return Vec::new();
+        }
+        let frames = data.iter().map(Vec::len).min().unwrap_or(0);
+        self.window.extend(
+            (0..frames).map(|n| data.iter().map(|chan| chan[n]).sum::<f64>() / data.len() as f64),
+        );
+        self.window.drain(..self.window.len() - FFT_LEN);
+
+        let sample_rate = cfg.sampling_rate as f64;
+        match self.detect(sample_rate) {
+            Some(pitch) => {
+                self.lost_secs = 0.0;
+                // Glide while it stays within the same semitone, jump
+                // otherwise.
+                self.pitch = Some(match self.pitch {
+                    Some(last) if (1200.0 * (pitch / last).log2()).abs() < 100.0 => {
+                        last + (pitch - last) * SMOOTHING
+                    }
+                    _ => pitch,
+                });
+            }
+            None => {
+                self.lost_secs += frames as f64 / sample_rate;
+                if self.lost_secs > HOLD_SECS {
+                    self.pitch = None;
+                }
+            }
+        }
+        Vec::new()
+    }
+
+    fn render(&self, cfg: &GraphConfig, frame: &mut Frame, area: Rect) -> bool {
+        let width = (area.width as usize).saturating_sub(4).min(NEEDLE_WIDTH) | 1;
+        let reading = self.pitch.and_then(|pitch| {
+            Note::nearest(pitch as f32).map(|(note, cents)| (pitch, note, cents))
+        });
+
+        let mut lines = vec![Line::default()];
+        match &reading {
+            Some((pitch, note, _)) => lines.push(Line::from(vec![
+                Span::styled(
+                    note.to_string(),
+                    Style::default()
+                        .fg(cfg.labels_color)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!("   {:.1} Hz", pitch)),
+            ])),
+            None => lines.push(Line::from(Span::styled(
+                "no clear pitch",
+                Style::default().fg(Color::DarkGray),
+            ))),
+        }
+        lines.push(Line::default());
+        let cents = reading.as_ref().map(|(_, _, cents)| *cents as f64);
+        lines.push(Line::from(needle(width, cents)));
+        lines.push(Line::from(Span::styled(
+            needle_scale(width),
+            Style::default().fg(cfg.axis_color),
+        )));
+        lines.push(Line::default());
+        if let Some(cents) = cents {
+            lines.push(Line::from(Span::styled(
+                format!("{:+} cents", cents.round() as i32),
+                Style::default().fg(cents_color(cents)),
+            )));
+        }
+
+        frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), area);
+        true
+    }
+}
@@ -5,6 +5,7 @@ use ratatui::{
 };
 
 use crate::input::Matrix;
+use crate::stereo::Stereo;
 
 use super::{DataSet, Dimension, DisplayMode, GraphConfig};
 
@@ -25,7 +26,11 @@ impl DisplayMode for Vectorscope {
     }
 
     fn header(&self, _: &GraphConfig) -> String {
-        "live".into()
+        // The scope is fed after the stereo controls; say what they did.
+        match Stereo::global().describe() {
+            stereo if stereo.is_empty() => "live".into(),
+            stereo => format!("live · {}", stereo),
+        }
     }
 
     fn axis(&self, cfg: &GraphConfig, dimension: Dimension) -> Axis<'_> {
@@ -1,13 +1,23 @@
-use async_graphql::{EmptySubscription, Error, Object, Result, Schema, SimpleObject, ID};
+use async_graphql::{Context, EmptySubscription, Error, Object, Result, Schema, SimpleObject, ID};
 
+use crate::alarm::{AlarmStore, DEFAULT_SNOOZE_MINUTES};
 use crate::favorites::{FavoriteStation, FavoritesStore};
+use tunein_cli::api::tunein::v1alpha1::{
+    self, playback_service_client::PlaybackServiceClient, GetDynamicsRequest,
+    GetPlaybackStatsRequest, SetDynamicsRequest,
+};
 use tunein_cli::extract::get_currently_playing;
 use tunein_cli::provider::{radiobrowser::Radiobrowser, tunein::Tunein, Provider};
 
 pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
 
-pub fn build_schema() -> AppSchema {
-    Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish()
+/// gRPC address of the `server` daemon, set with `tunein web --server`.
+pub struct Daemon(pub String);
+
+pub fn build_schema(daemon: Daemon) -> AppSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(daemon)
+        .finish()
 }
 
 #[derive(SimpleObject)]
@@ -50,6 +60,116 @@ impl From<FavoriteStation> for Favorite {
     }
 }
 
+#[derive(SimpleObject)]
+pub struct Alarm {
+    pub id: u32,
+    pub time: String,
+    pub days: Vec<String>,
+    pub station: String,
+    pub provider: String,
+    pub volume: f32,
+    pub ramp_secs: u64,
+    pub fallback: Option<String>,
+    pub enabled: bool,
+    pub snoozed_until: Option<i64>,
+}
+
+impl From<crate::alarm::Alarm> for Alarm {
+    fn from(alarm: crate::alarm::Alarm) -> Self {
+        Self {
+            id: alarm.id,
+            time: alarm.time,
+            days: alarm.days,
+            station: alarm.station,
+            provider: alarm.provider,
+            volume: alarm.volume,
+            ramp_secs: alarm.ramp_secs,
+            fallback: alarm.fallback,
+            enabled: alarm.enabled,
+            snoozed_until: alarm.snoozed_until,
+        }
+    }
+}
+
+/// Stream statistics of the `server` daemon's player.
+#[derive(SimpleObject)]
+pub struct PlaybackStats {
+    /// idle, connecting, buffering, playing or paused.
+    pub phase: String,
+    /// Progress towards the prebuffer threshold in percent, set while
+    /// buffering.
+    pub buffering_percent: Option<u32>,
+    pub station: Option<String>,
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bytes_received: u64,
+    pub throughput_kbps: u32,
+    pub underruns: u64,
+    pub decode_errors: u64,
+    /// Read-ahead buffer fill in percent.
+    pub buffer_fill_percent: u32,
+    /// Decoded layout, e.g. "stereo" or "5.1 → stereo" when downmixed.
+    pub channel_layout: String,
+}
+
+impl From<v1alpha1::PlaybackStats> for PlaybackStats {
+    fn from(stats: v1alpha1::PlaybackStats) -> Self {
+        Self {
+            phase: stats.phase,
+            buffering_percent: stats.buffering_percent,
+            station: stats.station,
+            codec: stats.codec,
+            sample_rate: stats.sample_rate,
+            channels: stats.channels,
+            bytes_received: stats.bytes_received,
+            throughput_kbps: stats.throughput_kbps,
+            underruns: stats.underruns,
+            decode_errors: stats.decode_errors,
+            buffer_fill_percent: stats.buffer_fill_percent,
+            channel_layout: stats.channel_layout,
+        }
+    }
+}
+
+/// Night mode of the `server` daemon's player: a compressor followed by a
+/// brick-wall limiter, after the EQ.
+#[derive(SimpleObject)]
+pub struct Dynamics {
+    pub night_mode: bool,
+    /// Compressor threshold in dBFS.
+    pub threshold_db: f32,
+    /// Compressor ratio, e.g. 4 = 4:1.
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Limiter ceiling in dBFS.
+    pub ceiling_db: f32,
+}
+
+impl From<v1alpha1::Dynamics> for Dynamics {
+    fn from(dynamics: v1alpha1::Dynamics) -> Self {
+        Self {
+            night_mode: dynamics.night_mode,
+            threshold_db: dynamics.threshold_db,
+            ratio: dynamics.ratio,
+            attack_ms: dynamics.attack_ms,
+            release_ms: dynamics.release_ms,
+            ceiling_db: dynamics.ceiling_db,
+        }
+    }
+}
+
+/// Connect to the configured `server` daemon.
+async fn daemon_client(
+    ctx: &Context<'_>,
+) -> Result<PlaybackServiceClient<tonic::transport::Channel>> {
+    let Daemon(server) = ctx.data::<Daemon>()?;
+    PlaybackServiceClient::connect(server.clone())
+        .await
+        .map_err(|e| Error::new(format!("failed to reach the server at {}: {}", server, e)))
+}
+
 async fn resolve_provider(name: Option<String>) -> Result<Box<dyn Provider + Send + Sync>> {
     match name.as_deref() {
         Some("tunein") | None => Ok(Box::new(Tunein::new())),
@@ -124,6 +244,33 @@ impl QueryRoot {
         let store = FavoritesStore::load().map_err(|e| Error::new(e.to_string()))?;
         Ok(store.all().iter().cloned().map(Favorite::from).collect())
     }
+
+    /// Stream statistics of the `server` daemon's player, fetched over
+    /// gRPC.
+    async fn playback_stats(&self, ctx: &Context<'_>) -> Result<PlaybackStats> {
+        let mut client = daemon_client(ctx).await?;
+        let response = client
+            .get_playback_stats(GetPlaybackStatsRequest {})
+            .await
+            .map_err(|e| Error::new(e.message().to_string()))?;
+        Ok(response.into_inner().stats.unwrap_or_default().into())
+    }
+
+    /// Night mode settings of the `server` daemon's player.
+    async fn dynamics(&self, ctx: &Context<'_>) -> Result<Dynamics> {
+        let mut client = daemon_client(ctx).await?;
+        let response = client
+            .get_dynamics(GetDynamicsRequest {})
+            .await
+            .map_err(|e| Error::new(e.message().to_string()))?;
+        Ok(response.into_inner().dynamics.unwrap_or_default().into())
+    }
+
+    /// Alarms rung by the `server` daemon (alarms.json).
+    async fn alarms(&self) -> Result<Vec<Alarm>> {
+        let store = AlarmStore::load().map_err(|e| Error::new(e.to_string()))?;
+        Ok(store.all().iter().cloned().map(Alarm::from).collect())
+    }
 }
 
 pub struct MutationRoot;
@@ -159,4 +306,45 @@ impl MutationRoot {
             .map_err(|e| Error::new(e.to_string()))?;
         Ok(existed)
     }
+
+    /// Snooze an alarm; the daemon silences it and rings again afterwards.
+    async fn snooze_alarm(&self, id: u32, minutes: Option<u32>) -> Result<Alarm> {
+        let mut store = AlarmStore::load().map_err(|e| Error::new(e.to_string()))?;
+        let alarm = store
+            .snooze(
+                id,
+                minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES),
+                chrono::Utc::now().timestamp(),
+            )
+            .map_err(|e| Error::new(e.to_string()))?;
+        Ok(alarm.into())
+    }
+
+    /// Switch night mode or tune its compressor on the `server` daemon.
+    /// Omitted arguments keep their current value.
+    #[allow(clippy::too_many_arguments)]
+    async fn set_dynamics(
+        &self,
+        ctx: &Context<'_>,
+        night_mode: Option<bool>,
+        threshold_db: Option<f32>,
+        ratio: Option<f32>,
+        attack_ms: Option<f32>,
+        release_ms: Option<f32>,
+        ceiling_db: Option<f32>,
+    ) -> Result<Dynamics> {
+        let mut client = daemon_client(ctx).await?;
+        let response = client
+            .set_dynamics(SetDynamicsRequest {
+                night_mode,
+                threshold_db,
+                ratio,
+                attack_ms,
+                release_ms,
+                ceiling_db,
+            })
+            .await
+            .map_err(|e| Error::new(e.message().to_string()))?;
+        Ok(response.into_inner().dynamics.unwrap_or_default().into())
+    }
 }
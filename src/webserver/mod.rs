@@ -9,7 +9,7 @@ use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
 use owo_colors::OwoColorize;
 use rust_embed::RustEmbed;
 
-use self::graphql::{build_schema, AppSchema};
+use self::graphql::{build_schema, AppSchema, Daemon};
 
 pub mod graphql;
 
@@ -60,8 +60,8 @@ async fn spa(req: HttpRequest) -> HttpResponse {
     serve_embedded(path)
 }
 
-pub async fn exec(port: u16) -> Result<(), Error> {
-    let schema = build_schema();
+pub async fn exec(port: u16, daemon: String) -> Result<(), Error> {
+    let schema = build_schema(Daemon(daemon));
     println!(
         "{}",
         r#"